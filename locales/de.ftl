@@ -0,0 +1,150 @@
+# Deutsche Meldungen.
+
+summary-packages = { $count } Pakete
+
+duration-seconds = { $s } s
+duration-minutes = { $m } min
+duration-hours = { $h } h { $m } min
+duration-days = { $d } T { $h } h
+
+reboot-marker = Neustart { $date }
+reboot-after = { $duration } nach der vorigen Änderung
+reboot-before = { $duration } vor der nächsten
+
+doctor-ok = keine Probleme gefunden
+severity-info = INFO
+severity-warning = WARN
+severity-problem = FEHLER
+doctor-fix = Abhilfe:
+doctor-partial-upgrade-action = vollständig aktualisieren mit `pacman -Syu`
+doctor-modules-removed = die Module des laufenden Kernels { $release } wurden bei einer Aktualisierung entfernt
+doctor-modules-removed-action = vor dem Laden neuer Module (USB-Laufwerke, Dateisysteme, ...) neu starten
+doctor-reboot = { $packages } seit dem letzten Start geändert ({ $date })
+doctor-reboot-action = neu starten, um die aktualisierten Versionen zu verwenden
+doctor-retired-repo = pacman.conf aktiviert noch das eingestellte Repository [{ $repo }]
+doctor-retired-repo-action = den Abschnitt [{ $repo }] aus /etc/pacman.conf entfernen
+doctor-replaced = { $package } wurde durch { $replacement } ersetzt
+doctor-replaced-action = { $name } installieren mit `pacman -S { $name }`
+doctor-dropped = { $package } ist in keinem eingerichteten Repository mehr
+doctor-dropped-action = einen Ersatz suchen, es aus dem AUR installieren oder entfernen
+doctor-pacnew = { $count } nicht zusammengeführte .pacnew/.pacsave-Datei(en): { $files }
+doctor-pacnew-more = und { $count } weitere
+doctor-pacnew-action = mit `pacdiff` (pacman-contrib) zusammenführen
+doctor-transaction-ended = Transaktion #{ $id } am { $date } { $outcome }
+doctor-hooks-failed = Transaktion #{ $id } am { $date }: { $hooks } fehlgeschlagen
+doctor-transaction-action = ansehen mit `pkglist transactions show { $id }`
+doctor-orphan = { $package } ist eine verwaiste Abhängigkeit
+doctor-orphan-came-with = { $package } ist eine verwaiste Abhängigkeit; es kam mit { $packages }
+doctor-orphan-removed = { $package } (entfernt { $date })
+doctor-orphan-action = entfernen mit `pacman -Rns { $package }` oder behalten mit `pacman -D --asexplicit { $package }`
+doctor-ignored = { $package } { $version } wird durch IgnorePkg/IgnoreGroup { $held }
+doctor-ignored-action = aktualisieren mit `pacman -S { $package }` oder aus IgnorePkg/IgnoreGroup in /etc/pacman.conf streichen
+doctor-holdback = { $package } { $version }: { $pending }
+doctor-holdback-action = mit `pacman -S { $package }` nachsehen, was es blockiert, oder IgnorePkg in /etc/pacman.conf prüfen
+doctor-reason-explicit = explizit
+doctor-reason-dependency = als Abhängigkeit
+doctor-reason-changed = { $package } wurde am { $date } { $initial } installiert, jetzt { $current } ({ $how })
+doctor-reason-marked = markiert am { $date }
+doctor-reason-outside-log = außerhalb des Logs geändert
+doctor-sandbox = ohne externe Befehle ausgeführt, daher wurden ausstehende Aktualisierungen, Dateien und die Aktualität der Spiegel nicht geprüft
+doctor-sandbox-action = pacman ausführbar machen oder --sandbox weglassen, um alle Prüfungen auszuführen
+
+mirrors-db-stale = Paketdatenbanken zuletzt vor { $age } synchronisiert
+mirrors-upstream-stale = Spiegel { $mirror } seit { $age } nicht mit dem Ursprung abgeglichen
+mirrors-db-stale-action = synchronisieren und aktualisieren mit `pacman -Syu`
+mirrors-upstream-stale-action = aktuellere Spiegel in /etc/pacman.d/mirrorlist eintragen (z. B. mit reflector)
+risk-partial-upgrade = Risiko einer Teilaktualisierung: { $warning }
+risk-unsynced = { $count } Paket(e) nach dem Synchronisieren der Paketlisten ({ $synced }) ohne vollständige Aktualisierung geändert (letztes -Syu: { $last })
+risk-never = nie
+risk-coupled = { $a } ({ $date_a }) und { $b } ({ $date_b }) wurden zuletzt getrennt aktualisiert
+holdback-pending = Aktualisierung auf { $version } ausstehend, seit { $duration } unverändert
+ignored-held-back = zurückgehalten ({ $version } verfügbar)
+ignored-held-back-for = seit { $duration } zurückgehalten ({ $version } verfügbar)
+
+fleet-no-hosts = { $file }: keine Hosts aufgeführt
+fleet-ssh-exited = ssh beendet mit { $status }
+fleet-hosts = Hosts
+fleet-host-packages = { $count } Pakete
+fleet-partial = nur auf manchen Hosts
+fleet-missing-on = fehlt auf { $hosts }
+fleet-divergent = abweichende Versionen
+
+mark-explicit = explizit
+mark-dependency = Abhängigkeit
+mark-to = zu
+mark-not-installed = { $package } ist nicht installiert, übersprungen
+mark-unchanged = { $count } Paket(e) bereits { $reason }
+mark-prompt = { $count } Paket(e) als { $reason } markieren?
+adopt-ran = ausgeführt: { $programs }
+adopt-provides = stellt { $programs } bereit
+adopt-none = keine als Abhängigkeit installierten Programme im Shell-Verlauf gefunden
+adopt-prompt-one = { $package } ({ $note }) als explizit installiert markieren?
+adopt-prompt = { $count } Paket(e) als explizit installiert markieren?
+
+report-title = pkglist-Bericht für { $month }
+report-generated = Erstellt am { $date } aus dem pacman-Log
+report-transactions = Transaktionen
+report-failed = fehlgeschlagen
+report-installed = installiert
+report-upgraded = aktualisiert
+report-downgraded = herabgestuft
+report-removed = entfernt
+report-size-added = hinzugekommene Größe
+report-changes-per-day = Änderungen pro Tag
+report-packages = { $count } Paket(e)
+report-big-upgrades = große Aktualisierungen
+report-new-packages = neue Pakete
+report-removals = Entfernungen
+report-size = Größe
+report-size-summary = { $size } durch { $count } noch installierte(s) neue(s) Paket(e) hinzugekommen
+report-started = begonnen
+report-outcome = Ergebnis
+report-package-count = Pakete
+report-duration = Dauer
+report-command = Befehl
+report-date = Datum
+report-package = Paket
+report-version = Version
+report-installed-size = installierte Größe
+
+transactions-completed = abgeschlossen
+transactions-failed = fehlgeschlagen
+transactions-interrupted = abgebrochen
+transactions-unfinished = unvollendet
+transactions-finished = beendet { $date }
+transactions-hooks = Hooks:
+transactions-messages = Meldungen:
+transactions-hook-failed = (fehlgeschlagen)
+transactions-not-found = keine Transaktion #{ $id } (das Log enthält { $count } Transaktionen)
+
+stats-upgrades = { $count } Upgrade-Transaktionen :: durchschnittlich { $average } :: längste { $longest } (#{ $id })
+stats-explicit = { $count } explizit installierte Pakete
+stats-average-age = Durchschnittsalter :: { $age } seit der ersten Installation, über { $count } Pakete
+stats-busiest-day = aktivster Tag :: { $date } :: { $count } Ereignisse
+stats-most-upgraded = am häufigsten aktualisiert :: { $packages }
+stats-per-month = pro Monat:
+stats-growth-none = noch kein Größenverlauf; Größen werden erfasst, sobald der Verlaufscache neue Logeinträge übernimmt
+stats-growth-total = installierte Größe :: { $from } am { $date } -> { $to } jetzt ({ $change })
+stats-growth-packages = größte Änderungen seit { $date }:
+stats-jumps = { $count } Aktualisierungen nach Versionssprung
+stats-jumps-none = keine Aktualisierungen in diesem Zeitraum protokolliert
+stats-jumps-major = Hauptversionssprünge:
+
+group-installed = Installiert ({ $count })
+group-upgraded = Aktualisiert ({ $count })
+group-removed = Entfernt ({ $count })
+group-downgraded = Herabgestuft ({ $count })
+group-reinstalled = Neu installiert ({ $count })
+listing-no-previous-run = noch kein früherer Lauf aufgezeichnet
+notify-changed = pkglist: { $count } Paket(e) geändert
+watchlist-changed = pkglist: { $count } beobachtete(s) Paket(e) geändert
+diff-baseline = { $count } Pakete aufgezeichnet; der nächste Vergleich zeigt die Änderungen ab jetzt
+diff-none = keine Änderungen seit { $date }
+diff-stock-none = nichts über die Basis { $baseline } hinaus installiert
+diff-stock-summary = { $added } über die Basis { $baseline } hinaus installiert, { $missing } ihrer Pakete fehlen
+diff-summary = { $installed } installiert, { $upgraded } aktualisiert, { $removed } entfernt seit { $date }
+
+error-not-installed = Paket '{ $package }' ist nicht installiert
+error-group-not-found = Gruppe '{ $group }' nicht gefunden
+error-no-history = keine Protokolleinträge für '{ $package }'
+error-did-you-mean = meinten Sie { $names }?
@@ -0,0 +1,150 @@
+# English messages; the fallback for every other catalog.
+
+summary-packages = { $count } packages
+
+duration-seconds = { $s }s
+duration-minutes = { $m }m
+duration-hours = { $h }h { $m }m
+duration-days = { $d }d { $h }h
+
+reboot-marker = reboot { $date }
+reboot-after = { $duration } after the previous change
+reboot-before = { $duration } before the next
+
+doctor-ok = no problems found
+severity-info = INFO
+severity-warning = WARN
+severity-problem = FAIL
+doctor-fix = fix:
+doctor-partial-upgrade-action = run a full upgrade with `pacman -Syu`
+doctor-modules-removed = the modules of the running kernel { $release } were removed by an upgrade
+doctor-modules-removed-action = reboot before loading new modules (USB drives, filesystems, ...)
+doctor-reboot = { $packages } changed since the last boot ({ $date })
+doctor-reboot-action = reboot to run the upgraded versions
+doctor-retired-repo = pacman.conf still enables the retired [{ $repo }] repository
+doctor-retired-repo-action = remove the [{ $repo }] section from /etc/pacman.conf
+doctor-replaced = { $package } has been replaced by { $replacement }
+doctor-replaced-action = install { $name } with `pacman -S { $name }`
+doctor-dropped = { $package } is no longer in any configured repository
+doctor-dropped-action = find a replacement, install it from the AUR, or remove it
+doctor-pacnew = { $count } unmerged .pacnew/.pacsave file(s): { $files }
+doctor-pacnew-more = and { $count } more
+doctor-pacnew-action = merge them with `pacdiff` (pacman-contrib)
+doctor-transaction-ended = transaction #{ $id } on { $date } { $outcome }
+doctor-hooks-failed = transaction #{ $id } on { $date }: { $hooks } failed
+doctor-transaction-action = inspect it with `pkglist transactions show { $id }`
+doctor-orphan = { $package } is an orphaned dependency
+doctor-orphan-came-with = { $package } is an orphaned dependency; it came in with { $packages }
+doctor-orphan-removed = { $package } (removed { $date })
+doctor-orphan-action = remove it with `pacman -Rns { $package }`, or keep it with `pacman -D --asexplicit { $package }`
+doctor-ignored = { $package } { $version } is { $held } by IgnorePkg/IgnoreGroup
+doctor-ignored-action = upgrade it with `pacman -S { $package }` or drop it from IgnorePkg/IgnoreGroup in /etc/pacman.conf
+doctor-holdback = { $package } { $version }: { $pending }
+doctor-holdback-action = see what blocks it with `pacman -S { $package }`, or check IgnorePkg in /etc/pacman.conf
+doctor-reason-explicit = explicitly
+doctor-reason-dependency = as a dependency
+doctor-reason-changed = { $package } was installed { $initial } on { $date }, now { $current } ({ $how })
+doctor-reason-marked = marked on { $date }
+doctor-reason-outside-log = changed outside the log
+doctor-sandbox = running without external commands, so pending updates, file checks and mirror freshness were not checked
+doctor-sandbox-action = make pacman executable, or drop --sandbox, for the full set of checks
+
+mirrors-db-stale = package databases were last synced { $age } ago
+mirrors-upstream-stale = mirror { $mirror } has not synced with upstream for { $age }
+mirrors-db-stale-action = sync and upgrade with `pacman -Syu`
+mirrors-upstream-stale-action = pick fresher mirrors in /etc/pacman.d/mirrorlist (e.g. with reflector)
+risk-partial-upgrade = partial upgrade risk: { $warning }
+risk-unsynced = { $count } package(s) changed after the package lists were synced ({ $synced }) without a full upgrade (last -Syu: { $last })
+risk-never = never
+risk-coupled = { $a } ({ $date_a }) and { $b } ({ $date_b }) were last upgraded separately
+holdback-pending = update to { $version } pending, unchanged for { $duration }
+ignored-held-back = held back ({ $version } available)
+ignored-held-back-for = held back for { $duration } ({ $version } available)
+
+fleet-no-hosts = { $file }: no hosts listed
+fleet-ssh-exited = ssh exited with { $status }
+fleet-hosts = hosts
+fleet-host-packages = { $count } packages
+fleet-partial = on some hosts only
+fleet-missing-on = missing on { $hosts }
+fleet-divergent = divergent versions
+
+mark-explicit = explicit
+mark-dependency = dependency
+mark-to = to
+mark-not-installed = { $package } is not installed, skipping
+mark-unchanged = { $count } package(s) already { $reason }
+mark-prompt = Mark { $count } package(s) as { $reason }?
+adopt-ran = ran { $programs }
+adopt-provides = provides { $programs }
+adopt-none = no dependency-installed programs found in the shell history
+adopt-prompt-one = Mark { $package } ({ $note }) as explicitly installed?
+adopt-prompt = Mark { $count } package(s) as explicitly installed?
+
+report-title = pkglist report for { $month }
+report-generated = Generated { $date } from the pacman log
+report-transactions = transactions
+report-failed = failed
+report-installed = installed
+report-upgraded = upgraded
+report-downgraded = downgraded
+report-removed = removed
+report-size-added = size added
+report-changes-per-day = changes per day
+report-packages = { $count } package(s)
+report-big-upgrades = big upgrades
+report-new-packages = new packages
+report-removals = removals
+report-size = size
+report-size-summary = { $size } added by { $count } new package(s) still installed
+report-started = started
+report-outcome = outcome
+report-package-count = packages
+report-duration = duration
+report-command = command
+report-date = date
+report-package = package
+report-version = version
+report-installed-size = installed size
+
+transactions-completed = completed
+transactions-failed = failed
+transactions-interrupted = interrupted
+transactions-unfinished = unfinished
+transactions-finished = finished { $date }
+transactions-hooks = hooks:
+transactions-messages = messages:
+transactions-hook-failed = (failed)
+transactions-not-found = no transaction #{ $id } (the log has { $count } transactions)
+
+stats-upgrades = { $count } upgrade transactions :: average { $average } :: longest { $longest } (#{ $id })
+stats-explicit = { $count } explicitly installed packages
+stats-average-age = average age :: { $age } since first install, over { $count } packages
+stats-busiest-day = busiest day :: { $date } :: { $count } events
+stats-most-upgraded = most upgraded :: { $packages }
+stats-per-month = per month:
+stats-growth-none = no size history yet; sizes are recorded whenever the history cache takes in new log entries
+stats-growth-total = installed size :: { $from } on { $date } -> { $to } now ({ $change })
+stats-growth-packages = largest changes since { $date }:
+stats-jumps = { $count } upgrades by version jump
+stats-jumps-none = no upgrades logged in this period
+stats-jumps-major = major upgrades:
+
+group-installed = Installed ({ $count })
+group-upgraded = Upgraded ({ $count })
+group-removed = Removed ({ $count })
+group-downgraded = Downgraded ({ $count })
+group-reinstalled = Reinstalled ({ $count })
+listing-no-previous-run = no previous run recorded yet
+notify-changed = pkglist: { $count } package(s) changed
+watchlist-changed = pkglist: { $count } watched package(s) changed
+diff-baseline = recorded { $count } packages; the next diff shows what changed since now
+diff-none = no changes since { $date }
+diff-stock-none = nothing installed beyond the { $baseline } baseline
+diff-stock-summary = { $added } installed beyond the { $baseline } baseline, { $missing } of its packages missing
+diff-summary = { $installed } installed, { $upgraded } upgraded, { $removed } removed since { $date }
+
+error-not-installed = package '{ $package }' is not installed
+error-group-not-found = group '{ $group }' not found
+error-no-history = no log entries for '{ $package }'
+error-did-you-mean = did you mean { $names }?
@@ -0,0 +1,134 @@
+//! pkglist's own audit log: every file written and every command run with
+//! side effects, appended as JSON lines so automated runs can be traced.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::OnceLock;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::dirs;
+use crate::history;
+
+const LOG_FILE: &str = "actions.jsonl";
+
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// One side effect and how it went.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Action {
+    pub date: String,
+    /// The pkglist command line that performed it, with its process id to
+    /// tell apart runs started at the same second.
+    pub invocation: String,
+    pub pid: u32,
+    /// `write` or `run`.
+    pub kind: String,
+    /// The file written or the command run.
+    pub target: String,
+    /// `ok`, or what went wrong.
+    pub result: String,
+}
+
+/// In the data directory, `~/.local/share/pkglist/actions.jsonl`.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(LOG_FILE))
+}
+
+/// Turns recording on; until then, as in library use, nothing is logged.
+pub fn set_path(path: PathBuf) {
+    let _ = LOG_PATH.set(path);
+}
+
+fn invocation() -> String {
+    env::args().collect::<Vec<_>>().join(" ")
+}
+
+/// Appends one action. Failing to log never fails the action itself, so
+/// errors are only reported on stderr.
+pub fn record(kind: &str, target: &str, result: &io::Result<()>) {
+    let Some(path) = LOG_PATH.get() else {
+        return;
+    };
+    let action = Action {
+        date: history::format_log_date(&Local::now().fixed_offset()),
+        invocation: invocation(),
+        pid: process::id(),
+        kind: kind.to_string(),
+        target: target.to_string(),
+        result: match result {
+            Ok(()) => "ok".to_string(),
+            Err(e) => e.to_string(),
+        },
+    };
+    if let Err(e) = append(path, &action) {
+        eprintln!("pkglist: {}: {e}", path.display());
+    }
+}
+
+fn append(path: &Path, action: &Action) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut line = serde_json::to_vec(action)?;
+    line.push(b'\n');
+    // One write per line, so concurrent runs interleave whole lines.
+    File::options()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(&line)
+}
+
+/// Every action in the log at `path`, oldest first; lines that don't parse
+/// are skipped.
+pub fn read(path: &Path) -> io::Result<Vec<Action>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut actions = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(action) = serde_json::from_str(&line?) {
+            actions.push(action);
+        }
+    }
+    Ok(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_round_trip() {
+        let dir = env::temp_dir().join(format!("pkglist-actions-{}", process::id()));
+        let path = dir.join(LOG_FILE);
+        let action = Action {
+            date: "2024-01-15T14:30:47+0100".to_string(),
+            invocation: "pkglist notify".to_string(),
+            pid: 42,
+            kind: "run".to_string(),
+            target: "notify-send --app-name=pkglist".to_string(),
+            result: "ok".to_string(),
+        };
+        assert!(read(&path).unwrap().is_empty());
+        append(&path, &action).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+        append(&path, &action).unwrap();
+        assert_eq!(read(&path).unwrap(), [action.clone(), action]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+//! The apt/dpkg backend for Debian-based systems: `/var/log/dpkg.log` for
+//! the history, `dpkg-query` for the installed packages and apt's
+//! `extended_states` for which of them were pulled in automatically.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{Local, NaiveDateTime, TimeZone};
+
+use crate::backend::Backend;
+use crate::exec;
+use crate::history;
+use crate::pacman::{self, InfoBlock, InstallScope};
+use crate::size;
+
+pub const DPKG_LOG: &str = "/var/log/dpkg.log";
+pub const DPKG_STATUS: &str = "/var/lib/dpkg/status";
+pub const EXTENDED_STATES: &str = "/var/lib/apt/extended_states";
+const DPKG_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// The `status` fields read, in the order `QUERY_FORMAT` prints them.
+const FIELDS: &[&str] = &[
+    "Package",
+    "Status",
+    "Version",
+    "Installed-Size",
+    "Description",
+    "Homepage",
+    "Depends",
+    "Provides",
+];
+const QUERY_FORMAT: &str = "${Package}\\t${db:Status-Status}\\t${Version}\\t${Installed-Size}\\t\
+                            ${binary:Summary}\\t${Homepage}\\t${Depends}\\t${Provides}\\n";
+
+/// A paragraph of a deb822 file like `status`: field → value, with
+/// continuation lines left out.
+pub type Paragraph = BTreeMap<String, String>;
+
+pub fn parse_paragraphs(text: &str) -> Vec<Paragraph> {
+    text.split("\n\n")
+        .map(|block| {
+            block
+                .lines()
+                .filter(|line| !line.starts_with([' ', '\t']))
+                .filter_map(|line| line.split_once(':'))
+                .map(|(key, value)| (key.to_string(), value.trim().to_string()))
+                .collect::<Paragraph>()
+        })
+        .filter(|paragraph| paragraph.contains_key("Package"))
+        .collect()
+}
+
+// Both the `status` file's `install ok installed` and dpkg-query's
+// `${db:Status-Status}` end in the package's current state.
+fn is_installed(paragraph: &Paragraph) -> bool {
+    paragraph
+        .get("Status")
+        .is_some_and(|status| status.rsplit(' ').next() == Some("installed"))
+}
+
+/// `dpkg-query` output in `QUERY_FORMAT`, as the installed packages'
+/// paragraphs.
+pub fn parse_query(text: &str) -> Vec<Paragraph> {
+    text.lines()
+        .map(|line| {
+            FIELDS
+                .iter()
+                .zip(line.split('\t'))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<Paragraph>()
+        })
+        .filter(is_installed)
+        .collect()
+}
+
+/// The installed packages' paragraphs in a `status` file, as sandbox mode
+/// reads them.
+pub fn parse_status(text: &str) -> Vec<Paragraph> {
+    parse_paragraphs(text)
+        .into_iter()
+        .filter(is_installed)
+        .collect()
+}
+
+/// The packages `extended_states` marks `Auto-Installed: 1`.
+pub fn parse_auto_installed(text: &str) -> HashSet<String> {
+    parse_paragraphs(text)
+        .into_iter()
+        .filter(|paragraph| paragraph.get("Auto-Installed").map(String::as_str) == Some("1"))
+        .map(|paragraph| paragraph["Package"].clone())
+        .collect()
+}
+
+// `libc6 (>= 2.34), libtinfo6 | libncurses6` → `libc6 libtinfo6`: the
+// package names, first of each set of alternatives.
+fn relation_names(value: &str) -> String {
+    value
+        .split(',')
+        .filter_map(|relation| relation.split('|').next()?.split_whitespace().next())
+        .map(|name| name.split_once(':').map_or(name, |(name, _)| name))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+/// A package's paragraph as the block `pacman -Qi` would print, so the
+/// columns and queries built on the local database work unchanged.
+pub fn info_block(paragraph: &Paragraph, auto: &HashSet<String>) -> InfoBlock {
+    let get = |key: &str| paragraph.get(key).cloned().unwrap_or_default();
+    let name = get("Package");
+    let reason = if auto.contains(&name) {
+        "Installed as a dependency for another package"
+    } else {
+        "Explicitly installed"
+    };
+    let size = paragraph
+        .get("Installed-Size")
+        .and_then(|kib| kib.parse::<u64>().ok())
+        .map(|kib| size::format_size(kib * 1024))
+        .unwrap_or_default();
+    InfoBlock::from([
+        ("Name".to_string(), name),
+        ("Version".to_string(), get("Version")),
+        ("Description".to_string(), get("Description")),
+        ("URL".to_string(), get("Homepage")),
+        ("Depends On".to_string(), relation_names(&get("Depends"))),
+        ("Provides".to_string(), relation_names(&get("Provides"))),
+        ("Installed Size".to_string(), size),
+        ("Install Reason".to_string(), reason.to_string()),
+    ])
+}
+
+fn installed() -> Vec<Paragraph> {
+    if exec::is_sandboxed() {
+        return fs::read_to_string(DPKG_STATUS)
+            .map(|text| parse_status(&text))
+            .unwrap_or_default();
+    }
+    pacman::run("dpkg-query", &["-W", "-f", QUERY_FORMAT])
+        .map(|text| parse_query(&text))
+        .unwrap_or_default()
+}
+
+fn auto_installed() -> HashSet<String> {
+    fs::read_to_string(EXTENDED_STATES)
+        .map(|text| parse_auto_installed(&text))
+        .unwrap_or_default()
+}
+
+// `vim:amd64` → `vim`; multiarch copies of a package share its history.
+fn strip_arch(package: &str) -> &str {
+    package.split_once(':').map_or(package, |(name, _)| name)
+}
+
+/// Rewrites `dpkg.log` action lines into `pacman.log` ones:
+///
+/// ```text
+/// 2024-01-15 14:30:47 upgrade vim:amd64 2:9.1.0-1 2:9.1.0-2
+/// [2024-01-15T14:30:47+0100] [ALPM] upgraded vim (2:9.1.0-1 -> 2:9.1.0-2)
+/// ```
+///
+/// Status and conffile lines are dropped, as are purges, which follow the
+/// removal already logged.
+pub fn normalize_dpkg_log(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for line in String::from_utf8_lossy(raw).lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [day, time, action, package, old, new] = fields[..] else {
+            continue;
+        };
+        let Some(date) = NaiveDateTime::parse_from_str(&format!("{day} {time}"), DPKG_DATE_FORMAT)
+            .ok()
+            .and_then(|date| Local.from_local_datetime(&date).earliest())
+        else {
+            continue;
+        };
+        let (verb, versions) = match action {
+            "install" if old == "<none>" => ("installed", new.to_string()),
+            "install" | "upgrade" if old == new => ("reinstalled", new.to_string()),
+            "install" | "upgrade" => ("upgraded", format!("{old} -> {new}")),
+            "remove" => ("removed", old.to_string()),
+            _ => continue,
+        };
+        let _ = writeln!(
+            out,
+            "[{}] [ALPM] {verb} {} ({versions})",
+            history::format_log_date(&date.fixed_offset()),
+            strip_arch(package)
+        );
+    }
+    out
+}
+
+/// Debian and its derivatives.
+pub struct Apt;
+
+impl Backend for Apt {
+    fn name(&self) -> &'static str {
+        "apt"
+    }
+
+    fn program(&self) -> &'static str {
+        "dpkg-query"
+    }
+
+    fn detect(&self) -> bool {
+        exec::on_path("dpkg-query") || Path::new(DPKG_STATUS).is_file()
+    }
+
+    fn log_path(&self) -> &'static Path {
+        Path::new(DPKG_LOG)
+    }
+
+    fn normalize_log<'a>(&self, raw: &'a [u8]) -> Cow<'a, [u8]> {
+        Cow::Owned(normalize_dpkg_log(raw))
+    }
+
+    fn read_installed(&self, scope: InstallScope) -> Vec<String> {
+        let auto = match scope {
+            InstallScope::All => HashSet::new(),
+            _ => auto_installed(),
+        };
+        let mut names: Vec<String> = installed()
+            .into_iter()
+            .map(|paragraph| paragraph["Package"].clone())
+            .filter(|name| match scope {
+                InstallScope::Explicit => !auto.contains(name),
+                InstallScope::Dependencies => auto.contains(name),
+                InstallScope::All => true,
+            })
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    fn installed_versions(&self) -> BTreeMap<String, String> {
+        installed()
+            .into_iter()
+            .map(|paragraph| {
+                let version = paragraph.get("Version").cloned().unwrap_or_default();
+                (paragraph["Package"].clone(), version)
+            })
+            .collect()
+    }
+
+    fn local_info(&self) -> Vec<InfoBlock> {
+        let auto = auto_installed();
+        installed()
+            .iter()
+            .map(|paragraph| info_block(paragraph, &auto))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dpkg_log_lines() {
+        let log = b"\
+2024-01-15 14:30:45 startup archives unpack
+2024-01-15 14:30:45 install vim:amd64 <none> 2:9.1.0-1
+2024-01-15 14:30:46 status half-installed vim:amd64 2:9.1.0-1
+2024-01-16 09:00:00 upgrade libc6:amd64 2.36-8 2.36-9
+2024-01-17 09:00:00 remove vim:amd64 2:9.1.0-1 <none>
+2024-01-17 09:00:01 purge vim:amd64 2:9.1.0-1 <none>
+";
+        let normalized = normalize_dpkg_log(log);
+        let events = history::parse_events(&normalized);
+        let vim: Vec<_> = events["vim"]
+            .iter()
+            .map(|event| (event.status, event.versions.as_str()))
+            .collect();
+        assert_eq!(
+            vim,
+            [
+                (history::Status::Installed, "2:9.1.0-1"),
+                (history::Status::Removed, "2:9.1.0-1"),
+            ]
+        );
+        assert_eq!(events["libc6"][0].versions, "2.36-8 -> 2.36-9");
+        assert!(events["libc6"][0].date.starts_with("2024-01-16T09:00:00"));
+    }
+
+    #[test]
+    fn installed_packages() {
+        let query = "bash\tinstalled\t5.2.15-2\t7164\tGNU Bourne Again SHell\t\
+                     http://tiswww.case.edu/php/chet/bash/bashtop.html\t\
+                     base-files (>= 2.1.12), debianutils (>= 5.6-0.1)\t\n\
+                     old\tconfig-files\t1.0\t12\t\t\t\t\n";
+        let status = "Package: bash\nStatus: install ok installed\nInstalled-Size: 7164\n\
+                      Version: 5.2.15-2\nDepends: base-files (>= 2.1.12), debianutils (>= 5.6-0.1)\n\
+                      Description: GNU Bourne Again SHell\n Bash is an sh-compatible shell.\n\
+                      Homepage: http://tiswww.case.edu/php/chet/bash/bashtop.html\n\n\
+                      Package: old\nStatus: deinstall ok config-files\nVersion: 1.0\n";
+        let states = "Package: bash\nArchitecture: amd64\nAuto-Installed: 0\n\n\
+                      Package: base-files\nArchitecture: amd64\nAuto-Installed: 1\n";
+        let auto = parse_auto_installed(states);
+        assert_eq!(auto, HashSet::from(["base-files".to_string()]));
+
+        for paragraphs in [parse_query(query), parse_status(status)] {
+            assert_eq!(paragraphs.len(), 1);
+            let block = info_block(&paragraphs[0], &auto);
+            assert_eq!(block["Version"], "5.2.15-2");
+            assert_eq!(block["Description"], "GNU Bourne Again SHell");
+            assert_eq!(block["Depends On"], "base-files  debianutils");
+            assert_eq!(block["Installed Size"], "7.00 MiB");
+            assert_eq!(block["Install Reason"], "Explicitly installed");
+        }
+    }
+}
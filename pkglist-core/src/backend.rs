@@ -0,0 +1,91 @@
+//! The package managers pkglist can read. Each backend knows its log, how
+//! to list what is installed and how to rewrite its log into `pacman.log`'s
+//! format, which everything downstream parses. pacman is the default; the
+//! others let the same listing run on other distributions.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::apt::Apt;
+use crate::dnf::Dnf;
+use crate::pacman::{InfoBlock, InstallScope, Pacman, Update};
+
+static CURRENT: OnceLock<&'static dyn Backend> = OnceLock::new();
+
+/// Every backend, in the order [`detect`] tries them.
+pub const BACKENDS: &[&dyn Backend] = &[&Pacman, &Apt, &Dnf];
+
+pub trait Backend: Sync {
+    /// `pacman`, `apt`, ...: what `--backend` takes.
+    fn name(&self) -> &'static str;
+
+    /// The program queried for the installed packages; without it on
+    /// `PATH`, sandbox mode reads the database files instead.
+    fn program(&self) -> &'static str;
+
+    /// Whether this system looks managed by this package manager.
+    fn detect(&self) -> bool;
+
+    /// The log read unless `--log` names another.
+    fn log_path(&self) -> &'static Path;
+
+    /// Rewrites log content, the whole log or an appended tail, into
+    /// `pacman.log` lines.
+    fn normalize_log<'a>(&self, raw: &'a [u8]) -> Cow<'a, [u8]>;
+
+    /// Whether the log is a database rather than appended text: read whole
+    /// through [`read_database`](Backend::read_database), never from an
+    /// offset.
+    fn is_database(&self) -> bool {
+        false
+    }
+
+    /// The history in the database log at `path`, as `pacman.log` lines.
+    fn read_database(&self, _path: &Path) -> io::Result<Vec<u8>> {
+        Ok(Vec::new())
+    }
+
+    /// The installed packages of `scope`, sorted.
+    fn read_installed(&self, scope: InstallScope) -> Vec<String>;
+
+    /// Every installed package with its version.
+    fn installed_versions(&self) -> BTreeMap<String, String>;
+
+    /// Installed packages no configured repository provides.
+    fn foreign_packages(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Every installed package as the block `pacman -Qi` prints, which
+    /// [`LocalDb`](crate::pacman::LocalDb) is built from.
+    fn local_info(&self) -> Vec<InfoBlock> {
+        Vec::new()
+    }
+
+    /// Upgrades the synced package lists have for installed packages.
+    fn pending_updates(&self) -> Vec<Update> {
+        Vec::new()
+    }
+}
+
+/// The first backend whose package manager is present, or pacman.
+pub fn detect() -> &'static dyn Backend {
+    BACKENDS
+        .iter()
+        .copied()
+        .find(|backend| backend.detect())
+        .unwrap_or(&Pacman)
+}
+
+/// Selects the backend; set once at startup before anything is read.
+pub fn set(backend: &'static dyn Backend) {
+    let _ = CURRENT.set(backend);
+}
+
+/// The selected backend, pacman unless [`set`] chose another.
+pub fn current() -> &'static dyn Backend {
+    CURRENT.get().copied().unwrap_or(&Pacman)
+}
@@ -0,0 +1,200 @@
+//! The history cache: the parsed log saved as JSON so later runs only
+//! parse what was appended since.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dirs;
+use crate::exec;
+use crate::history::{self, PackageInfo, Status, SyncMarkers};
+
+const CACHE_FILE: &str = "history.json";
+
+/// Bumped when the cached data gains fields the log has to be parsed again
+/// for; caches of another format are rebuilt, keeping their settings.
+pub const CACHE_FORMAT: u32 = 3;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CacheData {
+    #[serde(default)]
+    pub format: u32,
+    pub pkg_hash: u64,
+    pub last_log_size: u64,
+    /// The log the data was parsed from; `None` in caches written before it
+    /// could be chosen, which always read pacman's.
+    #[serde(default)]
+    pub log: Option<PathBuf>,
+    pub data: BTreeMap<String, PackageInfo>,
+    #[serde(default)]
+    pub markers: SyncMarkers,
+    /// Set by `pkglist cache compact`: removals older than this are dropped.
+    #[serde(default)]
+    pub compacted_before: Option<String>,
+}
+
+/// Per user, so nobody else can read the cache or plant a file in its place.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(CACHE_FILE))
+}
+
+/// An exclusive lock on `<cache>.lock`, held while the cache is read, brought
+/// up to date and saved so concurrent runs take turns instead of saving over
+/// each other. Released when dropped. Without a lock file, for instance in a
+/// read-only directory, the run goes ahead unlocked: the cache only saves time.
+pub struct CacheLock {
+    _file: Option<File>,
+}
+
+pub fn lock(cache_file: &Path) -> CacheLock {
+    if exec::is_dry_run() {
+        return CacheLock { _file: None };
+    }
+    let file = cache_file
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| {
+            File::options()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .mode(0o600)
+                .open(cache_file.with_extension("lock"))
+        })
+        .and_then(|file| file.lock().map(|()| file));
+    CacheLock { _file: file.ok() }
+}
+
+impl CacheData {
+    /// Whether the cache was written in this format from the log being read,
+    /// so it can be used or brought up to date.
+    pub fn is_current(&self) -> bool {
+        self.format == CACHE_FORMAT
+            && self.log.as_deref().unwrap_or(Path::new(history::LOG_PATH)) == history::log_path()
+    }
+}
+
+pub fn calculate_pkg_hash(pkgs: &[String]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    pkgs.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn load_cache(cache_file: &Path) -> Option<CacheData> {
+    fs::read(cache_file)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+}
+
+pub fn save_cache(cache_file: &Path, data: &CacheData) -> io::Result<()> {
+    exec::write_file(cache_file, &serde_json::to_vec(data)?, Some(0o600))
+}
+
+/// Drops packages whose last event is a removal before `before`, a date
+/// prefix like `2020-01-01`. Anything still installed keeps its entry and so
+/// its date. Returns how many entries went.
+pub fn prune(data: &mut BTreeMap<String, PackageInfo>, before: &str) -> usize {
+    let count = data.len();
+    data.retain(|_, info| {
+        info.status != Status::Removed
+            || info
+                .date
+                .is_none_or(|date| history::format_log_date(&date).as_str() >= before)
+    });
+    count - data.len()
+}
+
+/// Prunes the cache in place and remembers the cut-off, so rebuilding the
+/// cache after the log grows prunes the same way.
+pub fn compact(cache_file: &Path, before: &str) -> io::Result<()> {
+    let _lock = lock(cache_file);
+    let mut cache_data = load_cache(cache_file).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no cache at {}", cache_file.display()),
+        )
+    })?;
+    let size = fs::metadata(cache_file).map(|meta| meta.len()).unwrap_or(0);
+    let dropped = prune(&mut cache_data.data, before);
+    cache_data.compacted_before = Some(before.to_string());
+    let bytes = serde_json::to_vec(&cache_data)?;
+    println!(
+        "dropped {dropped} removed package(s) last seen before {before}; cache {} -> {} bytes",
+        size,
+        bytes.len()
+    );
+    exec::write_file(cache_file, &bytes, Some(0o600))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(date: &str, status: Status) -> PackageInfo {
+        PackageInfo {
+            date: history::parse_log_date(date),
+            status,
+            versions: String::new(),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn prune_drops_only_old_removals() {
+        let mut data = BTreeMap::from([
+            (
+                "old".to_string(),
+                info("2019-05-01T10:00:00+0000", Status::Removed),
+            ),
+            (
+                "new".to_string(),
+                info("2021-05-01T10:00:00+0000", Status::Removed),
+            ),
+            (
+                "kept".to_string(),
+                info("2018-05-01T10:00:00+0000", Status::Installed),
+            ),
+        ]);
+        assert_eq!(prune(&mut data, "2020-01-01"), 1);
+        assert_eq!(data.keys().collect::<Vec<_>>(), ["kept", "new"]);
+    }
+
+    #[test]
+    fn package_hash_follows_the_list() {
+        let list = ["a".to_string(), "b".to_string()];
+        assert_eq!(calculate_pkg_hash(&list), calculate_pkg_hash(&list.clone()));
+        assert_ne!(calculate_pkg_hash(&list), calculate_pkg_hash(&list[..1]));
+    }
+
+    #[test]
+    fn saved_cache_loads_back() {
+        let dir = std::env::temp_dir().join(format!("pkglist-core-cache-{}", std::process::id()));
+        let file = dir.join(CACHE_FILE);
+        let data = CacheData {
+            format: CACHE_FORMAT,
+            pkg_hash: 42,
+            last_log_size: 1234,
+            log: None,
+            data: BTreeMap::from([(
+                "vim".to_string(),
+                info("2024-01-02T10:00:05+0100", Status::Installed),
+            )]),
+            markers: SyncMarkers::default(),
+            compacted_before: None,
+        };
+        save_cache(&file, &data).unwrap();
+        let loaded = load_cache(&file).unwrap();
+        assert_eq!(loaded.pkg_hash, 42);
+        assert_eq!(loaded.data, data.data);
+        // Written before `log` existed, so it was read from pacman's log.
+        assert!(loaded.is_current());
+        assert!(load_cache(&dir.join("missing.json")).is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+//! pacman's databases read straight from disk, for sandbox mode where
+//! pacman itself can't be run: the local `desc` files, the sync database
+//! archives and the repositories in `pacman.conf`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use chrono::{Local, TimeZone};
+use flate2::read::MultiGzDecoder;
+
+use crate::pacman::{self, InfoBlock, LOCAL_DB_DIR, SYNC_DB_DIR};
+use crate::size;
+
+pub const PACMAN_CONF: &str = "/etc/pacman.conf";
+const TAR_BLOCK: usize = 512;
+/// `%c` in the C locale, the way `pacman -Qi` prints dates under `LC_ALL=C`.
+const DATE_FORMAT: &str = "%a %b %e %H:%M:%S %Y";
+
+/// The `%KEY%` sections of a `desc` file, one value per line.
+pub type Desc = BTreeMap<String, Vec<String>>;
+
+pub fn parse_desc(text: &str) -> Desc {
+    let mut desc = Desc::new();
+    let mut key: Option<String> = None;
+    for line in text.lines() {
+        if let Some(name) = line
+            .strip_prefix('%')
+            .and_then(|rest| rest.strip_suffix('%'))
+        {
+            key = Some(name.to_string());
+            desc.entry(name.to_string()).or_default();
+        } else if line.is_empty() {
+            key = None;
+        } else if let Some(values) = key.as_ref().and_then(|key| desc.get_mut(key)) {
+            values.push(line.to_string());
+        }
+    }
+    desc
+}
+
+fn first<'a>(desc: &'a Desc, key: &str) -> Option<&'a str> {
+    desc.get(key)?.first().map(String::as_str)
+}
+
+/// `vim-9.1.0-1` → `vim`; database entries are named `name-pkgver-pkgrel`.
+pub fn package_name(entry: &str) -> Option<&str> {
+    entry.rsplitn(3, '-').nth(2)
+}
+
+/// Every `desc` file under `dir`, a local database directory.
+pub fn read_descs(dir: &Path) -> Vec<Desc> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| fs::read_to_string(entry.path().join("desc")).ok())
+        .map(|text| parse_desc(&text))
+        .filter(|desc| first(desc, "NAME").is_some())
+        .collect()
+}
+
+pub fn local_descs() -> Vec<Desc> {
+    read_descs(Path::new(LOCAL_DB_DIR))
+}
+
+/// Dependencies are marked with `%REASON%` 1; explicit installs leave it out.
+pub fn is_explicit(desc: &Desc) -> bool {
+    first(desc, "REASON") != Some("1")
+}
+
+pub fn name(desc: &Desc) -> Option<&str> {
+    first(desc, "NAME")
+}
+
+pub fn version(desc: &Desc) -> Option<&str> {
+    first(desc, "VERSION")
+}
+
+fn date(desc: &Desc, key: &str) -> String {
+    first(desc, key)
+        .and_then(|secs| Local.timestamp_opt(secs.parse().ok()?, 0).single())
+        .map(|date| date.format(DATE_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+/// A `desc` file as the block `pacman -Qi` would print for it, without
+/// `Required By`, which takes the other packages to work out.
+pub fn info_block(desc: &Desc) -> InfoBlock {
+    let join = |key: &str| {
+        desc.get(key)
+            .map(|values| values.join("  "))
+            .unwrap_or_default()
+    };
+    let mut block = InfoBlock::new();
+    for (field, key) in [
+        ("Name", "NAME"),
+        ("Version", "VERSION"),
+        ("Description", "DESC"),
+        ("Architecture", "ARCH"),
+        ("URL", "URL"),
+        ("Licenses", "LICENSE"),
+        ("Groups", "GROUPS"),
+        ("Provides", "PROVIDES"),
+        ("Depends On", "DEPENDS"),
+        ("Optional Deps", "OPTDEPENDS"),
+        ("Conflicts With", "CONFLICTS"),
+        ("Replaces", "REPLACES"),
+        ("Packager", "PACKAGER"),
+    ] {
+        block.insert(field.to_string(), join(key));
+    }
+    let size = first(desc, "SIZE").and_then(|bytes| bytes.parse().ok());
+    block.insert(
+        "Installed Size".to_string(),
+        size.map(size::format_size).unwrap_or_default(),
+    );
+    block.insert("Build Date".to_string(), date(desc, "BUILDDATE"));
+    block.insert("Install Date".to_string(), date(desc, "INSTALLDATE"));
+    let reason = if is_explicit(desc) {
+        "Explicitly installed"
+    } else {
+        "Installed as a dependency for another package"
+    };
+    block.insert("Install Reason".to_string(), reason.to_string());
+    block
+}
+
+fn words<'a>(block: &'a InfoBlock, key: &str) -> impl Iterator<Item = &'a str> {
+    block
+        .get(key)
+        .into_iter()
+        .flat_map(|value| value.split_whitespace())
+}
+
+/// `info_block` for every package in `descs`, with `Required By` filled in
+/// from the others' dependencies.
+pub fn info_blocks(descs: &[Desc]) -> Vec<InfoBlock> {
+    let mut blocks: Vec<InfoBlock> = descs.iter().map(info_block).collect();
+    let mut providers: HashMap<&str, &str> = HashMap::new();
+    for block in &blocks {
+        for provided in words(block, "Provides") {
+            providers
+                .entry(pacman::strip_constraint(provided))
+                .or_insert(&block["Name"]);
+        }
+    }
+    for block in &blocks {
+        providers.insert(&block["Name"], &block["Name"]);
+    }
+    let mut required_by: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for block in &blocks {
+        for dep in words(block, "Depends On") {
+            if let Some(target) = providers.get(pacman::strip_constraint(dep)) {
+                required_by
+                    .entry(target.to_string())
+                    .or_default()
+                    .insert(block["Name"].clone());
+            }
+        }
+    }
+    for block in &mut blocks {
+        let names = required_by.remove(&block["Name"]).unwrap_or_default();
+        let names: Vec<String> = names.into_iter().collect();
+        block.insert("Required By".to_string(), names.join("  "));
+    }
+    blocks
+}
+
+/// The repository sections of a `pacman.conf`, in order.
+pub fn parse_conf_repos(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix('[')?.strip_suffix(']'))
+        .filter(|section| *section != "options")
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn configured_repos() -> Vec<String> {
+    fs::read_to_string(PACMAN_CONF)
+        .map(|text| parse_conf_repos(&text))
+        .unwrap_or_default()
+}
+
+fn tar_field(field: &[u8]) -> &str {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    std::str::from_utf8(&field[..end]).unwrap_or("").trim()
+}
+
+/// The top-level directories of a tar archive, which in a sync database
+/// are its `name-pkgver-pkgrel` entries. Reading stops at anything that
+/// isn't a tar header.
+pub fn tar_dirs(data: &[u8]) -> BTreeSet<String> {
+    let mut dirs = BTreeSet::new();
+    let mut offset = 0;
+    while let Some(header) = data.get(offset..offset + TAR_BLOCK) {
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let Ok(size) = usize::from_str_radix(tar_field(&header[124..136]), 8) else {
+            break;
+        };
+        // Extended headers describe the entry after them.
+        if !matches!(header[156], b'x' | b'g')
+            && let Some(dir) = tar_field(&header[..100])
+                .split('/')
+                .next()
+                .filter(|dir| !dir.is_empty())
+        {
+            dirs.insert(dir.to_string());
+        }
+        offset += TAR_BLOCK + size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+    }
+    dirs
+}
+
+// Sync databases are gzip'd by default; uncompressed ones are read as they
+// are, and other compressions give nothing.
+fn read_sync_db(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    if !data.starts_with(&[0x1f, 0x8b]) {
+        return Ok(data);
+    }
+    let mut tar = Vec::new();
+    MultiGzDecoder::new(data.as_slice()).read_to_end(&mut tar)?;
+    Ok(tar)
+}
+
+/// Every package in the sync databases as (repository, name), like
+/// `pacman::sync_packages`.
+pub fn sync_packages() -> Vec<(String, String)> {
+    configured_repos()
+        .into_iter()
+        .flat_map(|repo| {
+            let path = Path::new(SYNC_DB_DIR).join(format!("{repo}.db"));
+            let data = read_sync_db(&path).unwrap_or_default();
+            tar_dirs(&data)
+                .iter()
+                .filter_map(|entry| package_name(entry))
+                .map(|name| (repo.clone(), name.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESC: &str = "\
+%NAME%
+bash
+
+%VERSION%
+5.2.026-2
+
+%URL%
+https://www.gnu.org/software/bash/bash.html
+
+%SIZE%
+9751538
+
+%REASON%
+1
+
+%DEPENDS%
+readline>=7.0
+glibc
+
+%PROVIDES%
+sh
+";
+
+    #[test]
+    fn desc_blocks() {
+        let base = parse_desc("%NAME%\nbase\n\n%VERSION%\n3-2\n\n%DEPENDS%\nsh\n");
+        let bash = parse_desc(DESC);
+        assert_eq!(name(&bash), Some("bash"));
+        assert!(!is_explicit(&bash));
+        assert!(is_explicit(&base));
+
+        let blocks = info_blocks(&[base, bash]);
+        let bash = &blocks[1];
+        assert_eq!(bash["Version"], "5.2.026-2");
+        assert_eq!(bash["Depends On"], "readline>=7.0  glibc");
+        assert_eq!(bash["Installed Size"], "9.30 MiB");
+        assert_eq!(bash["Required By"], "base");
+        assert_eq!(blocks[0]["Required By"], "");
+        assert_eq!(blocks[0]["Install Reason"], "Explicitly installed");
+    }
+
+    #[test]
+    fn conf_repos() {
+        let conf = "[options]\nHoldPkg = pacman\n\n[core]\nInclude = /etc/pacman.d/mirrorlist\n\
+                    #[testing]\n[extra]\n";
+        assert_eq!(parse_conf_repos(conf), ["core", "extra"]);
+    }
+
+    fn tar_header(name: &str, size: usize) -> Vec<u8> {
+        let mut header = vec![0; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{size:011o}");
+        header[124..135].copy_from_slice(size.as_bytes());
+        header[156] = b'0';
+        header
+    }
+
+    #[test]
+    fn sync_db_entries() {
+        let mut tar = tar_header("vim-9.1.0-1/desc", 600);
+        tar.extend(vec![b'x'; 2 * TAR_BLOCK]);
+        tar.extend(tar_header("lib32-glibc-2.40-1/desc", 0));
+        tar.extend(vec![0; 2 * TAR_BLOCK]);
+        let dirs = tar_dirs(&tar);
+        assert_eq!(
+            dirs.iter()
+                .filter_map(|dir| package_name(dir))
+                .collect::<Vec<_>>(),
+            ["lib32-glibc", "vim"]
+        );
+    }
+}
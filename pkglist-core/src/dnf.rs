@@ -0,0 +1,457 @@
+//! The dnf/rpm backend for Fedora and its relatives: dnf's history database
+//! for the transactions, `rpm -qa` for the installed packages. Reading the
+//! database needs the `dnf` feature; without it, or without the database,
+//! the history is what rpm itself keeps: when each installed package was
+//! last installed or upgraded.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Local, TimeZone};
+
+use crate::backend::Backend;
+use crate::exec;
+use crate::history;
+use crate::pacman::{self, InfoBlock, InstallScope};
+use crate::size;
+
+pub const HISTORY_DB: &str = "/var/lib/dnf/history.sqlite";
+/// The `rpm -qa` query, one tab-separated line per package in the order of
+/// [`Rpm`]'s fields.
+const QUERY_FORMAT: &str = "%{NAME}\\t%{EPOCHNUM}\\t%{VERSION}\\t%{RELEASE}\\t%{SIZE}\\t\
+                            %{INSTALLTIME}\\t%{SUMMARY}\\t%{URL}\\t[%{REQUIRENAME} ]\\t\
+                            [%{PROVIDENAME} ]\\n";
+
+// libdnf's `TransactionItemAction` values.
+const INSTALL: i64 = 1;
+const DOWNGRADE: i64 = 2;
+const DOWNGRADED: i64 = 3;
+const OBSOLETE: i64 = 4;
+const OBSOLETED: i64 = 5;
+const UPGRADE: i64 = 6;
+const UPGRADED: i64 = 7;
+const REMOVE: i64 = 8;
+const REINSTALL: i64 = 9;
+// libdnf's `TransactionState` for a finished transaction.
+const DONE: i64 = 1;
+
+/// An installed package as `rpm -qa` describes it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rpm {
+    pub name: String,
+    pub version: String,
+    /// In bytes.
+    pub size: u64,
+    /// Seconds since the epoch.
+    pub install_time: i64,
+    pub summary: String,
+    pub url: String,
+    pub requires: Vec<String>,
+    pub provides: Vec<String>,
+}
+
+/// One package of one transaction in dnf's history database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryItem {
+    pub transaction: i64,
+    /// When the transaction began and ended, in seconds since the epoch;
+    /// no end for one that was interrupted.
+    pub begin: i64,
+    pub end: Option<i64>,
+    /// The arguments dnf was run with.
+    pub command: String,
+    pub state: i64,
+    pub action: i64,
+    pub name: String,
+    pub version: String,
+}
+
+/// `2:9.1.0-1.fc40`, or without the epoch when it is 0, as dnf prints
+/// versions.
+pub fn evr(epoch: &str, version: &str, release: &str) -> String {
+    match epoch {
+        "" | "0" => format!("{version}-{release}"),
+        _ => format!("{epoch}:{version}-{release}"),
+    }
+}
+
+// rpm's dependencies name files and sonames as well as packages, like
+// `/bin/sh` and `libc.so.6()(64bit)`; only the packages are kept.
+fn package_names(value: &str) -> Vec<String> {
+    let mut names: Vec<String> = value
+        .split_whitespace()
+        .filter(|name| !name.starts_with('/') && !name.contains('('))
+        .map(str::to_string)
+        .collect();
+    names.dedup();
+    names
+}
+
+/// `rpm -qa` output in `QUERY_FORMAT`.
+pub fn parse_query(text: &str) -> Vec<Rpm> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [
+                name,
+                epoch,
+                version,
+                release,
+                size,
+                time,
+                summary,
+                url,
+                requires,
+                provides,
+            ] = fields[..]
+            else {
+                return None;
+            };
+            Some(Rpm {
+                name: name.to_string(),
+                version: evr(epoch, version, release),
+                size: size.parse().unwrap_or(0),
+                install_time: time.parse().ok()?,
+                summary: summary.to_string(),
+                url: if url == "(none)" {
+                    String::new()
+                } else {
+                    url.to_string()
+                },
+                requires: package_names(requires),
+                provides: package_names(provides),
+            })
+        })
+        .collect()
+}
+
+/// A package as the block `pacman -Qi` would print, so the columns and
+/// queries built on the local database work unchanged.
+pub fn info_block(rpm: &Rpm, auto: &HashSet<String>) -> InfoBlock {
+    let reason = if auto.contains(&rpm.name) {
+        "Installed as a dependency for another package"
+    } else {
+        "Explicitly installed"
+    };
+    InfoBlock::from([
+        ("Name".to_string(), rpm.name.clone()),
+        ("Version".to_string(), rpm.version.clone()),
+        ("Description".to_string(), rpm.summary.clone()),
+        ("URL".to_string(), rpm.url.clone()),
+        ("Depends On".to_string(), rpm.requires.join("  ")),
+        ("Provides".to_string(), rpm.provides.join("  ")),
+        ("Installed Size".to_string(), size::format_size(rpm.size)),
+        ("Install Reason".to_string(), reason.to_string()),
+    ])
+}
+
+fn log_date(seconds: i64) -> String {
+    let date: DateTime<Local> = Local.timestamp_opt(seconds, 0).single().unwrap_or_default();
+    history::format_log_date(&date.fixed_offset())
+}
+
+/// Writes dnf's history as `pacman.log` lines, one transaction after
+/// another with its command line and outcome, so the transaction views
+/// work too. `items` come grouped by transaction; an upgrade's old and new
+/// versions are separate items that are merged here.
+pub fn normalize_history(items: &[HistoryItem]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for transaction in items.chunk_by(|a, b| a.transaction == b.transaction) {
+        let first = &transaction[0];
+        let begin = log_date(first.begin);
+        if !first.command.is_empty() {
+            let _ = writeln!(out, "[{begin}] [PACMAN] Running 'dnf {}'", first.command);
+        }
+        let _ = writeln!(out, "[{begin}] [ALPM] transaction started");
+        let replaced: HashMap<&str, &str> = transaction
+            .iter()
+            .filter(|item| matches!(item.action, UPGRADED | DOWNGRADED))
+            .map(|item| (item.name.as_str(), item.version.as_str()))
+            .collect();
+        for item in transaction {
+            let (verb, versions) = match item.action {
+                INSTALL | OBSOLETE => ("installed", item.version.clone()),
+                REINSTALL => ("reinstalled", item.version.clone()),
+                REMOVE | OBSOLETED => ("removed", item.version.clone()),
+                UPGRADE | DOWNGRADE => {
+                    let verb = if item.action == UPGRADE {
+                        "upgraded"
+                    } else {
+                        "downgraded"
+                    };
+                    match replaced.get(item.name.as_str()) {
+                        Some(old) => (verb, format!("{old} -> {}", item.version)),
+                        None => (verb, item.version.clone()),
+                    }
+                }
+                _ => continue,
+            };
+            let _ = writeln!(out, "[{begin}] [ALPM] {verb} {} ({versions})", item.name);
+        }
+        let outcome = match (first.end, first.state) {
+            (Some(_), DONE) => "completed",
+            (Some(_), _) => "failed",
+            (None, _) => "interrupted",
+        };
+        let end = first.end.map_or_else(|| begin.clone(), log_date);
+        let _ = writeln!(out, "[{end}] [ALPM] transaction {outcome}");
+    }
+    out
+}
+
+/// The history rpm keeps on its own, for when dnf's database can't be
+/// read: each installed package as installed when it last was, oldest
+/// first. Removals and the versions before upgrades are lost.
+pub fn install_times(rpms: &[Rpm]) -> Vec<u8> {
+    let mut rpms: Vec<&Rpm> = rpms.iter().collect();
+    rpms.sort_by_key(|rpm| rpm.install_time);
+    let mut out = Vec::new();
+    for rpm in rpms {
+        let _ = writeln!(
+            out,
+            "[{}] [ALPM] installed {} ({})",
+            log_date(rpm.install_time),
+            rpm.name,
+            rpm.version
+        );
+    }
+    out
+}
+
+#[cfg(feature = "dnf")]
+mod database {
+    use std::collections::HashSet;
+    use std::io;
+    use std::path::Path;
+
+    use rusqlite::{Connection, OpenFlags};
+
+    use super::{HistoryItem, evr};
+
+    // libdnf's `TransactionItemReason`s of packages nobody asked for by name.
+    const DEPENDENCY_REASONS: &[i64] = &[1, 3, 4];
+
+    const ITEMS: &str = "\
+        SELECT t.id, t.dt_begin, t.dt_end, t.cmdline, t.state, ti.action, \
+               r.name, r.epoch, r.version, r.release \
+        FROM trans t \
+        JOIN trans_item ti ON ti.trans_id = t.id \
+        JOIN rpm r ON r.item_id = ti.item_id \
+        ORDER BY t.id, ti.id";
+    const REASONS: &str = "\
+        SELECT r.name, ti.reason \
+        FROM trans_item ti \
+        JOIN trans t ON t.id = ti.trans_id \
+        JOIN rpm r ON r.item_id = ti.item_id \
+        WHERE t.state = 1 \
+        ORDER BY t.id, ti.id";
+
+    fn sql_error(err: rusqlite::Error) -> io::Error {
+        io::Error::other(err)
+    }
+
+    fn open(path: &Path) -> io::Result<Connection> {
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(sql_error)
+    }
+
+    pub fn items(path: &Path) -> io::Result<Vec<HistoryItem>> {
+        let db = open(path)?;
+        let mut statement = db.prepare(ITEMS).map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                let epoch: Option<i64> = row.get(7)?;
+                Ok(HistoryItem {
+                    transaction: row.get(0)?,
+                    begin: row.get(1)?,
+                    end: row.get(2)?,
+                    command: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                    state: row.get(4)?,
+                    action: row.get(5)?,
+                    name: row.get(6)?,
+                    version: evr(
+                        &epoch.unwrap_or(0).to_string(),
+                        &row.get::<_, String>(8)?,
+                        &row.get::<_, String>(9)?,
+                    ),
+                })
+            })
+            .map_err(sql_error)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(sql_error)
+    }
+
+    /// The packages whose latest reason is a dependency of some kind.
+    pub fn auto_installed(path: &Path) -> io::Result<HashSet<String>> {
+        let db = open(path)?;
+        let mut statement = db.prepare(REASONS).map_err(sql_error)?;
+        let mut auto = HashSet::new();
+        let mut rows = statement.query([]).map_err(sql_error)?;
+        while let Some(row) = rows.next().map_err(sql_error)? {
+            let name: String = row.get(0).map_err(sql_error)?;
+            let reason: i64 = row.get(1).map_err(sql_error)?;
+            if DEPENDENCY_REASONS.contains(&reason) {
+                auto.insert(name);
+            } else {
+                auto.remove(&name);
+            }
+        }
+        Ok(auto)
+    }
+}
+
+fn installed() -> Vec<Rpm> {
+    pacman::run("rpm", &["-qa", "--qf", QUERY_FORMAT])
+        .map(|text| parse_query(&text))
+        .unwrap_or_default()
+}
+
+// Without the database every package counts as explicitly installed.
+fn auto_installed() -> HashSet<String> {
+    #[cfg(feature = "dnf")]
+    if let Ok(auto) = database::auto_installed(history::log_path()) {
+        return auto;
+    }
+    HashSet::new()
+}
+
+/// Fedora, RHEL and their relatives.
+pub struct Dnf;
+
+impl Backend for Dnf {
+    fn name(&self) -> &'static str {
+        "dnf"
+    }
+
+    fn program(&self) -> &'static str {
+        "rpm"
+    }
+
+    fn detect(&self) -> bool {
+        exec::on_path("dnf") || Path::new(HISTORY_DB).is_file()
+    }
+
+    fn log_path(&self) -> &'static Path {
+        Path::new(HISTORY_DB)
+    }
+
+    fn normalize_log<'a>(&self, _raw: &'a [u8]) -> Cow<'a, [u8]> {
+        Cow::Owned(Vec::new())
+    }
+
+    fn is_database(&self) -> bool {
+        true
+    }
+
+    fn read_database(&self, path: &Path) -> io::Result<Vec<u8>> {
+        #[cfg(feature = "dnf")]
+        if path.is_file() {
+            return database::items(path).map(|items| normalize_history(&items));
+        }
+        let _ = path;
+        Ok(install_times(&installed()))
+    }
+
+    fn read_installed(&self, scope: InstallScope) -> Vec<String> {
+        let auto = match scope {
+            InstallScope::All => HashSet::new(),
+            _ => auto_installed(),
+        };
+        let mut names: Vec<String> = installed()
+            .into_iter()
+            .map(|rpm| rpm.name)
+            .filter(|name| match scope {
+                InstallScope::Explicit => !auto.contains(name),
+                InstallScope::Dependencies => auto.contains(name),
+                InstallScope::All => true,
+            })
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    fn installed_versions(&self) -> BTreeMap<String, String> {
+        installed()
+            .into_iter()
+            .map(|rpm| (rpm.name, rpm.version))
+            .collect()
+    }
+
+    fn local_info(&self) -> Vec<InfoBlock> {
+        let auto = auto_installed();
+        installed()
+            .iter()
+            .map(|rpm| info_block(rpm, &auto))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(transaction: i64, action: i64, name: &str, version: &str) -> HistoryItem {
+        HistoryItem {
+            transaction,
+            begin: 1_705_325_447 + transaction * 86_400,
+            end: Some(1_705_325_450 + transaction * 86_400),
+            command: format!("transaction {transaction}"),
+            state: DONE,
+            action,
+            name: name.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn history_items() {
+        let mut failed = item(3, REMOVE, "vim", "2:9.1.0-1.fc40");
+        failed.state = 2;
+        let items = [
+            item(1, INSTALL, "vim", "2:9.1.0-1.fc40"),
+            item(2, UPGRADED, "bash", "5.2.21-1.fc40"),
+            item(2, UPGRADE, "bash", "5.2.26-1.fc40"),
+            failed,
+        ];
+        let log = normalize_history(&items);
+        let events = history::parse_events(&log);
+        assert_eq!(events["vim"].len(), 2);
+        assert_eq!(events["vim"][0].status, history::Status::Installed);
+        assert_eq!(events["vim"][1].status, history::Status::Removed);
+        assert_eq!(events["bash"][0].versions, "5.2.21-1.fc40 -> 5.2.26-1.fc40");
+
+        let text = String::from_utf8(log).unwrap();
+        assert!(text.contains("[PACMAN] Running 'dnf transaction 2'"));
+        assert_eq!(text.matches("transaction started").count(), 3);
+        assert_eq!(text.matches("transaction completed").count(), 2);
+        assert_eq!(text.matches("transaction failed").count(), 1);
+    }
+
+    #[test]
+    fn installed_packages() {
+        let query = "bash\t0\t5.2.26\t1.fc40\t8388608\t1705325447\tThe GNU Bourne Again shell\t\
+                     https://www.gnu.org/software/bash\t/bin/sh filesystem libc.so.6()(64bit) \t\
+                     /bin/bash bash bash(x86-64) \n\
+                     vim-enhanced\t2\t9.1.0\t1.fc40\t4194304\t1705411847\tA version of VIM\t\
+                     (none)\tvim-common \tvim-enhanced \n";
+        let rpms = parse_query(query);
+        assert_eq!(rpms.len(), 2);
+        assert_eq!(rpms[1].version, "2:9.1.0-1.fc40");
+
+        let auto = HashSet::from(["bash".to_string()]);
+        let block = info_block(&rpms[0], &auto);
+        assert_eq!(block["Version"], "5.2.26-1.fc40");
+        assert_eq!(block["Depends On"], "filesystem");
+        assert_eq!(block["Provides"], "bash");
+        assert_eq!(block["Installed Size"], "8.00 MiB");
+        assert_eq!(
+            block["Install Reason"],
+            "Installed as a dependency for another package"
+        );
+        assert_eq!(info_block(&rpms[1], &auto)["URL"], "");
+
+        let events = history::parse_events(&install_times(&rpms));
+        assert_eq!(events["vim-enhanced"][0].versions, "2:9.1.0-1.fc40");
+    }
+}
@@ -0,0 +1,457 @@
+//! Side effects — writes and external commands — routed through one place
+//! so a dry run can describe them instead, and every external command runs
+//! under a timeout so a hung mirror or host can't hang pkglist with it.
+//! In sandbox mode no command runs at all. Whatever is done is recorded in
+//! the audit log of `actions`.
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::actions;
+
+const TTY: &str = "/dev/tty";
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// The pause before the first retry, doubled for each one after it.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+// Every write to disk and every external command with side effects goes
+// through here, so `--dry-run` covers all of them by describing each step
+// on stderr instead of performing it.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+pub fn enable_dry_run() {
+    DRY_RUN.store(true, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+// Inside containers and hardened services pacman may not be executable at
+// all; sandbox mode refuses every external command up front, and callers
+// that can fall back to reading files directly do.
+static SANDBOX: AtomicBool = AtomicBool::new(false);
+
+pub fn enable_sandbox() {
+    SANDBOX.store(true, Ordering::Relaxed);
+}
+
+pub fn is_sandboxed() -> bool {
+    SANDBOX.load(Ordering::Relaxed)
+}
+
+/// Whether `program` is an executable file in a `PATH` directory, checked
+/// without running anything.
+pub fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|path| {
+        env::split_paths(&path).any(|dir| {
+            fs::metadata(dir.join(program))
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        })
+    })
+}
+
+fn spawn(command: &mut Command) -> io::Result<Child> {
+    if is_sandboxed() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("sandbox mode: not running {}", command_line(command)),
+        ));
+    }
+    command.spawn()
+}
+
+/// How long an external command may run before it is killed, and how often
+/// a query that timed out or failed in passing is tried again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub timeout: Option<Duration>,
+    pub retries: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            timeout: Some(Duration::from_secs(60)),
+            retries: 1,
+        }
+    }
+}
+
+static LIMITS: OnceLock<Limits> = OnceLock::new();
+
+/// Set once at startup, before any command runs.
+pub fn set_limits(limits: Limits) {
+    let _ = LIMITS.set(limits);
+}
+
+pub fn limits() -> Limits {
+    LIMITS.get().copied().unwrap_or_default()
+}
+
+/// How privileged steps get root: through `sudo`, `doas` or `pkexec`, or
+/// not at all when pkglist already runs as root.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Elevation {
+    #[default]
+    Sudo,
+    Doas,
+    Pkexec,
+    None,
+}
+
+impl Elevation {
+    fn program(self) -> Option<&'static str> {
+        match self {
+            Elevation::Sudo => Some("sudo"),
+            Elevation::Doas => Some("doas"),
+            Elevation::Pkexec => Some("pkexec"),
+            Elevation::None => None,
+        }
+    }
+
+    /// `program` wrapped in the elevation command, ready for its arguments.
+    pub fn command(self, program: &str) -> Command {
+        match self.program() {
+            Some(wrapper) => {
+                let mut command = Command::new(wrapper);
+                command.arg(program);
+                command
+            }
+            None => Command::new(program),
+        }
+    }
+}
+
+static ELEVATION: OnceLock<Elevation> = OnceLock::new();
+
+/// Set once at startup, like `set_limits`.
+pub fn set_elevation(elevation: Elevation) {
+    let _ = ELEVATION.set(elevation);
+}
+
+/// `program` run with the configured elevation, for `run_checked`.
+pub fn privileged(program: &str) -> Command {
+    ELEVATION
+        .get()
+        .copied()
+        .unwrap_or_default()
+        .command(program)
+}
+
+pub fn describe(action: &str) {
+    eprintln!("pkglist: dry run: would {action}");
+}
+
+fn quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.into_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// The command as it would be typed into a shell, environment first.
+pub fn command_line(command: &Command) -> String {
+    let env = command.get_envs().filter_map(|(key, value)| {
+        value.map(|value| format!("{}={}", key.to_string_lossy(), quote(value)))
+    });
+    let program = std::iter::once(quote(command.get_program()));
+    env.chain(program)
+        .chain(command.get_args().map(quote))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn create_dir_all(dir: &Path) -> io::Result<()> {
+    if is_dry_run() {
+        if !dir.is_dir() {
+            describe(&format!("create directory {}", dir.display()));
+        }
+        return Ok(());
+    }
+    fs::create_dir_all(dir)
+}
+
+/// Writes through a temporary file and a rename so readers never see half a
+/// file; `mode` sets the permissions before the rename.
+pub fn write_file(path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()> {
+    let target = format!("{} ({} bytes)", path.display(), contents.len());
+    if is_dry_run() {
+        describe(&format!("write {target}"));
+        return Ok(());
+    }
+    let result = replace_file(path, contents, mode);
+    actions::record("write", &target, &result);
+    result
+}
+
+/// Copies `from` to `to`, replacing it, like `write_file` for a file that
+/// already exists elsewhere.
+pub fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    let target = format!("{} to {}", from.display(), to.display());
+    if is_dry_run() {
+        describe(&format!("copy {target}"));
+        return Ok(());
+    }
+    let result = fs::copy(from, to).map(|_| ());
+    actions::record("copy", &target, &result);
+    result
+}
+
+fn replace_file(path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let temp_file = path.with_extension("tmp");
+    fs::write(&temp_file, contents)?;
+    if let Some(mode) = mode {
+        fs::set_permissions(&temp_file, fs::Permissions::from_mode(mode))?;
+    }
+    fs::rename(temp_file, path)
+}
+
+// Waits for `child`, killing it once `timeout` has passed.
+fn wait(child: &mut Child, command: &Command, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+    let Some(timeout) = timeout else {
+        return child.wait();
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "{} timed out after {}s",
+                    command_line(command),
+                    timeout.as_secs_f32()
+                ),
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// Read on a thread of its own, so a command filling one pipe never blocks
+// while the other is being read or the clock watched.
+fn drain(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+fn collect(pipe: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    pipe.and_then(|pipe| pipe.join().ok()).unwrap_or_default()
+}
+
+fn output_within(command: &mut Command, timeout: Option<Duration>) -> io::Result<Output> {
+    let mut child = spawn(
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )?;
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+    let status = wait(&mut child, command, timeout)?;
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+/// Runs a read-only `command` and collects its output, like
+/// `Command::output` but within `limits`. An attempt that times out, or whose
+/// output `transient` calls a passing failure such as a dropped connection,
+/// is retried after a pause; the last attempt's result is returned. Queries
+/// change nothing, so they run in dry runs too.
+pub fn query_within(
+    command: &mut Command,
+    limits: Limits,
+    transient: impl Fn(&Output) -> bool,
+) -> io::Result<Output> {
+    retry(
+        limits,
+        || output_within(command, limits.timeout),
+        |result| match result {
+            Ok(output) => transient(output),
+            Err(e) => e.kind() == io::ErrorKind::TimedOut,
+        },
+    )
+}
+
+/// Makes `attempt` until it succeeds or `transient` calls its failure a
+/// lasting one, at most `limits.retries` times more, with a pause doubling
+/// from `RETRY_BACKOFF` between attempts; the last result is returned. The
+/// one retry policy for everything that only reads, commands or not.
+pub fn retry<T, E>(
+    limits: Limits,
+    mut attempt: impl FnMut() -> Result<T, E>,
+    transient: impl Fn(&Result<T, E>) -> bool,
+) -> Result<T, E> {
+    let mut retries = 0;
+    loop {
+        let result = attempt();
+        if !transient(&result) || retries >= limits.retries {
+            return result;
+        }
+        thread::sleep(RETRY_BACKOFF * 2u32.saturating_pow(retries));
+        retries += 1;
+    }
+}
+
+/// `query_within` the configured limits.
+pub fn query(command: &mut Command, transient: impl Fn(&Output) -> bool) -> io::Result<Output> {
+    query_within(command, limits(), transient)
+}
+
+fn run_within(
+    command: &mut Command,
+    input: Option<&[u8]>,
+    timeout: Option<Duration>,
+) -> io::Result<ExitStatus> {
+    if is_dry_run() {
+        describe(&format!("run {}", command_line(command)));
+        return Ok(ExitStatus::from_raw(0));
+    }
+    let result = run_to_end(command, input, timeout);
+    let outcome = match &result {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(io::Error::other(status.to_string())),
+        Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+    };
+    actions::record("run", &command_line(command), &outcome);
+    result
+}
+
+fn run_to_end(
+    command: &mut Command,
+    input: Option<&[u8]>,
+    timeout: Option<Duration>,
+) -> io::Result<ExitStatus> {
+    if input.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let mut child = spawn(command)?;
+    if let Some(input) = input
+        && let Some(mut stdin) = child.stdin.take()
+    {
+        stdin.write_all(input)?;
+    }
+    wait(&mut child, command, timeout)
+}
+
+/// Runs `command` to completion, feeding it `input` on stdin when given, and
+/// kills it after the configured timeout. Nothing is retried since the
+/// command may have done part of its work. A dry run reports the command and
+/// pretends it succeeded.
+pub fn run(command: &mut Command, input: Option<&[u8]>) -> io::Result<ExitStatus> {
+    run_within(command, input, limits().timeout)
+}
+
+/// Like `run`, but a non-zero exit becomes an error naming the command. These
+/// are the privileged steps (`privileged("pacman")`), which may wait on a
+/// password or a confirmation, so they are never timed out.
+pub fn run_checked(command: &mut Command) -> io::Result<()> {
+    let status = run_within(command, None, None)?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} failed ({status})",
+            command_line(command)
+        )))
+    }
+}
+
+/// Shows the exact commands about to run, then asks like `confirm`.
+pub fn confirm_commands(prompt: &str, commands: &[Command]) -> io::Result<bool> {
+    for command in commands {
+        eprintln!("  $ {}", command_line(command));
+    }
+    confirm(prompt)
+}
+
+/// Asks before a step that cannot be undone. Dry runs answer yes on the
+/// user's behalf since nothing is going to happen anyway.
+pub fn confirm(prompt: &str) -> io::Result<bool> {
+    if is_dry_run() {
+        return Ok(true);
+    }
+    eprint!("{prompt} [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    // With a selection piped in, stdin is taken; ask the terminal instead.
+    if !io::stdin().is_terminal()
+        && let Ok(tty) = fs::File::open(TTY)
+    {
+        io::BufReader::new(tty).read_line(&mut answer)?;
+    } else {
+        io::stdin().lock().read_line(&mut answer)?;
+    }
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_lines_are_quoted_for_a_shell() {
+        let mut command = Command::new("pacman");
+        command
+            .env("LC_ALL", "C")
+            .args(["-D", "--asdeps", "it's", "", "a b"]);
+        assert_eq!(
+            command_line(&command),
+            r"LC_ALL=C pacman -D --asdeps 'it'\''s' '' 'a b'"
+        );
+    }
+
+    #[test]
+    fn privileged_commands_are_wrapped() {
+        let mut command = Elevation::Doas.command("pacman");
+        command.args(["-D", "--asdeps", "vim"]);
+        assert_eq!(command_line(&command), "doas pacman -D --asdeps vim");
+        assert_eq!(command_line(&Elevation::None.command("pacman")), "pacman");
+    }
+
+    #[test]
+    fn hung_commands_are_killed_at_the_timeout() {
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(100)),
+            retries: 0,
+        };
+        let error = query_within(Command::new("sleep").arg("5"), limits, |_| false).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        let output = query_within(Command::new("echo").arg("ok"), limits, |_| false).unwrap();
+        assert_eq!(output.stdout, b"ok\n");
+    }
+}
@@ -0,0 +1,228 @@
+//! Flatpak applications, listed alongside the packages of the backend.
+//! `flatpak list` gives the installed apps; their history comes from the
+//! journal entries flatpak writes for every deploy and uninstall, the same
+//! ones `flatpak history` shows, but read with full dates.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use crate::dirs;
+use crate::history::{self, PackageInfo};
+use crate::packages::PackageData;
+use crate::pacman::{self, InstallScope};
+
+const LIST_COLUMNS: &str = "--columns=application,version,origin,installation,active";
+const SYSTEM_DIR: &str = "/var/lib/flatpak";
+/// The `MESSAGE_ID` of flatpak's journal entries for changes to an
+/// installation.
+const MESSAGE_ID: &str = "MESSAGE_ID=c7b39b1e006b464599465e105b361485";
+const COMMIT_LEN: usize = 12;
+
+/// An installed application, as `flatpak list` describes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct App {
+    pub id: String,
+    pub version: String,
+    /// The remote it was installed from, like `flathub`.
+    pub origin: String,
+    /// `system`, `user` or the name of another installation.
+    pub installation: String,
+    pub commit: String,
+}
+
+/// One journal entry of a change to an application.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// Microseconds since the epoch, as a string like every journal field.
+    #[serde(rename = "__REALTIME_TIMESTAMP")]
+    pub time: String,
+    /// `deploy install`, `deploy update`, `uninstall`, ...
+    #[serde(rename = "OPERATION", default)]
+    pub operation: String,
+    /// `app/org.mozilla.firefox/x86_64/stable`.
+    #[serde(rename = "REF", default)]
+    pub reference: String,
+    #[serde(rename = "REMOTE", default)]
+    pub remote: String,
+    #[serde(rename = "COMMIT", default)]
+    pub commit: String,
+    #[serde(rename = "OLD_COMMIT", default)]
+    pub old_commit: String,
+}
+
+/// `flatpak list` output with `LIST_COLUMNS`, tab-separated as it prints
+/// them to a pipe.
+pub fn parse_list(text: &str) -> Vec<App> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [id, version, origin, installation, commit] = fields[..] else {
+                return None;
+            };
+            Some(App {
+                id: id.to_string(),
+                version: version.to_string(),
+                origin: origin.to_string(),
+                installation: installation.to_string(),
+                commit: commit.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// `journalctl -o json` output, one entry per line; runtimes and lines that
+/// don't parse are skipped.
+pub fn parse_journal(text: &str) -> Vec<JournalEntry> {
+    text.lines()
+        .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok())
+        .filter(|entry| entry.reference.starts_with("app/"))
+        .collect()
+}
+
+fn app_id(reference: &str) -> &str {
+    reference.split('/').nth(1).unwrap_or(reference)
+}
+
+// An installed commit by the version it carries, other commits abbreviated
+// as flatpak prints them.
+fn describe_commit(commit: &str, apps: &[App]) -> String {
+    apps.iter()
+        .find(|app| app.commit == commit && !app.version.is_empty())
+        .map_or_else(
+            || commit.chars().take(COMMIT_LEN).collect(),
+            |app| app.version.clone(),
+        )
+}
+
+/// The latest change of every application in `entries`, as the history
+/// holds a package's latest event, tagged `flatpak:<remote>`. Updates carry
+/// only the new version: the commits before them name no version.
+pub fn history(entries: &[JournalEntry], apps: &[App]) -> BTreeMap<String, PackageInfo> {
+    let mut log = String::new();
+    for entry in entries {
+        let Some(date) = entry
+            .time
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+        else {
+            continue;
+        };
+        let (verb, commit) = match entry.operation.as_str() {
+            "deploy install" => ("installed", &entry.commit),
+            "deploy update" => ("upgraded", &entry.commit),
+            "uninstall" => ("removed", &entry.old_commit),
+            _ => continue,
+        };
+        let _ = writeln!(
+            log,
+            "[{}] [ALPM] {verb} {} ({})",
+            history::format_log_date(&date.with_timezone(&Local).fixed_offset()),
+            app_id(&entry.reference),
+            describe_commit(commit, apps)
+        );
+    }
+    let remotes: BTreeMap<&str, &str> = entries
+        .iter()
+        .map(|entry| (app_id(&entry.reference), entry.remote.as_str()))
+        .chain(
+            apps.iter()
+                .map(|app| (app.id.as_str(), app.origin.as_str())),
+        )
+        .collect();
+    let mut history = history::parse_log_entries(log.as_bytes());
+    for (id, info) in &mut history {
+        let remote = remotes.get(id.as_str()).copied().unwrap_or_default();
+        info.source = Some(format!("flatpak:{remote}"));
+    }
+    history
+}
+
+// Where an installation keeps its deployments; other installations than
+// `system` and `user` are configured elsewhere and go without.
+fn installation_dir(installation: &str) -> Option<PathBuf> {
+    match installation {
+        "system" => Some(PathBuf::from(SYSTEM_DIR)),
+        "user" => Some(dirs::data_dir()?.parent()?.join("flatpak")),
+        _ => None,
+    }
+}
+
+// When the app's active deployment last changed, for apps the journal
+// doesn't remember.
+fn deploy_time(app: &App) -> Option<DateTime<Local>> {
+    let active = installation_dir(&app.installation)?
+        .join("app")
+        .join(&app.id)
+        .join("current")
+        .join("active");
+    Some(fs::symlink_metadata(active).ok()?.modified().ok()?.into())
+}
+
+/// Adds the installed applications and their history to `data`. Apps are
+/// installed by hand, so they count as explicit and are left out of a
+/// listing of dependencies.
+pub fn merge(data: &mut PackageData, scope: InstallScope) {
+    let apps = pacman::run("flatpak", &["list", "--app", LIST_COLUMNS])
+        .map(|text| parse_list(&text))
+        .unwrap_or_default();
+    let entries = pacman::run(
+        "journalctl",
+        &["-o", "json", "-q", "--no-pager", MESSAGE_ID],
+    )
+    .map(|text| parse_journal(&text))
+    .unwrap_or_default();
+    let history = history(&entries, &apps);
+    for app in &apps {
+        if !history.contains_key(&app.id)
+            && let Some(time) = deploy_time(app)
+        {
+            data.estimated.insert(app.id.clone(), time.fixed_offset());
+        }
+    }
+    data.history.extend(history);
+    if scope != InstallScope::Dependencies {
+        data.installed.extend(apps.into_iter().map(|app| app.id));
+        data.installed.sort_unstable();
+        data.installed.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Status;
+
+    #[test]
+    fn apps_and_their_history() {
+        let list = "org.mozilla.firefox\t124.0\tflathub\tsystem\tb0f8a9c2e1d34f5a6b7c\n\
+                    org.gimp.GIMP\t2.10.36\tflathub\tuser\t9d8c7b6a5f4e3d2c1b0a\n";
+        let apps = parse_list(list);
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[1].installation, "user");
+
+        let journal = r#"{"__REALTIME_TIMESTAMP":"1705325447000000","OPERATION":"deploy install","REF":"app/org.mozilla.firefox/x86_64/stable","REMOTE":"flathub","COMMIT":"1111aaaa2222bbbb3333"}
+{"__REALTIME_TIMESTAMP":"1705325448000000","OPERATION":"deploy install","REF":"runtime/org.freedesktop.Platform/x86_64/23.08","REMOTE":"flathub","COMMIT":"4444"}
+{"__REALTIME_TIMESTAMP":"1705411847000000","OPERATION":"deploy update","REF":"app/org.mozilla.firefox/x86_64/stable","REMOTE":"flathub","COMMIT":"b0f8a9c2e1d34f5a6b7c","OLD_COMMIT":"1111aaaa2222bbbb3333"}
+{"__REALTIME_TIMESTAMP":"1705498247000000","OPERATION":"uninstall","REF":"app/com.spotify.Client/x86_64/stable","REMOTE":"flathub","OLD_COMMIT":"5555cccc6666dddd7777"}
+not json
+"#;
+        let entries = parse_journal(journal);
+        assert_eq!(entries.len(), 3);
+
+        let history = history(&entries, &apps);
+        let firefox = &history["org.mozilla.firefox"];
+        assert_eq!(firefox.status, Status::Upgraded);
+        assert_eq!(firefox.versions, "124.0");
+        assert_eq!(firefox.source.as_deref(), Some("flatpak:flathub"));
+        let spotify = &history["com.spotify.Client"];
+        assert_eq!(spotify.status, Status::Removed);
+        assert_eq!(spotify.versions, "5555cccc6666");
+        assert!(!history.contains_key("org.freedesktop.Platform"));
+    }
+}
@@ -0,0 +1,181 @@
+//! Installed sizes over time, to trace how the system grew back to the
+//! packages behind it. Sizes are recorded whenever the history cache picks
+//! up new log entries and when a snapshot is saved; each record keeps only
+//! what changed since the one before it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::dirs;
+use crate::exec;
+use crate::history;
+use crate::pacman::LocalDb;
+
+const LOG_FILE: &str = "sizes.jsonl";
+
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Installed size in bytes per package name.
+pub type Sizes = BTreeMap<String, u64>;
+
+/// One recording: the packages whose size differs from the previous one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub date: String,
+    /// The new size, or `null` for a package that went.
+    pub changes: BTreeMap<String, Option<u64>>,
+}
+
+/// In the data directory, `~/.local/share/pkglist/sizes.jsonl`.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(LOG_FILE))
+}
+
+/// Turns recording on; until then, as in library use, [`refresh`] does
+/// nothing.
+pub fn set_path(path: PathBuf) {
+    let _ = LOG_PATH.set(path);
+}
+
+pub fn path() -> Option<&'static Path> {
+    LOG_PATH.get().map(PathBuf::as_path)
+}
+
+/// The installed size of every package in `local`.
+pub fn installed(local: &LocalDb) -> Sizes {
+    local
+        .names()
+        .filter_map(|name| Some((name.to_string(), local.installed_size(name)?)))
+        .collect()
+}
+
+/// Every record in the file at `path`, oldest first; lines that don't parse
+/// are skipped.
+pub fn read(path: &Path) -> io::Result<Vec<Record>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// The sizes as of each record, oldest first.
+pub fn replay(records: &[Record]) -> Vec<(&str, Sizes)> {
+    let mut sizes = Sizes::new();
+    records
+        .iter()
+        .map(|record| {
+            for (name, size) in &record.changes {
+                match size {
+                    Some(size) => sizes.insert(name.clone(), *size),
+                    None => sizes.remove(name),
+                };
+            }
+            (record.date.as_str(), sizes.clone())
+        })
+        .collect()
+}
+
+/// What changed from `previous` to `current`, in the form of a record.
+pub fn delta(previous: &Sizes, current: &Sizes) -> BTreeMap<String, Option<u64>> {
+    let changed = current
+        .iter()
+        .filter(|(name, size)| previous.get(*name) != Some(size))
+        .map(|(name, size)| (name.clone(), Some(*size)));
+    let gone = previous
+        .keys()
+        .filter(|name| !current.contains_key(*name))
+        .map(|name| (name.clone(), None));
+    changed.chain(gone).collect()
+}
+
+/// Appends `current` to the file at `path` unless it matches the latest
+/// record; true when it was written.
+pub fn record(path: &Path, current: &Sizes) -> io::Result<bool> {
+    let records = read(path)?;
+    let previous = replay(&records).pop().map(|(_, sizes)| sizes);
+    let changes = delta(&previous.unwrap_or_default(), current);
+    if changes.is_empty() {
+        return Ok(false);
+    }
+    let record = Record {
+        date: history::format_log_date(&Local::now().fixed_offset()),
+        changes,
+    };
+    let mut text = fs::read(path).unwrap_or_default();
+    if !text.is_empty() && !text.ends_with(b"\n") {
+        text.push(b'\n');
+    }
+    text.extend(serde_json::to_vec(&record)?);
+    text.push(b'\n');
+    exec::write_file(path, &text, Some(0o600))?;
+    Ok(true)
+}
+
+/// Records the installed sizes now, when recording is on. An empty local
+/// database is taken as unreadable rather than as everything removed,
+/// and failures only go to stderr, so recording never fails the caller.
+pub fn refresh() {
+    let Some(path) = path() else {
+        return;
+    };
+    let current = installed(&LocalDb::load());
+    if current.is_empty() {
+        return;
+    }
+    if let Err(e) = record(path, &current) {
+        eprintln!("pkglist: {}: {e}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    fn sizes(entries: &[(&str, u64)]) -> Sizes {
+        entries
+            .iter()
+            .map(|(name, size)| (name.to_string(), *size))
+            .collect()
+    }
+
+    #[test]
+    fn records_hold_deltas() {
+        let dir = std::env::temp_dir().join(format!("pkglist-growth-{}", process::id()));
+        let path = dir.join(LOG_FILE);
+        let first = sizes(&[("bash", 9_000_000), ("vim", 4_000_000)]);
+        let second = sizes(&[("bash", 9_500_000), ("zsh", 7_000_000)]);
+
+        assert!(record(&path, &first).unwrap());
+        assert!(!record(&path, &first).unwrap());
+        assert!(record(&path, &second).unwrap());
+
+        let records = read(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[1].changes,
+            BTreeMap::from([
+                ("bash".to_string(), Some(9_500_000)),
+                ("vim".to_string(), None),
+                ("zsh".to_string(), Some(7_000_000)),
+            ])
+        );
+        let states: Vec<Sizes> = replay(&records)
+            .into_iter()
+            .map(|(_, sizes)| sizes)
+            .collect();
+        assert_eq!(states, [first, second]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,632 @@
+//! Parsing `pacman.log`, including rotated and gzipped logs. Other
+//! backends' logs are read through the same functions, rewritten into
+//! `pacman.log`'s format as they are read.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use chrono::{DateTime, FixedOffset};
+use flate2::read::MultiGzDecoder;
+use lazy_static::lazy_static;
+use memchr::memchr;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::backend;
+use crate::progress;
+
+pub const LOG_PATH: &str = "/var/log/pacman.log";
+static LOG_FILE: OnceLock<PathBuf> = OnceLock::new();
+pub const LOG_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%z";
+/// Shown in place of a date for packages that predate the log.
+pub const UNKNOWN_DATE: &str = "unknown";
+
+#[derive(
+    Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum Status {
+    #[serde(rename = "INS")]
+    Installed,
+    #[serde(rename = "UPG")]
+    Upgraded,
+    #[serde(rename = "REM")]
+    Removed,
+    #[serde(rename = "DWN")]
+    Downgraded,
+    /// The same version installed again.
+    #[serde(rename = "RNS")]
+    Reinstalled,
+}
+
+impl Status {
+    pub const ALL: [Status; 5] = [
+        Status::Installed,
+        Status::Upgraded,
+        Status::Downgraded,
+        Status::Reinstalled,
+        Status::Removed,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Installed => "INS",
+            Status::Upgraded => "UPG",
+            Status::Removed => "REM",
+            Status::Downgraded => "DWN",
+            Status::Reinstalled => "RNS",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PackageInfo {
+    /// When it was logged, or `None` for a package that predates the log.
+    #[serde(with = "log_date")]
+    pub date: Option<DateTime<FixedOffset>>,
+    pub status: Status,
+    /// As logged: `1.0-1` for installs, reinstalls and removals, `1.0-1 -> 1.1-1` for upgrades and downgrades.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub versions: String,
+    /// The label of an imported log this event came from, for events the
+    /// current log doesn't have, or the other source of the package, like
+    /// `flatpak:flathub`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// One logged install, upgrade or removal of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub date: String,
+    pub status: Status,
+    /// As logged: `1.0-1` for installs, reinstalls and removals, `1.0-1 -> 1.1-1` for upgrades and downgrades.
+    pub versions: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncMarkers {
+    pub last_sync: Option<String>,
+    pub last_full_upgrade: Option<String>,
+}
+
+impl SyncMarkers {
+    /// Takes the markers of a later part of the log over these.
+    pub fn merge(&mut self, later: SyncMarkers) {
+        self.last_sync = later.last_sync.or(self.last_sync.take());
+        self.last_full_upgrade = later.last_full_upgrade.or(self.last_full_upgrade.take());
+    }
+}
+
+lazy_static! {
+    static ref LOG_REGEX: Regex = Regex::new(
+        r"\[([0-9T:+-]+)\] \[ALPM\] (installed|upgraded|downgraded|reinstalled|removed) ([^\s(]+)(?: \(([^)]+)\))?"
+    )
+    .unwrap();
+}
+
+/// Reads another log than pacman's own, e.g. one of a chroot; set once at
+/// startup before anything is parsed.
+pub fn set_log_path(path: PathBuf) {
+    let _ = LOG_FILE.set(path);
+}
+
+/// The `--log` path, or the selected backend's log.
+pub fn log_path() -> &'static Path {
+    LOG_FILE
+        .get()
+        .map_or_else(|| backend::current().log_path(), PathBuf::as_path)
+}
+
+// The backend's rewrite of `content`, without copying pacman's own log.
+fn normalize(content: Vec<u8>) -> Vec<u8> {
+    let normalized = match backend::current().normalize_log(&content) {
+        Cow::Owned(normalized) => Some(normalized),
+        Cow::Borrowed(_) => None,
+    };
+    normalized.unwrap_or(content)
+}
+
+// Seconds since the epoch of the last change to `path`.
+fn modified(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(
+        modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?
+            .as_secs(),
+    )
+}
+
+/// The log's size, which the history cache compares to tell whether it
+/// changed. Database logs change in place, so for them it is the latest
+/// modification time of the database or of SQLite's write-ahead log beside
+/// it.
+pub fn get_log_size() -> u64 {
+    let log = log_path();
+    if backend::current().is_database() {
+        let mut wal = log.as_os_str().to_owned();
+        wal.push("-wal");
+        return modified(log).max(modified(Path::new(&wal))).unwrap_or(0);
+    }
+    fs::metadata(log).map(|m| m.len()).unwrap_or(0)
+}
+
+/// The bytes appended to the log since it was `offset` bytes long, with how
+/// many bytes of the log they cover before the backend rewrote them, or `None`
+/// when it no longer continues from there: shrunk, rotated or rewritten, as
+/// far as the byte before `offset` no longer ending a line tells.
+pub fn read_log_tail(offset: u64) -> Option<(Vec<u8>, u64)> {
+    if backend::current().is_database() {
+        return None;
+    }
+    let mut file = fs::File::open(log_path()).ok()?;
+    if offset == 0 || file.metadata().ok()?.len() < offset {
+        return None;
+    }
+    file.seek(SeekFrom::Start(offset - 1)).ok()?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).ok()?;
+    match buffer.first() {
+        Some(b'\n') => {
+            let tail = buffer.split_off(1);
+            let read = tail.len() as u64;
+            Some((normalize(tail), read))
+        }
+        _ => None,
+    }
+}
+
+// `.1`, `.2.gz`, `-20240101.gz`, ... as logrotate names them.
+fn is_rotation_suffix(suffix: &str) -> bool {
+    let suffix = suffix.strip_suffix(".gz").unwrap_or(suffix);
+    let Some(rest) = suffix.strip_prefix(['.', '-']) else {
+        return false;
+    };
+    !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit() || c == '-')
+}
+
+/// Logs rotated away from pacman.log, oldest first by modification time,
+/// which orders both numbered and dated rotations.
+pub fn rotated_logs() -> Vec<PathBuf> {
+    let log = log_path();
+    let (Some(dir), Some(name)) = (log.parent(), log.file_name().and_then(|name| name.to_str()))
+    else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut rotated: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|file_name| file_name.strip_prefix(name))
+                .is_some_and(is_rotation_suffix)
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    rotated.sort();
+    rotated.into_iter().map(|(_, path)| path).collect()
+}
+
+fn read_rotated(path: &Path) -> io::Result<Vec<u8>> {
+    let file = fs::File::open(path)?;
+    let mut buffer = Vec::new();
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        MultiGzDecoder::new(file).read_to_end(&mut buffer)?;
+    } else {
+        io::BufReader::new(file).read_to_end(&mut buffer)?;
+    }
+    Ok(buffer)
+}
+
+/// The whole history: rotated logs in chronological order, then the log
+/// itself, or everything a database log holds.
+pub fn read_log_file() -> io::Result<Vec<u8>> {
+    let backend = backend::current();
+    if backend.is_database() {
+        return backend.read_database(log_path());
+    }
+    let mut file = fs::File::open(log_path())?;
+    let metadata = file.metadata()?;
+    let mut buffer = Vec::with_capacity(metadata.len() as usize);
+    for path in rotated_logs() {
+        match read_rotated(&path) {
+            Ok(content) => {
+                buffer.extend_from_slice(&content);
+                if !buffer.ends_with(b"\n") {
+                    buffer.push(b'\n');
+                }
+            }
+            Err(e) => eprintln!("pkglist: {}: {e}", path.display()),
+        }
+    }
+    file.read_to_end(&mut buffer)?;
+    Ok(normalize(buffer))
+}
+
+// Calls `f` with (date, status, name, versions) for every package event,
+// borrowing from the log buffer.
+fn scan_events<'a>(log_content: &'a [u8], mut f: impl FnMut(&'a str, Status, &'a str, &'a str)) {
+    let mut pos = 0;
+    let mut task = progress::Task::start("parse-log", "bytes", Some(log_content.len() as u64));
+
+    while let Some(newline_pos) = memchr(b'\n', &log_content[pos..]) {
+        let line_end = pos + newline_pos;
+        let line = &log_content[pos..line_end];
+        pos = line_end + 1;
+        task.set(pos as u64);
+
+        if line.len() < 50 {
+            continue;
+        }
+
+        let Ok(line) = std::str::from_utf8(line) else {
+            continue;
+        };
+
+        if let Some(caps) = LOG_REGEX.captures(line) {
+            let date_str = caps.get(1).unwrap().as_str();
+            let action = caps.get(2).unwrap().as_str();
+            let pkg_name = caps.get(3).unwrap().as_str();
+            let versions = caps.get(4).map_or("", |versions| versions.as_str());
+
+            let status = match action {
+                "installed" => Status::Installed,
+                "upgraded" => Status::Upgraded,
+                "removed" => Status::Removed,
+                "downgraded" => Status::Downgraded,
+                "reinstalled" => Status::Reinstalled,
+                _ => continue,
+            };
+
+            f(date_str, status, pkg_name, versions);
+        }
+    }
+}
+
+pub fn parse_log_entries(log_content: &[u8]) -> BTreeMap<String, PackageInfo> {
+    // Borrow names and dates from the log buffer while scanning so that a
+    // package seen thousands of times is only allocated once at the end.
+    let mut latest: HashMap<&str, (&str, Status, &str)> = HashMap::new();
+    scan_events(log_content, |date, status, name, versions| {
+        latest.insert(name, (date, status, versions));
+    });
+
+    latest
+        .into_iter()
+        .map(|(pkg, (date, status, versions))| {
+            (
+                pkg.to_string(),
+                PackageInfo {
+                    date: parse_log_date(date),
+                    status,
+                    versions: versions.to_string(),
+                    source: None,
+                },
+            )
+        })
+        .collect()
+}
+
+/// The latest logged event of every package in one log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageHistory {
+    packages: BTreeMap<String, PackageInfo>,
+}
+
+impl PackageHistory {
+    /// Parses the log at `path`, which may be gzipped like a rotated log.
+    /// Only that file is read; [`read_log_file`] adds the rotated ones.
+    pub fn from_log(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_bytes(&read_rotated(path.as_ref())?))
+    }
+
+    pub fn from_bytes(log_content: &[u8]) -> Self {
+        PackageHistory {
+            packages: parse_log_entries(log_content),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PackageInfo> {
+        self.packages.get(name)
+    }
+
+    /// Packages in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PackageInfo)> {
+        self.packages
+            .iter()
+            .map(|(name, info)| (name.as_str(), info))
+    }
+
+    pub fn len(&self) -> usize {
+        self.packages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    pub fn into_map(self) -> BTreeMap<String, PackageInfo> {
+        self.packages
+    }
+}
+
+/// Every event of every package, oldest first, where `parse_log_entries`
+/// keeps only the latest.
+pub fn parse_events(log_content: &[u8]) -> BTreeMap<String, Vec<Event>> {
+    let mut events: BTreeMap<String, Vec<Event>> = BTreeMap::new();
+    scan_events(log_content, |date, status, name, versions| {
+        let event = Event {
+            date: date.to_string(),
+            status,
+            versions: versions.to_string(),
+        };
+        match events.get_mut(name) {
+            Some(list) => list.push(event),
+            None => {
+                events.insert(name.to_string(), vec![event]);
+            }
+        }
+    });
+    events
+}
+
+pub fn parse_log_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_str(date, LOG_DATE_FORMAT).ok()
+}
+
+pub fn format_log_date(date: &DateTime<FixedOffset>) -> String {
+    date.format(LOG_DATE_FORMAT).to_string()
+}
+
+/// A date as shown to people: in the log's format, or [`UNKNOWN_DATE`].
+pub fn date_or_unknown(date: Option<DateTime<FixedOffset>>) -> String {
+    date.map_or_else(|| UNKNOWN_DATE.to_string(), |date| format_log_date(&date))
+}
+
+/// Serde for optional dates, kept in the log's own format and `null` when
+/// unknown. A date that doesn't parse, as in state files from before dates
+/// were typed, reads as unknown.
+pub mod log_date {
+    use chrono::{DateTime, FixedOffset};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<DateTime<FixedOffset>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_str(&super::format_log_date(date)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<FixedOffset>>, D::Error> {
+        let date: Option<String> = Option::deserialize(deserializer)?;
+        Ok(date.as_deref().and_then(super::parse_log_date))
+    }
+}
+
+/// `pacman -Sy` logs the sync and `-Su` logs the upgrade start, so comparing
+/// the two tells whether packages were installed against fresher databases
+/// than the system was upgraded to.
+pub fn scan_sync_markers(log_content: &[u8]) -> SyncMarkers {
+    let mut markers = SyncMarkers::default();
+
+    for line in log_content.split(|&b| b == b'\n') {
+        let Ok(line) = std::str::from_utf8(line) else {
+            continue;
+        };
+        let Some(rest) = line.strip_prefix('[') else {
+            continue;
+        };
+        let Some((date, message)) = rest.split_once("] [PACMAN] ") else {
+            continue;
+        };
+
+        if message.starts_with("synchronizing package lists") {
+            markers.last_sync = Some(date.to_string());
+        } else if message.starts_with("starting full system upgrade") {
+            markers.last_full_upgrade = Some(date.to_string());
+        }
+    }
+    markers
+}
+
+/// Every `[PACMAN] Running '...'` line as (date, command). Unlike transactions
+/// this includes database-only operations such as `pacman -D --asexplicit`.
+pub fn scan_commands(log_content: &[u8]) -> Vec<(String, String)> {
+    log_content
+        .split(|&b| b == b'\n')
+        .filter_map(|line| std::str::from_utf8(line).ok())
+        .filter_map(|line| {
+            let (date, message) = line.strip_prefix('[')?.split_once("] [PACMAN] Running '")?;
+            let command = message.strip_suffix('\'')?;
+            Some((date.to_string(), command.to_string()))
+        })
+        .collect()
+}
+
+pub fn first_install(log_content: &[u8], package: &str) -> Option<String> {
+    let needle = format!("] [ALPM] installed {package} (");
+    log_content
+        .split(|&b| b == b'\n')
+        .filter_map(|line| std::str::from_utf8(line).ok())
+        .find(|line| line.contains(&needle))
+        .and_then(|line| line.strip_prefix('['))
+        .and_then(|line| line.split_once(']'))
+        .map(|(date, _)| date.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+
+    const LOG: &str = "\
+[2024-01-02T10:00:00+0100] [PACMAN] Running 'pacman -Syu'
+[2024-01-02T10:00:00+0100] [PACMAN] synchronizing package lists
+[2024-01-02T10:00:01+0100] [PACMAN] starting full system upgrade
+[2024-01-02T10:00:05+0100] [ALPM] installed vim (9.0.0000-1)
+[2024-01-02T10:00:06+0100] [ALPM] installed nano (7.2-1)
+[2024-02-03T11:00:00+0100] [ALPM] upgraded vim (9.0.0000-1 -> 9.1.0000-1)
+[2024-02-03T11:00:01+0100] [ALPM] removed nano (7.2-1)
+[2024-02-04T09:00:00+0100] [PACMAN] synchronizing package lists
+";
+
+    #[test]
+    fn entries_keep_the_latest_event() {
+        let entries = parse_log_entries(LOG.as_bytes());
+        assert_eq!(entries.len(), 2);
+        let vim = &entries["vim"];
+        assert_eq!(vim.status, Status::Upgraded);
+        assert_eq!(vim.date, parse_log_date("2024-02-03T11:00:00+0100"));
+        assert_eq!(vim.versions, "9.0.0000-1 -> 9.1.0000-1");
+        assert_eq!(entries["nano"].status, Status::Removed);
+    }
+
+    #[test]
+    fn events_are_kept_in_order() {
+        let events = parse_events(LOG.as_bytes());
+        let statuses: Vec<Status> = events["vim"].iter().map(|event| event.status).collect();
+        assert_eq!(statuses, [Status::Installed, Status::Upgraded]);
+    }
+
+    #[test]
+    fn downgrades_and_reinstalls() {
+        let log = "\
+[2024-03-01T10:00:00+0100] [ALPM] downgraded vim (9.1.0000-1 -> 9.0.0000-1)
+[2024-03-02T10:00:00+0100] [ALPM] reinstalled nano (7.2-1)
+";
+        let entries = parse_log_entries(log.as_bytes());
+        assert_eq!(entries["vim"].status, Status::Downgraded);
+        assert_eq!(entries["vim"].versions, "9.1.0000-1 -> 9.0.0000-1");
+        assert_eq!(entries["nano"].status, Status::Reinstalled);
+        assert_eq!(entries["nano"].versions, "7.2-1");
+    }
+
+    #[test]
+    fn unterminated_and_foreign_lines_are_skipped() {
+        let log = "[2024-01-02T10:00:05+0100] [ALPM-SCRIPTLET] installed nothing here (1)\n\
+                   [2024-01-02T10:00:05+0100] [ALPM] installed vim (9.0.0000-1)";
+        assert!(parse_log_entries(log.as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn sync_markers_take_the_latest_lines() {
+        let markers = scan_sync_markers(LOG.as_bytes());
+        assert_eq!(
+            markers.last_sync.as_deref(),
+            Some("2024-02-04T09:00:00+0100")
+        );
+        assert_eq!(
+            markers.last_full_upgrade.as_deref(),
+            Some("2024-01-02T10:00:01+0100")
+        );
+
+        let mut earlier = SyncMarkers {
+            last_sync: Some("a".to_string()),
+            last_full_upgrade: Some("b".to_string()),
+        };
+        earlier.merge(SyncMarkers {
+            last_sync: Some("c".to_string()),
+            last_full_upgrade: None,
+        });
+        assert_eq!(earlier.last_sync.as_deref(), Some("c"));
+        assert_eq!(earlier.last_full_upgrade.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn commands_and_first_install() {
+        assert_eq!(
+            scan_commands(LOG.as_bytes()),
+            [(
+                "2024-01-02T10:00:00+0100".to_string(),
+                "pacman -Syu".to_string()
+            )]
+        );
+        assert_eq!(
+            first_install(LOG.as_bytes(), "nano").as_deref(),
+            Some("2024-01-02T10:00:06+0100")
+        );
+        assert_eq!(first_install(LOG.as_bytes(), "nan"), None);
+    }
+
+    #[test]
+    fn log_dates_round_trip() {
+        let date = parse_log_date("2024-02-03T11:00:00+0100").unwrap();
+        assert_eq!(format_log_date(&date), "2024-02-03T11:00:00+0100");
+        assert_eq!(parse_log_date(UNKNOWN_DATE), None);
+    }
+
+    #[test]
+    fn package_dates_serialize_in_the_log_format() {
+        let info = PackageInfo {
+            date: parse_log_date("2024-02-03T11:00:00+0100"),
+            status: Status::Upgraded,
+            versions: String::new(),
+            source: None,
+        };
+        let json = serde_json::to_string(&info).unwrap();
+        assert_eq!(
+            json,
+            r#"{"date":"2024-02-03T11:00:00+0100","status":"UPG"}"#
+        );
+        assert_eq!(serde_json::from_str::<PackageInfo>(&json).unwrap(), info);
+
+        let unknown = PackageInfo { date: None, ..info };
+        let json = serde_json::to_string(&unknown).unwrap();
+        assert_eq!(json, r#"{"date":null,"status":"UPG"}"#);
+        // The sentinel older state files carry reads as unknown.
+        let old: PackageInfo =
+            serde_json::from_str(r#"{"date":"unknown","status":"UPG"}"#).unwrap();
+        assert_eq!(old, unknown);
+    }
+
+    #[test]
+    fn rotation_suffixes() {
+        for suffix in [".1", ".2.gz", "-20240101", "-20240101.gz"] {
+            assert!(is_rotation_suffix(suffix), "{suffix}");
+        }
+        for suffix in ["", ".gz", ".bak", ".1.bak", "-"] {
+            assert!(!is_rotation_suffix(suffix), "{suffix}");
+        }
+    }
+
+    #[test]
+    fn history_from_plain_and_gzipped_logs() {
+        let dir = std::env::temp_dir().join(format!("pkglist-core-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let plain = dir.join("pacman.log");
+        fs::write(&plain, LOG).unwrap();
+        let gzipped = dir.join("pacman.log.1.gz");
+        let mut encoder =
+            GzEncoder::new(fs::File::create(&gzipped).unwrap(), Compression::default());
+        encoder.write_all(LOG.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let history = PackageHistory::from_log(&plain).unwrap();
+        assert_eq!(history, PackageHistory::from_log(&gzipped).unwrap());
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get("vim").unwrap().status, Status::Upgraded);
+        let names: Vec<&str> = history.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["nano", "vim"]);
+        assert!(PackageHistory::from_log(dir.join("missing.log")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+//! Logs of earlier installs, imported to fill in dates the current log
+//! lacks.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::dirs;
+use crate::exec;
+use crate::history::{self, LOG_DATE_FORMAT, PackageInfo};
+
+const IMPORTS_DIR: &str = "imports";
+
+// The latest event per package from a log of an earlier install, kept in
+// the state directory so it survives cache rebuilds and reinstalls.
+#[derive(Serialize, Deserialize)]
+struct ImportedLog {
+    source: String,
+    imported: String,
+    packages: BTreeMap<String, PackageInfo>,
+}
+
+fn imports_dir() -> Option<PathBuf> {
+    dirs::state_dir().map(|dir| dir.join(IMPORTS_DIR))
+}
+
+fn is_later(date: Option<DateTime<FixedOffset>>, than: Option<DateTime<FixedOffset>>) -> bool {
+    match (date, than) {
+        (Some(date), Some(than)) => date > than,
+        _ => false,
+    }
+}
+
+fn load_all() -> Vec<(String, ImportedLog)> {
+    let Some(entries) = imports_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut imports: Vec<(String, ImportedLog)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let label = path.file_stem()?.to_string_lossy().into_owned();
+            let log = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+            Some((label, log))
+        })
+        .collect();
+    imports.sort_by(|a, b| a.0.cmp(&b.0));
+    imports
+}
+
+/// Adds imported events to `history` where the current log has nothing
+/// newer for the package, labelled with where they came from.
+pub fn merge(history: &mut BTreeMap<String, PackageInfo>) {
+    for (label, log) in load_all() {
+        for (name, info) in log.packages {
+            let newer = history
+                .get(&name)
+                .is_none_or(|current| is_later(info.date, current.date));
+            if newer {
+                let source = Some(label.clone());
+                history.insert(name, PackageInfo { source, ..info });
+            }
+        }
+    }
+}
+
+fn valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && !label.starts_with('.')
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+/// Parses `file` as a pacman log and stores its packages under `label`,
+/// replacing an earlier import with the same label. Returns how many
+/// packages it found.
+pub fn import(file: &Path, label: &str) -> io::Result<usize> {
+    if !valid_label(label) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid label '{label}': use letters, digits, '-', '_' and '.'"),
+        ));
+    }
+    let dir = imports_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no state directory"))?;
+    let content = fs::read(file)?;
+    let packages = history::parse_log_entries(&content);
+    if packages.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: no package events found", file.display()),
+        ));
+    }
+
+    let count = packages.len();
+    let log = ImportedLog {
+        source: file.display().to_string(),
+        imported: Local::now().format(LOG_DATE_FORMAT).to_string(),
+        packages,
+    };
+    exec::write_file(
+        &dir.join(format!("{label}.json")),
+        &serde_json::to_vec(&log)?,
+        None,
+    )?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels() {
+        assert!(valid_label("old-laptop_2019.1"));
+        assert!(!valid_label(""));
+        assert!(!valid_label(".hidden"));
+        assert!(!valid_label("../escape"));
+        assert!(!valid_label("with space"));
+    }
+
+    #[test]
+    fn later_dates_compare_across_offsets() {
+        let date = history::parse_log_date;
+        assert!(is_later(
+            date("2024-01-02T10:00:00+0000"),
+            date("2024-01-02T10:30:00+0100")
+        ));
+        assert!(!is_later(
+            date("2024-01-02T10:00:00+0000"),
+            date("2024-01-02T10:00:00+0000")
+        ));
+        assert!(!is_later(None, date("2024-01-02T10:00:00+0000")));
+    }
+}
@@ -0,0 +1,110 @@
+//! How far an upgrade moved a package: a major, minor or patch release, or
+//! only a rebuild. Package versions follow no single scheme, so this is a
+//! best-effort reading of semver-like upstream versions that is right for
+//! most packages and merely coarse for the rest.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Jump {
+    Major,
+    Minor,
+    Patch,
+    /// The same upstream version, repackaged: only `pkgrel` or the
+    /// distribution's revision changed.
+    Rebuild,
+}
+
+impl Jump {
+    pub const ALL: [Jump; 4] = [Jump::Major, Jump::Minor, Jump::Patch, Jump::Rebuild];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Jump::Major => "major",
+            Jump::Minor => "minor",
+            Jump::Patch => "patch",
+            Jump::Rebuild => "rebuild",
+        }
+    }
+}
+
+// `2:9.1.0-1` → (`2`, `9.1.0`): the epoch, `0` when there is none, and the
+// upstream version without pacman's pkgrel or a Debian or rpm revision.
+fn upstream(version: &str) -> (&str, &str) {
+    let (epoch, rest) = version.split_once(':').unwrap_or(("0", version));
+    let upstream = rest.rsplit_once('-').map_or(rest, |(upstream, _)| upstream);
+    (epoch, upstream)
+}
+
+// `1.2.3`, `1_2rc3`, `20240115.r12.gabc` → their numbers, compared as
+// numbers; parts without digits, like git hashes, are left out.
+fn components(upstream: &str) -> Vec<u64> {
+    upstream
+        .split(['.', '_', '+', '~'])
+        .filter_map(|part| {
+            let digits: String = part
+                .chars()
+                .skip_while(|c| !c.is_ascii_digit())
+                .take_while(char::is_ascii_digit)
+                .collect();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+/// Classifies an upgrade from its logged versions, `old -> new`; `None`
+/// for anything else, like the single version of an install.
+///
+/// A changed epoch counts as major, since it marks a version scheme that
+/// restarted. As in semver, while the major version is 0 a minor bump
+/// counts as major, and a patch bump as minor.
+pub fn classify(versions: &str) -> Option<Jump> {
+    let (old, new) = versions.split_once(" -> ")?;
+    let (old_epoch, old) = upstream(old);
+    let (new_epoch, new) = upstream(new);
+    if old_epoch != new_epoch {
+        return Some(Jump::Major);
+    }
+    if old == new {
+        return Some(Jump::Rebuild);
+    }
+    let (old, new) = (components(old), components(new));
+    let changed = old
+        .iter()
+        .zip(&new)
+        .position(|(a, b)| a != b)
+        .unwrap_or(old.len().min(new.len()));
+    let shift = usize::from(old.first() == Some(&0) && new.first() == Some(&0));
+    Some(match changed.saturating_sub(shift) {
+        0 => Jump::Major,
+        1 => Jump::Minor,
+        _ => Jump::Patch,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_jumps() {
+        let cases = [
+            ("6.7.9.arch1-1 -> 6.8.1.arch1-1", Some(Jump::Minor)),
+            ("1.2.3-1 -> 2.0.0-1", Some(Jump::Major)),
+            ("1.2.3-1 -> 1.2.4-1", Some(Jump::Patch)),
+            ("1.2.3-1 -> 1.2.3-2", Some(Jump::Rebuild)),
+            ("1:1.2-1 -> 2:1.0-1", Some(Jump::Major)),
+            ("0.9.1-1 -> 0.10.0-1", Some(Jump::Major)),
+            ("0.9.1-1 -> 0.9.2-1", Some(Jump::Minor)),
+            ("1.2-1 -> 1.2.1-1", Some(Jump::Patch)),
+            ("2:9.1.0-1 -> 2:9.1.0100-1", Some(Jump::Patch)),
+            ("20240101-1 -> 20240215-1", Some(Jump::Major)),
+            ("r120.abc1234-1 -> r131.def5678-1", Some(Jump::Major)),
+            ("1.2.3-1", None),
+        ];
+        for (versions, jump) in cases {
+            assert_eq!(classify(versions), jump, "{versions}");
+        }
+    }
+}
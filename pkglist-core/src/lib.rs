@@ -0,0 +1,36 @@
+//! The parts of pkglist that know about pacman: the `pacman.log` parser,
+//! the history cache built from it and queries against the package
+//! databases.
+//!
+//! ```no_run
+//! use pkglist_core::history::{self, PackageHistory};
+//!
+//! let history = PackageHistory::from_log("/var/log/pacman.log")?;
+//! if let Some(info) = history.get("linux") {
+//!     let date = history::date_or_unknown(info.date);
+//!     println!("linux: {} {} at {date}", info.status.as_str(), info.versions);
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! [`packages::PackageData::load`] goes further and combines the log with
+//! what is installed right now, through the same cache the `pkglist`
+//! binary keeps.
+
+pub mod actions;
+pub mod apt;
+pub mod backend;
+pub mod cache;
+pub mod db;
+pub mod dirs;
+pub mod dnf;
+pub mod exec;
+pub mod flatpak;
+pub mod growth;
+pub mod history;
+pub mod imports;
+pub mod jump;
+pub mod packages;
+pub mod pacman;
+pub mod progress;
+pub mod size;
@@ -0,0 +1,327 @@
+//! Installed packages with their latest logged event.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use chrono::{DateTime, FixedOffset, Local};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::backend;
+use crate::cache::{self, CacheData};
+use crate::growth;
+use crate::history::{self, PackageInfo, Status, SyncMarkers};
+use crate::imports;
+use crate::pacman::{self, InstallScope, LocalDb};
+
+pub struct PackageData {
+    pub history: BTreeMap<String, PackageInfo>,
+    pub installed: Vec<String>,
+    pub markers: SyncMarkers,
+    /// Approximate install dates of packages that predate the log.
+    pub estimated: HashMap<String, DateTime<FixedOffset>>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone, Copy)]
+pub struct PackageRecord<'a> {
+    pub name: &'a str,
+    /// Log timestamp, or null for a package that predates the log and has no recorded install date
+    #[serde(serialize_with = "history::log_date::serialize")]
+    #[schemars(with = "Option<String>")]
+    pub date: Option<DateTime<FixedOffset>>,
+    pub status: Status,
+    /// Upstream project URL from the local package database
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<&'a str>,
+    /// Versions as logged: `1.0-1` for installs, reinstalls and removals, `1.0-1 -> 1.1-1` for upgrades and downgrades
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versions: Option<&'a str>,
+    /// The date comes from the local package database rather than the log, for packages that predate it
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub estimated: bool,
+}
+
+impl<'a> PackageRecord<'a> {
+    pub fn new(name: &'a str, date: Option<DateTime<FixedOffset>>, status: Status) -> Self {
+        PackageRecord {
+            name,
+            date,
+            status,
+            url: None,
+            versions: None,
+            estimated: false,
+        }
+    }
+
+    /// The latest logged event of `name`.
+    pub fn from_info(name: &'a str, info: &'a PackageInfo) -> Self {
+        PackageRecord {
+            versions: Some(info.versions.as_str()).filter(|versions| !versions.is_empty()),
+            ..PackageRecord::new(name, info.date, info.status)
+        }
+    }
+
+    pub fn with_local(mut self, local: &'a LocalDb) -> Self {
+        self.url = local.field(self.name, "URL");
+        self
+    }
+}
+
+// Install dates for the installed packages missing from the history. The
+// local database only knows the latest install or upgrade, which for these
+// packages is the closest thing to a first one.
+fn estimate_dates(
+    installed: &[String],
+    history: &BTreeMap<String, PackageInfo>,
+) -> HashMap<String, DateTime<FixedOffset>> {
+    if installed.iter().all(|name| history.contains_key(name)) {
+        return HashMap::new();
+    }
+    let mut times = pacman::install_times();
+    installed
+        .iter()
+        .filter(|name| !history.contains_key(*name))
+        .filter_map(|name| {
+            let time = DateTime::<Local>::from(times.remove(name)?);
+            Some((name.clone(), time.fixed_offset()))
+        })
+        .collect()
+}
+
+impl PackageData {
+    /// Without a cache file (`--no-cache`) the log is parsed in full.
+    /// `installed` holds the packages of `scope`.
+    pub fn load(cache_file: Option<&Path>, scope: InstallScope) -> Self {
+        let mut explicit = backend::current().read_installed(InstallScope::Explicit);
+        explicit.sort_unstable();
+        // The cache is keyed on the explicit packages whatever the scope, so
+        // switching scopes doesn't rebuild it.
+        let current_pkg_hash = cache::calculate_pkg_hash(&explicit);
+        let installed = match scope {
+            InstallScope::Explicit => explicit,
+            scope => {
+                let mut installed = backend::current().read_installed(scope);
+                installed.sort_unstable();
+                installed
+            }
+        };
+
+        if installed.is_empty() {
+            return PackageData {
+                history: BTreeMap::new(),
+                installed,
+                markers: SyncMarkers::default(),
+                estimated: HashMap::new(),
+            };
+        }
+
+        let current_log_size = history::get_log_size();
+
+        let _lock = cache_file.map(cache::lock);
+        let cached = cache_file.and_then(cache::load_cache);
+        // Whenever the cache takes in new log entries, the installed sizes
+        // are recorded too.
+        let save = |data: &CacheData| {
+            if let Some(path) = cache_file {
+                let _ = cache::save_cache(path, data);
+                growth::refresh();
+            }
+        };
+        let cache_data = match cached {
+            Some(data)
+                if data.is_current()
+                    && data.pkg_hash == current_pkg_hash
+                    && data.last_log_size == current_log_size =>
+            {
+                data
+            }
+            // The log only grew: parse what was appended and merge it in.
+            Some(mut data)
+                if data.is_current()
+                    && data.last_log_size < current_log_size
+                    && let Some((tail, read)) = history::read_log_tail(data.last_log_size) =>
+            {
+                data.data.extend(history::parse_log_entries(&tail));
+                data.markers.merge(history::scan_sync_markers(&tail));
+                data.last_log_size += read;
+                data.pkg_hash = current_pkg_hash;
+                if let Some(before) = &data.compacted_before {
+                    cache::prune(&mut data.data, before);
+                }
+                save(&data);
+                data
+            }
+            _ => {
+                let log_content = history::read_log_file().unwrap_or_default();
+                let mut cache_data = CacheData {
+                    format: cache::CACHE_FORMAT,
+                    pkg_hash: current_pkg_hash,
+                    last_log_size: current_log_size,
+                    log: Some(history::log_path().to_path_buf()),
+                    data: history::parse_log_entries(&log_content),
+                    markers: history::scan_sync_markers(&log_content),
+                    // A compaction keeps applying to rebuilds of the cache.
+                    compacted_before: cached.and_then(|data| data.compacted_before),
+                };
+                if let Some(before) = &cache_data.compacted_before {
+                    cache::prune(&mut cache_data.data, before);
+                }
+                save(&cache_data);
+                cache_data
+            }
+        };
+
+        let mut history = cache_data.data;
+        imports::merge(&mut history);
+        let estimated = estimate_dates(&installed, &history);
+
+        PackageData {
+            history,
+            installed,
+            markers: cache_data.markers,
+            estimated,
+        }
+    }
+
+    // An install of a package the log never mentions, dated from the local
+    // database when it has a date.
+    fn unlogged<'a>(&'a self, name: &'a str) -> PackageRecord<'a> {
+        match self.estimated.get(name) {
+            Some(date) => PackageRecord {
+                estimated: true,
+                ..PackageRecord::new(name, Some(*date), Status::Installed)
+            },
+            None => PackageRecord::new(name, None, Status::Installed),
+        }
+    }
+
+    /// The latest logged event for `name`, or an estimated install for
+    /// packages that predate the log.
+    pub fn record<'a>(&'a self, name: &'a str) -> PackageRecord<'a> {
+        match self.history.get(name) {
+            Some(info) => PackageRecord::from_info(name, info),
+            None => self.unlogged(name),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.installed.is_empty()
+    }
+
+    pub fn records_by_date(&self) -> Vec<PackageRecord<'_>> {
+        let mut pkg_set: HashMap<&str, PackageRecord<'_>> =
+            HashMap::with_capacity(self.history.len() + self.installed.len());
+
+        for (pkg, info) in &self.history {
+            pkg_set.insert(pkg, PackageRecord::from_info(pkg, info));
+        }
+
+        for pkg in &self.installed {
+            pkg_set.entry(pkg).or_insert_with(|| self.unlogged(pkg));
+        }
+
+        let mut records: Vec<_> = pkg_set.into_values().collect();
+        // Undated packages come first, as the oldest; ties go by name.
+        records.sort_unstable_by_key(|record| (record.date, record.name));
+        records
+    }
+
+    /// Both inputs are ordered by name, so a merge-join lets every record be
+    /// handed out as soon as it is resolved instead of collecting the full list.
+    pub fn for_each_by_name<E>(
+        &self,
+        mut f: impl FnMut(PackageRecord<'_>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mut installed = self.installed.iter().map(String::as_str).peekable();
+
+        for (pkg, info) in &self.history {
+            while let Some(name) = installed.next_if(|name| *name <= pkg.as_str()) {
+                if name != pkg {
+                    f(self.unlogged(name))?;
+                }
+            }
+            f(PackageRecord::from_info(pkg, info))?;
+        }
+
+        for name in installed {
+            f(self.unlogged(name))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> PackageData {
+        let log = "\
+[2024-01-02T10:00:05+0100] [ALPM] installed vim (9.0.0000-1)
+[2024-01-02T10:00:06+0100] [ALPM] installed nano (7.2-1)
+[2024-02-03T11:00:00+0100] [ALPM] upgraded vim (9.0.0000-1 -> 9.1.0000-1)
+[2024-02-03T11:00:01+0100] [ALPM] removed nano (7.2-1)
+";
+        PackageData {
+            history: history::parse_log_entries(log.as_bytes()),
+            installed: ["bash", "glibc", "vim"].map(String::from).to_vec(),
+            markers: SyncMarkers::default(),
+            estimated: HashMap::from([(
+                "glibc".to_string(),
+                history::parse_log_date("2023-06-01T08:00:00+0000").unwrap(),
+            )]),
+        }
+    }
+
+    #[test]
+    fn records_by_date_put_unknown_dates_first() {
+        let data = data();
+        let names: Vec<&str> = data
+            .records_by_date()
+            .iter()
+            .map(|record| record.name)
+            .collect();
+        assert_eq!(names, ["bash", "glibc", "vim", "nano"]);
+    }
+
+    #[test]
+    fn records_by_name_merge_log_and_installed() {
+        let data = data();
+        let mut records = Vec::new();
+        data.for_each_by_name(|record| {
+            records.push((record.name.to_string(), record.status, record.estimated));
+            Ok::<(), ()>(())
+        })
+        .unwrap();
+        assert_eq!(
+            records,
+            [
+                ("bash".to_string(), Status::Installed, false),
+                ("glibc".to_string(), Status::Installed, true),
+                ("nano".to_string(), Status::Removed, false),
+                ("vim".to_string(), Status::Upgraded, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn records() {
+        let data = data();
+        let vim = data.record("vim");
+        assert_eq!(vim.versions, Some("9.0.0000-1 -> 9.1.0000-1"));
+        assert_eq!(
+            vim.date
+                .map(|date| history::format_log_date(&date))
+                .as_deref(),
+            Some("2024-02-03T11:00:00+0100")
+        );
+        let bash = data.record("bash");
+        assert_eq!(
+            (bash.date, bash.versions, bash.estimated),
+            (None, None, false)
+        );
+        assert_eq!(
+            serde_json::to_value(bash).unwrap(),
+            serde_json::json!({ "name": "bash", "date": null, "status": "INS" })
+        );
+    }
+}
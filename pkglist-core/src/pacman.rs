@@ -0,0 +1,699 @@
+//! Queries against pacman and its local, sync and package cache databases.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+use crate::backend::{self, Backend};
+use crate::db;
+use crate::exec::{self, Limits};
+use crate::history;
+use crate::size;
+
+pub const LOCAL_DB_DIR: &str = "/var/lib/pacman/local";
+pub const SYNC_DB_DIR: &str = "/var/lib/pacman/sync";
+pub const PKG_CACHE_DIR: &str = "/var/cache/pacman/pkg";
+
+pub type InfoBlock = BTreeMap<String, String>;
+
+fn command(program: &str, args: &[&str]) -> Command {
+    let mut command = Command::new(program);
+    command.args(args).env("LC_ALL", "C");
+    command
+}
+
+/// Field names in `-Qi`/`-Si` output are translated, so pin the locale. A
+/// command that fails or times out gives `None`.
+pub fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = exec::query(&mut command(program, args), |_| false).ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub struct Captured {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// For queries like `-Qk` that report problems through stderr and the exit
+// status, both streams are needed regardless of success.
+fn run_capture(program: &str, args: &[&str], limits: Limits) -> Option<Captured> {
+    let output = exec::query_within(&mut command(program, args), limits, |_| false).ok()?;
+    Some(Captured {
+        success: output.status.success(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    })
+}
+
+fn lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Which installed packages to read: the explicitly installed ones (`-Qe`),
+/// the dependencies (`-Qd`) or all of them.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstallScope {
+    #[default]
+    Explicit,
+    #[serde(rename = "deps-only")]
+    Dependencies,
+    All,
+}
+
+pub fn read_installed(scope: InstallScope) -> Vec<String> {
+    if exec::is_sandboxed() {
+        let mut names: Vec<String> = db::local_descs()
+            .iter()
+            .filter(|desc| match scope {
+                InstallScope::Explicit => db::is_explicit(desc),
+                InstallScope::Dependencies => !db::is_explicit(desc),
+                InstallScope::All => true,
+            })
+            .filter_map(|desc| db::name(desc).map(str::to_string))
+            .collect();
+        names.sort_unstable();
+        return names;
+    }
+    let flag = match scope {
+        InstallScope::Explicit => "-Qeq",
+        InstallScope::Dependencies => "-Qdq",
+        InstallScope::All => "-Qq",
+    };
+    run("pacman", &[flag])
+        .map(|text| lines(&text))
+        .unwrap_or_default()
+}
+
+/// `name version` lines as printed by `pacman -Q`, locally or over ssh.
+pub fn parse_versions(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter_map(|line| {
+            let (name, version) = line.split_once(' ')?;
+            Some((name.to_string(), version.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Every installed package with its version.
+pub fn installed_versions() -> BTreeMap<String, String> {
+    if exec::is_sandboxed() {
+        return db::local_descs()
+            .iter()
+            .filter_map(|desc| Some((db::name(desc)?.to_string(), db::version(desc)?.to_string())))
+            .collect();
+    }
+    run("pacman", &["-Q"])
+        .map(|text| parse_versions(&text))
+        .unwrap_or_default()
+}
+
+/// Installed packages in no sync database, like `-Qm`. In sandbox mode
+/// nothing counts as foreign when the sync databases can't be read.
+pub fn foreign_packages() -> Vec<String> {
+    if exec::is_sandboxed() {
+        let synced: HashSet<String> = db::sync_packages()
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect();
+        if synced.is_empty() {
+            return Vec::new();
+        }
+        let mut foreign = read_installed(InstallScope::All);
+        foreign.retain(|name| !synced.contains(name));
+        return foreign;
+    }
+    run("pacman", &["-Qmq"])
+        .map(|text| lines(&text))
+        .unwrap_or_default()
+}
+
+/// The default backend, reading `pacman.log` as it is.
+pub struct Pacman;
+
+impl Backend for Pacman {
+    fn name(&self) -> &'static str {
+        "pacman"
+    }
+
+    fn program(&self) -> &'static str {
+        "pacman"
+    }
+
+    fn detect(&self) -> bool {
+        exec::on_path("pacman") || Path::new(LOCAL_DB_DIR).is_dir()
+    }
+
+    fn log_path(&self) -> &'static Path {
+        Path::new(history::LOG_PATH)
+    }
+
+    fn normalize_log<'a>(&self, raw: &'a [u8]) -> Cow<'a, [u8]> {
+        Cow::Borrowed(raw)
+    }
+
+    fn read_installed(&self, scope: InstallScope) -> Vec<String> {
+        read_installed(scope)
+    }
+
+    fn installed_versions(&self) -> BTreeMap<String, String> {
+        installed_versions()
+    }
+
+    fn foreign_packages(&self) -> Vec<String> {
+        foreign_packages()
+    }
+
+    fn local_info(&self) -> Vec<InfoBlock> {
+        local_info(&[], false)
+    }
+
+    fn pending_updates(&self) -> Vec<Update> {
+        pending_updates()
+    }
+}
+
+pub fn configured_repos() -> Vec<String> {
+    if exec::is_sandboxed() {
+        return db::configured_repos();
+    }
+    run("pacman-conf", &["--repo-list"])
+        .map(|text| lines(&text))
+        .unwrap_or_default()
+}
+
+/// Every package in the sync databases as (repository, name), repositories
+/// in `pacman.conf` order.
+pub fn sync_packages() -> Vec<(String, String)> {
+    if exec::is_sandboxed() {
+        return db::sync_packages();
+    }
+    run("pacman", &["-Sl"])
+        .map(|text| {
+            text.lines()
+                .filter_map(|line| {
+                    let mut fields = line.split_whitespace();
+                    Some((fields.next()?.to_string(), fields.next()?.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Mirror URLs for one repository in preference order, with `$repo` and
+/// `$arch` already expanded.
+pub fn servers(repo: &str) -> Vec<String> {
+    run("pacman-conf", &["--repo", repo, "Server"])
+        .map(|text| lines(&text))
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub name: String,
+    pub installed: String,
+    pub available: String,
+}
+
+/// `-Qu` lists installed packages the sync databases have another version
+/// of, as `name old -> new`, with ` [ignored]` appended for IgnorePkg. It
+/// exits with 1 when there is nothing to upgrade.
+pub fn pending_updates() -> Vec<Update> {
+    run("pacman", &["-Qu"])
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let name = words.next()?.to_string();
+            let installed = words.next()?.to_string();
+            let available = words.nth(1)?.to_string();
+            Some(Update {
+                name,
+                installed,
+                available,
+            })
+        })
+        .collect()
+}
+
+/// `-Ql` prints one `package /path` line per file; directories end in `/`.
+pub fn owned_files(packages: &[String]) -> Vec<(String, String)> {
+    if packages.is_empty() {
+        return Vec::new();
+    }
+    let mut args = vec!["-Ql"];
+    args.extend(packages.iter().map(String::as_str));
+    run("pacman", &args)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(_, path)| !path.ends_with('/'))
+        .map(|(package, path)| (package.to_string(), path.to_string()))
+        .collect()
+}
+
+pub fn check_files(packages: &[String], deep: bool) -> Option<Captured> {
+    let mut args = vec![if deep { "-Qkk" } else { "-Qk" }];
+    args.extend(packages.iter().map(String::as_str));
+    // Checking every file of every package can take minutes; it's asked for
+    // explicitly, so it gets as long as it needs.
+    let limits = Limits {
+        timeout: None,
+        ..exec::limits()
+    };
+    run_capture("pacman", &args, limits)
+}
+
+/// `-Rnsp` resolves the removal transaction and prints its targets without
+/// touching the system; pacman's own error is passed through on failure.
+pub fn removal_targets(package: &str) -> Result<Vec<String>, String> {
+    let captured = run_capture(
+        "pacman",
+        &["-Rnsp", "--print-format", "%n", package],
+        exec::limits(),
+    )
+    .ok_or_else(|| "failed to run pacman".to_string())?;
+    if captured.success {
+        Ok(lines(&captured.stdout))
+    } else {
+        Err(captured.stderr.trim().to_string())
+    }
+}
+
+/// `-Sg`/`-Qg` print `group package` pairs; `local` selects installed members.
+pub fn group_members(group: Option<&str>, local: bool) -> Vec<(String, String)> {
+    let mut args = vec![if local { "-Qg" } else { "-Sg" }];
+    args.extend(group);
+    run("pacman", &args)
+        .map(|text| {
+            text.lines()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(group, pkg)| (group.to_string(), pkg.trim().to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn version() -> Option<String> {
+    let text = run("pacman", &["-V"])?;
+    let start = text.find("Pacman v")?;
+    text[start..]
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+}
+
+/// Parses the `Key : value` blocks printed by `pacman -Qi`/`-Si`. Indented
+/// continuation lines are joined with spaces; unindented lines without a key
+/// (the `MODIFIED\t/etc/foo` rows under `Backup Files`) are kept one per line.
+pub fn parse_info_blocks(text: &str) -> Vec<InfoBlock> {
+    let mut blocks = Vec::new();
+    let mut block = InfoBlock::new();
+    let mut last_key: Option<String> = None;
+
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() {
+            if !block.is_empty() {
+                blocks.push(std::mem::take(&mut block));
+            }
+            last_key = None;
+            continue;
+        }
+
+        let field = line
+            .split_once(" : ")
+            .or_else(|| line.strip_suffix(" :").map(|key| (key, "")));
+        match field {
+            Some((key, value)) if !line.starts_with(char::is_whitespace) => {
+                let key = key.trim().to_string();
+                let value = value.trim();
+                let value = if matches!(value, "None" | "(none)") {
+                    ""
+                } else {
+                    value
+                };
+                block.insert(key.clone(), value.to_string());
+                last_key = Some(key);
+            }
+            _ => {
+                let Some(value) = last_key.as_ref().and_then(|key| block.get_mut(key)) else {
+                    continue;
+                };
+                if !value.is_empty() {
+                    value.push(if line.starts_with(char::is_whitespace) {
+                        ' '
+                    } else {
+                        '\n'
+                    });
+                }
+                value.push_str(line.trim());
+            }
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+pub fn sync_info(packages: &[String]) -> Vec<InfoBlock> {
+    let mut args = vec!["-Si"];
+    args.extend(packages.iter().map(String::as_str));
+    run("pacman", &args)
+        .map(|text| parse_info_blocks(&text))
+        .unwrap_or_default()
+}
+
+/// `-Qii` adds the backup file section with each file's modification state,
+/// which sandbox mode, reading the `desc` files instead, can't tell.
+pub fn local_info(packages: &[String], detailed: bool) -> Vec<InfoBlock> {
+    if exec::is_sandboxed() {
+        let mut blocks = db::info_blocks(&db::local_descs());
+        if !packages.is_empty() {
+            blocks.retain(|block| packages.contains(&block["Name"]));
+        }
+        return blocks;
+    }
+    let mut args = vec![if detailed { "-Qii" } else { "-Qi" }];
+    args.extend(packages.iter().map(String::as_str));
+    run("pacman", &args)
+        .map(|text| parse_info_blocks(&text))
+        .unwrap_or_default()
+}
+
+/// All installed packages' `-Qi` blocks keyed by name, for columns and
+/// fields that need metadata beyond the log.
+#[derive(Debug, Default)]
+pub struct LocalDb {
+    packages: HashMap<String, InfoBlock>,
+    providers: HashMap<String, String>,
+}
+
+/// `libfoo.so=1-64`, `python>=3.12` → the bare package or virtual name.
+pub fn strip_constraint(dep: &str) -> &str {
+    dep.split(['<', '>', '=', ':']).next().unwrap_or(dep)
+}
+
+impl LocalDb {
+    /// The selected backend's installed packages.
+    pub fn load() -> Self {
+        Self::from_blocks(backend::current().local_info())
+    }
+
+    /// Builds the database from `-Qi` blocks, e.g. from `parse_info_blocks`.
+    pub fn from_blocks(blocks: Vec<InfoBlock>) -> Self {
+        let packages: HashMap<String, InfoBlock> = blocks
+            .into_iter()
+            .filter_map(|block| Some((block.get("Name")?.clone(), block)))
+            .collect();
+        let mut providers = HashMap::new();
+        for (name, block) in &packages {
+            for provided in field_list(block, "Provides") {
+                providers
+                    .entry(strip_constraint(&provided).to_string())
+                    .or_insert_with(|| name.clone());
+            }
+        }
+        LocalDb {
+            packages,
+            providers,
+        }
+    }
+
+    /// Maps a dependency string to the installed package satisfying it,
+    /// following `Provides` for virtual names like `sh` or `libfoo.so`.
+    pub fn resolve(&self, dep: &str) -> Option<&str> {
+        let name = strip_constraint(dep);
+        if let Some((name, _)) = self.packages.get_key_value(name) {
+            return Some(name);
+        }
+        self.providers.get(name).map(String::as_str)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.packages.keys().map(String::as_str)
+    }
+
+    /// Virtual names (`sh`, `awk`, `libfoo.so`) some installed package provides.
+    pub fn provided_names(&self) -> impl Iterator<Item = &str> {
+        self.providers.keys().map(String::as_str)
+    }
+
+    /// An installed package declaring a conflict with `name`, which usually
+    /// means it is a drop-in alternative to it.
+    pub fn conflicting(&self, name: &str) -> Option<&str> {
+        let mut found: Vec<&str> = self
+            .packages
+            .iter()
+            .filter(|(_, block)| {
+                field_list(block, "Conflicts With")
+                    .iter()
+                    .any(|conflict| strip_constraint(conflict) == name)
+            })
+            .map(|(package, _)| package.as_str())
+            .collect();
+        found.sort_unstable();
+        found.first().copied()
+    }
+
+    pub fn installed_size(&self, name: &str) -> Option<u64> {
+        self.field(name, "Installed Size")
+            .and_then(size::parse_size)
+    }
+
+    pub fn required_by(&self, name: &str) -> Vec<&str> {
+        self.packages
+            .get(name)
+            .and_then(|block| block.get("Required By"))
+            .map(|names| names.split_whitespace().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_explicit(&self, name: &str) -> bool {
+        self.field(name, "Install Reason")
+            .is_some_and(|reason| reason.starts_with("Explicitly"))
+    }
+
+    pub fn depends(&self, name: &str) -> Vec<&str> {
+        self.packages
+            .get(name)
+            .and_then(|block| block.get("Depends On"))
+            .map(|deps| {
+                deps.split_whitespace()
+                    .filter_map(|dep| self.resolve(dep))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn block(&self, name: &str) -> Option<&InfoBlock> {
+        self.packages.get(name)
+    }
+
+    pub fn field(&self, name: &str, key: &str) -> Option<&str> {
+        self.packages
+            .get(name)?
+            .get(key)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+}
+
+pub fn field_list(block: &InfoBlock, key: &str) -> Vec<String> {
+    block
+        .get(key)
+        .map(|value| value.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// `name-pkgver-pkgrel-arch.pkg.tar.zst` → (name, pkgver-pkgrel)
+pub fn split_package_file(file_name: &str) -> Option<(&str, String)> {
+    let stem = &file_name[..file_name.find(".pkg.tar")?];
+    let mut parts = stem.rsplitn(4, '-');
+    let _arch = parts.next()?;
+    let rel = parts.next()?;
+    let ver = parts.next()?;
+    let name = parts.next()?;
+    Some((name, format!("{ver}-{rel}")))
+}
+
+/// Every package file in the cache as (name, pkgver-pkgrel, path); signatures
+/// are skipped.
+pub fn cached_packages() -> Vec<(String, String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(PKG_CACHE_DIR) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_str()?;
+            if file_name.ends_with(".sig") {
+                return None;
+            }
+            let (name, version) = split_package_file(file_name)?;
+            Some((name.to_string(), version, entry.path()))
+        })
+        .collect()
+}
+
+pub fn cached_package_names() -> Vec<String> {
+    let mut names: Vec<String> = cached_packages()
+        .into_iter()
+        .map(|(name, _, _)| name)
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+pub fn sync_db_mtime() -> Option<SystemTime> {
+    fs::read_dir(SYNC_DB_DIR)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "db"))
+        .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+        .max()
+}
+
+// `%INSTALLDATE%` from a local `desc` file, in seconds since the epoch.
+fn desc_install_date(desc: &str) -> Option<i64> {
+    let mut lines = desc.lines();
+    lines.find(|line| *line == "%INSTALLDATE%")?;
+    lines.next()?.trim().parse().ok()
+}
+
+/// When each installed package was last installed, read straight from the
+/// local database: its recorded install date, or failing that when its
+/// `desc` file was written. Keyed by name; directories are `name-ver-rel`.
+pub fn install_times() -> HashMap<String, SystemTime> {
+    let Ok(entries) = fs::read_dir(LOCAL_DB_DIR) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let dir_name = entry.file_name();
+            let name = db::package_name(dir_name.to_str()?)?.to_string();
+            let desc = entry.path().join("desc");
+            let recorded = fs::read_to_string(&desc)
+                .ok()
+                .and_then(|text| desc_install_date(&text))
+                .and_then(|secs| u64::try_from(secs).ok())
+                .map(|secs| SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs));
+            let time = recorded.or_else(|| fs::metadata(&desc).ok()?.modified().ok())?;
+            Some((name, time))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QI: &str = "\
+Name            : bash
+Version         : 5.2.026-2
+URL             : https://www.gnu.org/software/bash/bash.html
+Provides        : sh
+Depends On      : readline>=7.0  glibc
+Required By     : base
+Install Reason  : Installed as a dependency for another package
+Installed Size  : 9.30 MiB
+Backup Files    : (none)
+
+Name            : base
+Version         : 3-2
+Depends On      : bash  glibc
+Optional Deps   : linux-firmware: firmware for
+                  common hardware
+Install Reason  : Explicitly installed
+Backup Files    :
+MODIFIED\t/etc/foo
+UNMODIFIED\t/etc/bar
+
+Name            : glibc
+Version         : 2.39-1
+Conflicts With  : musl-glibc
+Install Reason  : Installed as a dependency for another package
+";
+
+    #[test]
+    fn info_blocks() {
+        let blocks = parse_info_blocks(QI);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0]["Backup Files"], "");
+        assert_eq!(
+            blocks[1]["Optional Deps"],
+            "linux-firmware: firmware for common hardware"
+        );
+        assert_eq!(
+            blocks[1]["Backup Files"],
+            "MODIFIED\t/etc/foo\nUNMODIFIED\t/etc/bar"
+        );
+        assert_eq!(field_list(&blocks[1], "Depends On"), ["bash", "glibc"]);
+    }
+
+    #[test]
+    fn local_db_queries() {
+        let local = LocalDb::from_blocks(parse_info_blocks(QI));
+        assert_eq!(local.resolve("sh"), Some("bash"));
+        assert_eq!(local.resolve("glibc>=2.38"), Some("glibc"));
+        assert_eq!(local.resolve("zsh"), None);
+        assert_eq!(local.depends("base"), ["bash", "glibc"]);
+        // readline isn't installed, so it can't resolve.
+        assert_eq!(local.depends("bash"), ["glibc"]);
+        assert_eq!(local.required_by("bash"), ["base"]);
+        assert!(local.is_explicit("base"));
+        assert!(!local.is_explicit("bash"));
+        assert_eq!(local.installed_size("bash"), Some(9_751_757));
+        assert_eq!(local.conflicting("musl-glibc"), Some("glibc"));
+        assert_eq!(local.field("bash", "Backup Files"), None);
+        assert_eq!(local.provided_names().collect::<Vec<_>>(), ["sh"]);
+    }
+
+    #[test]
+    fn constraints_are_stripped() {
+        assert_eq!(strip_constraint("python>=3.12"), "python");
+        assert_eq!(strip_constraint("libfoo.so=1-64"), "libfoo.so");
+        assert_eq!(strip_constraint("java-runtime:17"), "java-runtime");
+        assert_eq!(strip_constraint("vim"), "vim");
+    }
+
+    #[test]
+    fn version_lines() {
+        let versions = parse_versions("bash 5.2.026-2\nvim 9.1.0-1\n\nbroken\n");
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions["bash"], "5.2.026-2");
+        assert_eq!(versions["vim"], "9.1.0-1");
+    }
+
+    #[test]
+    fn package_file_names() {
+        assert_eq!(
+            split_package_file("lib32-gcc-libs-14.1.1+r1-1-x86_64.pkg.tar.zst"),
+            Some(("lib32-gcc-libs", "14.1.1+r1-1".to_string()))
+        );
+        assert_eq!(split_package_file("vim-9.1-1-x86_64.tar.zst"), None);
+        assert_eq!(split_package_file("vim-x86_64.pkg.tar.zst"), None);
+    }
+
+    #[test]
+    fn desc_install_dates() {
+        let desc = "%NAME%\nvim\n\n%INSTALLDATE%\n1704186005\n\n%SIZE%\n1\n";
+        assert_eq!(desc_install_date(desc), Some(1_704_186_005));
+        assert_eq!(desc_install_date("%NAME%\nvim\n"), None);
+    }
+}
@@ -0,0 +1,113 @@
+//! Machine-readable progress events on stderr for long-running steps.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+// Progress reports are off unless `--progress json` turned them on; a task
+// that nobody listens to costs one relaxed load per update.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Updates closer together than this fraction of the total are coalesced so
+// a multi-gigabyte log does not produce millions of events.
+const STEPS: u64 = 100;
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum Event<'a> {
+    Start {
+        task: &'a str,
+        unit: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        total: Option<u64>,
+    },
+    Progress {
+        task: &'a str,
+        unit: &'a str,
+        done: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        total: Option<u64>,
+    },
+    Finish {
+        task: &'a str,
+        unit: &'a str,
+        done: u64,
+    },
+}
+
+fn emit(event: &Event<'_>) {
+    // Progress is best effort: a closed stderr must not fail the command.
+    if let Ok(line) = serde_json::to_string(event) {
+        let _ = writeln!(io::stderr().lock(), "{line}");
+    }
+}
+
+/// One long-running step, reported as a `start` event, throttled `progress`
+/// events and a `finish` event when it is dropped.
+pub struct Task {
+    name: &'static str,
+    unit: &'static str,
+    total: Option<u64>,
+    done: u64,
+    reported: u64,
+    enabled: bool,
+}
+
+impl Task {
+    pub fn start(name: &'static str, unit: &'static str, total: Option<u64>) -> Self {
+        let enabled = ENABLED.load(Ordering::Relaxed);
+        if enabled {
+            emit(&Event::Start {
+                task: name,
+                unit,
+                total,
+            });
+        }
+        Task {
+            name,
+            unit,
+            total,
+            done: 0,
+            reported: 0,
+            enabled,
+        }
+    }
+
+    pub fn set(&mut self, done: u64) {
+        if !self.enabled {
+            return;
+        }
+        self.done = self.total.map_or(done, |total| done.min(total));
+        let step = self.total.map_or(1, |total| (total / STEPS).max(1));
+        if self.done >= self.reported + step {
+            self.reported = self.done;
+            emit(&Event::Progress {
+                task: self.name,
+                unit: self.unit,
+                done: self.done,
+                total: self.total,
+            });
+        }
+    }
+
+    pub fn advance(&mut self, by: u64) {
+        self.set(self.done + by);
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        if self.enabled {
+            emit(&Event::Finish {
+                task: self.name,
+                unit: self.unit,
+                done: self.done,
+            });
+        }
+    }
+}
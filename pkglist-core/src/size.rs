@@ -0,0 +1,69 @@
+//! Parsing and formatting sizes as pacman prints them.
+
+const UNITS: &[(&str, u64)] = &[
+    ("B", 1),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+];
+
+/// Accepts pacman's `245.12 MiB` as well as `100MiB`, `1.5G` or plain bytes.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let unit = unit.trim();
+
+    let factor = if unit.is_empty() {
+        1
+    } else {
+        UNITS
+            .iter()
+            .find(|(name, _)| {
+                name.eq_ignore_ascii_case(unit) || name[..1].eq_ignore_ascii_case(unit)
+            })
+            .map(|(_, factor)| *factor)?
+    };
+    Some((number * factor as f64).round() as u64)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    let (unit, factor) = UNITS
+        .iter()
+        .rev()
+        .find(|(_, factor)| bytes >= *factor)
+        .copied()
+        .unwrap_or(UNITS[0]);
+    if factor == 1 {
+        format!("{bytes} B")
+    } else {
+        format!("{:.2} {unit}", bytes as f64 / factor as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_parse() {
+        assert_eq!(parse_size("245.12 MiB"), Some(257_026_949));
+        assert_eq!(parse_size("100MiB"), Some(100 << 20));
+        assert_eq!(parse_size("1.5G"), Some(3 << 29));
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("12 parsecs"), None);
+        assert_eq!(parse_size("MiB"), None);
+    }
+
+    #[test]
+    fn sizes_format() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.50 KiB");
+        assert_eq!(format_size(3 << 30), "3.00 GiB");
+    }
+}
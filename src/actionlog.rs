@@ -0,0 +1,43 @@
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::actions::{self, Action};
+
+use crate::output::Palette;
+
+// Consecutive actions of one pkglist process, with the date it started.
+fn runs(actions: &[Action]) -> Vec<&[Action]> {
+    actions
+        .chunk_by(|a, b| a.pid == b.pid && a.invocation == b.invocation)
+        .collect()
+}
+
+pub fn run(palette: &Palette, limit: Option<usize>) -> io::Result<()> {
+    let Some(path) = actions::default_path() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no data directory"));
+    };
+    let actions = actions::read(&path)?;
+    let runs = runs(&actions);
+    let skip = limit.map_or(0, |limit| runs.len().saturating_sub(limit));
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if runs.is_empty() {
+        writeln!(out, "nothing recorded in {} yet", path.display())?;
+    }
+    for run in &runs[skip..] {
+        writeln!(
+            out,
+            "{} :: {}",
+            palette.date.paint(&run[0].date),
+            palette.pkg.paint(&run[0].invocation)
+        )?;
+        for action in *run {
+            let result = if action.result == "ok" {
+                palette.ins.paint(&action.result)
+            } else {
+                palette.rem.paint(&action.result)
+            };
+            writeln!(out, "    {} :: {} :: {result}", action.kind, action.target)?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use pkglist_core::exec;
+use pkglist_core::pacman::{self, LocalDb};
+
+use crate::i18n::tr;
+use crate::output::Palette;
+
+// Shell histories in the formats bash, zsh (extended or plain) and fish
+// write them.
+fn history_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = env::var_os("HISTFILE")
+        .map(PathBuf::from)
+        .into_iter()
+        .collect();
+    if let Some(home) = env::var_os("HOME").map(PathBuf::from) {
+        files.push(home.join(".bash_history"));
+        files.push(home.join(".zsh_history"));
+        files.push(home.join(".local/share/fish/fish_history"));
+    }
+    files.dedup();
+    files
+}
+
+// The programs a history line starts, including each stage of a pipeline
+// and the command behind `sudo`.
+fn commands_in(line: &str) -> impl Iterator<Item = &str> {
+    // `: 1700000000:0;cmd` (zsh extended history) and `- cmd: cmd` (fish).
+    let line = line
+        .split_once(';')
+        .filter(|_| line.starts_with(": "))
+        .map_or(line, |(_, cmd)| cmd);
+    let line = line.strip_prefix("- cmd: ").unwrap_or(line);
+    line.split(['|', ';', '&']).filter_map(|stage| {
+        stage
+            .split_whitespace()
+            .find(|word| {
+                !matches!(*word, "sudo" | "doas" | "exec" | "time" | "nohup") && !word.contains('=')
+            })
+            .map(|word| word.rsplit('/').next().unwrap_or(word))
+    })
+}
+
+fn executed_programs() -> HashSet<String> {
+    history_files()
+        .iter()
+        .filter_map(|path| fs::read(path).ok())
+        .flat_map(|bytes| {
+            String::from_utf8_lossy(&bytes)
+                .lines()
+                .flat_map(commands_in)
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// Executables each package installs into a directory on $PATH.
+fn executables(packages: &[String]) -> BTreeMap<String, Vec<String>> {
+    let path_dirs: Vec<PathBuf> = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_default();
+    let mut found: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (package, file) in pacman::owned_files(packages) {
+        let file = Path::new(&file);
+        let on_path = file
+            .parent()
+            .is_some_and(|dir| path_dirs.iter().any(|p| p == dir));
+        if let Some(name) = file.file_name().filter(|_| on_path) {
+            found
+                .entry(package)
+                .or_default()
+                .push(name.to_string_lossy().into_owned());
+        }
+    }
+    found
+}
+
+// Dependency-installed packages whose programs show up in a shell history
+// are being used directly; marking them explicit keeps `pacman -Rns` and
+// orphan cleanups from taking them away when whatever pulled them in goes.
+pub fn run(palette: &Palette, interactive: bool) -> io::Result<()> {
+    let local = LocalDb::load();
+    let mut dependencies: Vec<String> = local
+        .names()
+        .filter(|name| !local.is_explicit(name))
+        .map(str::to_string)
+        .collect();
+    dependencies.sort_unstable();
+
+    let programs = executables(&dependencies);
+    let executed = executed_programs();
+    let used: BTreeMap<&str, Vec<&str>> = programs
+        .iter()
+        .filter_map(|(package, bins)| {
+            let ran: Vec<&str> = bins
+                .iter()
+                .map(String::as_str)
+                .filter(|bin| executed.contains(*bin))
+                .collect();
+            (!ran.is_empty()).then_some((package.as_str(), ran))
+        })
+        .collect();
+
+    let mut adopt: Vec<&str> = Vec::new();
+    if interactive {
+        // Everything that installs a program is offered, used ones first.
+        let mut offered: Vec<&str> = programs.keys().map(String::as_str).collect();
+        offered.sort_by_key(|package| !used.contains_key(package));
+        for package in offered {
+            let note = match used.get(package) {
+                Some(ran) => tr!("adopt-ran", programs = ran.join(", ")),
+                None => tr!("adopt-provides", programs = programs[package].join(", ")),
+            };
+            if exec::confirm(&tr!("adopt-prompt-one", package = package, note = note))? {
+                adopt.push(package);
+            }
+        }
+    } else {
+        let mut out = BufWriter::new(io::stdout().lock());
+        if used.is_empty() {
+            writeln!(out, "{}", tr!("adopt-none"))?;
+        }
+        for (package, ran) in &used {
+            writeln!(
+                out,
+                "{} :: {}",
+                palette.pkg.paint(*package),
+                tr!("adopt-ran", programs = ran.join(", "))
+            )?;
+            adopt.push(package);
+        }
+        out.flush()?;
+    }
+
+    if adopt.is_empty() {
+        return Ok(());
+    }
+    adopt.sort_unstable();
+    let mut command = exec::privileged("pacman");
+    command.args(["-D", "--asexplicit"]).args(&adopt);
+    let prompt = tr!("adopt-prompt", count = adopt.len());
+    if !exec::confirm_commands(&prompt, std::slice::from_ref(&command))? {
+        return Ok(());
+    }
+    exec::run_checked(&mut command)
+}
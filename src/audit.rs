@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::backend;
+use pkglist_core::history::{self, Status};
+use pkglist_core::jump;
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::LocalDb;
+
+use crate::config::Config;
+use crate::output::Palette;
+
+// The packages the roles expect, merged, so a machine can combine a `base`
+// role with the ones for its services.
+fn expected<'a>(config: &'a Config, roles: &[String]) -> io::Result<BTreeSet<&'a str>> {
+    let mut expected = BTreeSet::new();
+    for role in roles {
+        let Some(packages) = config.roles.get(role) else {
+            let mut known: Vec<&str> = config.roles.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            let known = if known.is_empty() {
+                "none configured".to_string()
+            } else {
+                format!("known: {}", known.join(", "))
+            };
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no role `{role}` in the config ({known})"),
+            ));
+        };
+        expected.extend(packages.iter().map(String::as_str));
+    }
+    Ok(expected)
+}
+
+// Compares the machine against the packages its roles expect: expected ones
+// that aren't installed (a provider counts, so `sh` is met by bash) and
+// explicitly installed ones no role asks for. Returns whether there are any.
+fn audit_roles(
+    out: &mut impl Write,
+    config: &Config,
+    palette: &Palette,
+    data: &PackageData,
+    local: &LocalDb,
+    roles: &[String],
+) -> io::Result<bool> {
+    let expected = expected(config, roles)?;
+    let missing: Vec<&str> = expected
+        .iter()
+        .filter(|name| local.resolve(name).is_none())
+        .copied()
+        .collect();
+    let covered: BTreeSet<&str> = expected
+        .iter()
+        .filter_map(|name| local.resolve(name))
+        .collect();
+    let extra: Vec<&str> = data
+        .installed
+        .iter()
+        .map(String::as_str)
+        .filter(|name| !covered.contains(name))
+        .collect();
+
+    writeln!(
+        out,
+        "{} :: {} expected, {} missing, {} extra",
+        palette.pkg.bold().paint(roles.join(" + ")),
+        expected.len(),
+        missing.len(),
+        extra.len()
+    )?;
+    for name in &missing {
+        writeln!(out, "{} :: {}", palette.rem.paint("missing"), name)?;
+    }
+    for name in &extra {
+        writeln!(out, "{} :: {}", palette.upg.paint("extra  "), name)?;
+    }
+    Ok(!missing.is_empty() || !extra.is_empty())
+}
+
+// Installed packages on the config's denylist, however they were installed;
+// a package providing a denied name counts too.
+fn denied<'a>(config: &Config, local: &'a LocalDb) -> BTreeSet<&'a str> {
+    config
+        .denylist
+        .iter()
+        .filter_map(|name| local.resolve(name))
+        .collect()
+}
+
+// Whether anything is out of policy: a denylisted package installed or a
+// difference from the roles.
+pub fn run(
+    config: &Config,
+    palette: &Palette,
+    data: &PackageData,
+    roles: &[String],
+) -> io::Result<bool> {
+    if roles.is_empty() && config.denylist.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "nothing to audit: pass --role or set `denylist` in the config",
+        ));
+    }
+    let local = LocalDb::load();
+    let mut out = BufWriter::new(io::stdout().lock());
+    let drifted = !roles.is_empty() && audit_roles(&mut out, config, palette, data, &local, roles)?;
+
+    let denied = denied(config, &local);
+    let events = if denied.is_empty() {
+        BTreeMap::new()
+    } else {
+        history::parse_events(&history::read_log_file()?)
+    };
+    for name in &denied {
+        // The latest install in the log; a package the log only saw upgraded
+        // was installed before its first entry.
+        let installed = match events.get(*name) {
+            Some(events) => events
+                .iter()
+                .rev()
+                .find(|event| event.status == Status::Installed)
+                .map_or_else(
+                    || format!("before {}", events[0].date),
+                    |event| event.date.clone(),
+                ),
+            None => palette.date_text(&data.record(name)),
+        };
+        writeln!(
+            out,
+            "{} :: {} :: installed {}",
+            palette.rem.bold().paint("denied "),
+            name,
+            palette.date.paint(installed)
+        )?;
+    }
+    out.flush()?;
+    Ok(drifted || !denied.is_empty())
+}
+
+// Whether `installed` is what `declared` asks for: the exact version, the
+// version without its pkgrel, or a prefix ending in `*`, as in `6.6.*`.
+fn matches_declared(declared: &str, installed: &str) -> bool {
+    match declared.strip_suffix('*') {
+        Some(prefix) => installed.starts_with(prefix),
+        None => {
+            installed == declared
+                || installed
+                    .strip_prefix(declared)
+                    .is_some_and(|rest| rest.starts_with('-'))
+        }
+    }
+}
+
+// Compares the installed versions with the ones declared under `[versions]`:
+// packages at another version, with how far they moved and since when, and
+// declared packages that aren't installed. Returns whether there are any.
+pub fn run_versions(config: &Config, palette: &Palette, data: &PackageData) -> io::Result<bool> {
+    if config.versions.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "nothing to audit: declare versions under `[versions]` in the config",
+        ));
+    }
+    let installed = backend::current().installed_versions();
+    let mut drifted = Vec::new();
+    let mut missing = Vec::new();
+    for (name, declared) in &config.versions {
+        match installed.get(name) {
+            Some(version) if matches_declared(declared, version) => {}
+            Some(version) => drifted.push((name, declared, version)),
+            None => missing.push((name, declared)),
+        }
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(
+        out,
+        "{} :: {} declared, {} drifted, {} missing",
+        palette.pkg.bold().paint("versions"),
+        config.versions.len(),
+        drifted.len(),
+        missing.len()
+    )?;
+    for (name, declared, version) in &drifted {
+        // Wildcards say nothing about how far a version moved.
+        let jump = (!declared.ends_with('*'))
+            .then(|| jump::classify(&format!("{declared} -> {version}")))
+            .flatten()
+            .map_or_else(String::new, |jump| format!(" ({})", jump.as_str()));
+        writeln!(
+            out,
+            "{} :: {} :: declared {} :: installed {}{jump} since {}",
+            palette.upg.paint("drift  "),
+            name,
+            declared,
+            version,
+            palette.date.paint(palette.date_text(&data.record(name)))
+        )?;
+    }
+    for (name, declared) in &missing {
+        writeln!(
+            out,
+            "{} :: {} :: declared {}",
+            palette.rem.paint("missing"),
+            name,
+            declared
+        )?;
+    }
+    out.flush()?;
+    Ok(!drifted.is_empty() || !missing.is_empty())
+}
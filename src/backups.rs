@@ -0,0 +1,70 @@
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::{self, InfoBlock};
+
+use crate::output::{self, Highlight, Palette};
+
+// Each `Backup Files` row reads `MODIFIED\t/etc/foo`; the state is one of
+// MODIFIED, UNMODIFIED, MISSING or UNREADABLE.
+fn backup_files(block: &InfoBlock) -> Vec<(&str, &str)> {
+    block
+        .get("Backup Files")
+        .into_iter()
+        .flat_map(|value| value.lines())
+        .filter_map(|line| {
+            let (state, path) = line.split_once(char::is_whitespace)?;
+            Some((path.trim(), state))
+        })
+        .collect()
+}
+
+pub fn run(
+    data: &PackageData,
+    palette: &Palette,
+    packages: &[String],
+    show_all: bool,
+) -> io::Result<()> {
+    let blocks = pacman::local_info(packages, true);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut customized = 0;
+
+    for block in &blocks {
+        let Some(name) = block.get("Name") else {
+            continue;
+        };
+        let files: Vec<_> = backup_files(block)
+            .into_iter()
+            .filter(|(_, state)| show_all || *state != "UNMODIFIED")
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+        customized += 1;
+
+        let record = data.record(name);
+        let changed = files
+            .iter()
+            .filter(|(_, state)| *state != "UNMODIFIED")
+            .count();
+        let summary = format!("{changed} of {} backup file(s) changed", files.len());
+        output::write_record(&mut out, palette, &record, &[summary], Highlight::default())?;
+
+        for (path, state) in files {
+            let colour = match state {
+                "UNMODIFIED" => palette.ins,
+                "MODIFIED" => palette.upg,
+                _ => palette.rem,
+            };
+            writeln!(out, "    {} {path}", colour.paint(state.to_lowercase()))?;
+        }
+    }
+
+    writeln!(
+        out,
+        "{} packages checked, {customized} with customized backup files",
+        blocks.len()
+    )?;
+    out.flush()
+}
@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use pkglist_core::backend;
+use pkglist_core::history::Status;
+use pkglist_core::pacman::{self, LocalDb};
+
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::output::Palette;
+
+const STOCK: &[&str] = &["base", "linux", "linux-firmware"];
+
+// Fresh installs as archinstall's profiles set them up, on top of `STOCK`.
+// Names may be groups, which stand for all their members.
+const BUILTIN: &[(&str, &[&str])] = &[
+    ("base", &[]),
+    ("base-devel", &["base-devel"]),
+    ("gnome", &["gnome", "gdm"]),
+    ("plasma", &["plasma", "sddm", "konsole", "dolphin"]),
+    (
+        "xfce",
+        &["xfce4", "xfce4-goodies", "lightdm", "lightdm-gtk-greeter"],
+    ),
+    ("sway", &["sway", "foot", "wmenu"]),
+];
+
+// A file lists a package per line, `#` starting a comment, like archiso's
+// `packages.x86_64`.
+fn read_list(path: &Path) -> io::Result<Vec<String>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    Ok(text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+// The names a baseline consists of: a `[baseline]` entry of the config, a
+// built-in one or a file.
+fn names(config: &Config, baseline: &str) -> io::Result<Vec<String>> {
+    if let Some(names) = config.baselines.get(baseline) {
+        return Ok(names.clone());
+    }
+    if let Some((_, extra)) = BUILTIN.iter().find(|(name, _)| *name == baseline) {
+        return Ok(STOCK
+            .iter()
+            .chain(*extra)
+            .map(|name| name.to_string())
+            .collect());
+    }
+    let path = Path::new(baseline);
+    if path.is_file() {
+        return read_list(path);
+    }
+    let mut known: Vec<&str> = BUILTIN.iter().map(|(name, _)| *name).collect();
+    known.extend(config.baselines.keys().map(String::as_str));
+    known.sort_unstable();
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "no baseline `{baseline}` (known: {}), and no such file",
+            known.join(", ")
+        ),
+    ))
+}
+
+// Every installed package the baseline brings in: its packages, the members
+// of its groups and, transitively, their installed dependencies.
+fn covered<'a>(local: &'a LocalDb, roots: &[String]) -> BTreeSet<&'a str> {
+    let groups = pacman::group_members(None, true);
+    let mut covered = BTreeSet::new();
+    let mut pending: Vec<&str> = roots
+        .iter()
+        .filter_map(|root| local.resolve(root))
+        .chain(
+            groups
+                .iter()
+                .filter(|(group, _)| roots.contains(group))
+                .filter_map(|(_, member)| local.resolve(member)),
+        )
+        .collect();
+    while let Some(name) = pending.pop() {
+        if covered.insert(name) {
+            pending.extend(local.depends(name));
+        }
+    }
+    covered
+}
+
+// What sets this machine apart from a stock install: explicitly installed
+// packages the baseline doesn't bring in, and the baseline's own packages
+// that aren't installed. Groups can be installed in part, so their members
+// are never reported missing. True when there is any difference.
+pub fn run(config: &Config, palette: &Palette, baseline: &str, quiet: bool) -> io::Result<bool> {
+    let roots = names(config, baseline)?;
+    let versions: BTreeMap<String, String> = backend::current().installed_versions();
+    if versions.is_empty() {
+        return Err(io::Error::other("pacman -Q listed no packages"));
+    }
+    let local = LocalDb::load();
+    let covered = covered(&local, &roots);
+    let added: Vec<&str> = versions
+        .keys()
+        .map(String::as_str)
+        .filter(|name| local.is_explicit(name) && !covered.contains(name))
+        .collect();
+    let groups: BTreeSet<String> = pacman::group_members(None, false)
+        .into_iter()
+        .map(|(group, _)| group)
+        .collect();
+    let missing: Vec<&str> = roots
+        .iter()
+        .filter(|name| local.resolve(name).is_none() && !groups.contains(*name))
+        .map(String::as_str)
+        .collect();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if added.is_empty() && missing.is_empty() {
+        if !quiet {
+            writeln!(out, "{}", tr!("diff-stock-none", baseline = baseline))?;
+        }
+        return out.flush().map(|()| false);
+    }
+    for name in &added {
+        let status = palette.status(Status::Installed);
+        writeln!(
+            out,
+            "{} :: {} :: {}",
+            status.paint(palette.label(Status::Installed)),
+            palette.pkg.paint(*name),
+            versions[*name]
+        )?;
+    }
+    for name in &missing {
+        let status = palette.status(Status::Removed);
+        writeln!(
+            out,
+            "{} :: {}",
+            status.paint(palette.label(Status::Removed)),
+            palette.pkg.paint(*name)
+        )?;
+    }
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "diff-stock-summary",
+            added = added.len(),
+            missing = missing.len(),
+            baseline = baseline
+        )
+    )?;
+    out.flush().map(|()| true)
+}
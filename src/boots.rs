@@ -0,0 +1,139 @@
+use std::fs;
+use std::io::{self, Write};
+
+use chrono::{DateTime, FixedOffset, Local, TimeDelta};
+use pkglist_core::history::LOG_DATE_FORMAT;
+use pkglist_core::pacman;
+use serde::Deserialize;
+
+use crate::i18n::tr;
+use crate::output::Palette;
+
+#[derive(Deserialize)]
+struct JournalBoot {
+    // Microseconds since the epoch.
+    first_entry: i64,
+}
+
+fn from_journald() -> Option<Vec<DateTime<FixedOffset>>> {
+    let text = pacman::run("journalctl", &["--list-boots", "-o", "json", "-q"])?;
+    let boots: Vec<JournalBoot> = serde_json::from_str(&text).ok()?;
+    Some(
+        boots
+            .into_iter()
+            .filter_map(|boot| DateTime::from_timestamp_micros(boot.first_entry))
+            .map(|utc| utc.with_timezone(&Local).fixed_offset())
+            .collect(),
+    )
+}
+
+// `reboot   system boot  6.7.0-arch1-1  2024-01-15T10:00:00+01:00 - ...`
+fn from_wtmp() -> Option<Vec<DateTime<FixedOffset>>> {
+    let text = pacman::run("last", &["reboot", "--time-format", "iso"])?;
+    Some(
+        text.lines()
+            .filter(|line| line.starts_with("reboot"))
+            .filter_map(|line| {
+                line.split_whitespace()
+                    .find_map(|field| DateTime::parse_from_rfc3339(field).ok())
+            })
+            .collect(),
+    )
+}
+
+// Boot times oldest first, from journald when it keeps enough history and
+// from wtmp otherwise.
+pub fn boot_times() -> Vec<DateTime<FixedOffset>> {
+    let mut boots = from_journald()
+        .filter(|boots| !boots.is_empty())
+        .or_else(from_wtmp)
+        .unwrap_or_default();
+    boots.sort_unstable();
+    boots
+}
+
+// The current boot from the kernel's `btime`, which needs neither journald
+// nor wtmp.
+pub fn current_boot() -> Option<DateTime<FixedOffset>> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let btime = stat.lines().find_map(|line| line.strip_prefix("btime "))?;
+    let utc = DateTime::from_timestamp(btime.trim().parse().ok()?, 0)?;
+    Some(utc.with_timezone(&Local).fixed_offset())
+}
+
+pub fn format_duration(delta: TimeDelta) -> String {
+    let minutes = delta.num_minutes().abs();
+    match (minutes / (24 * 60), minutes / 60 % 24, minutes % 60) {
+        (0, 0, 0) => tr!("duration-seconds", s = delta.num_seconds().abs()),
+        (0, 0, m) => tr!("duration-minutes", m = m),
+        (0, h, m) => tr!("duration-hours", h = h, m = m),
+        (d, h, _) => tr!("duration-days", d = d, h = h),
+    }
+}
+
+// Interleaves reboot markers into a date-ordered stream of events.
+pub struct RebootMarkers {
+    boots: Vec<DateTime<FixedOffset>>,
+    next: usize,
+    last_event: Option<DateTime<FixedOffset>>,
+}
+
+impl RebootMarkers {
+    pub fn new(enabled: bool) -> Self {
+        RebootMarkers {
+            boots: if enabled { boot_times() } else { Vec::new() },
+            next: 0,
+            last_event: None,
+        }
+    }
+
+    fn write_marker(
+        &self,
+        out: &mut impl Write,
+        palette: &Palette,
+        boot: DateTime<FixedOffset>,
+        next_event: Option<DateTime<FixedOffset>>,
+    ) -> io::Result<()> {
+        let mut parts = vec![tr!("reboot-marker", date = boot.format(LOG_DATE_FORMAT))];
+        if let Some(last) = self.last_event {
+            parts.push(tr!("reboot-after", duration = format_duration(boot - last)));
+        }
+        if let Some(next) = next_event {
+            parts.push(tr!(
+                "reboot-before",
+                duration = format_duration(next - boot)
+            ));
+        }
+        let edge = palette.glyph("──", "");
+        let text = format!("{edge} {} {edge}", parts.join(", "));
+        let text = text.trim();
+        writeln!(out, "{}", palette.date.dimmed().paint(text))
+    }
+
+    // Writes a marker for every boot between the previous event and `date`.
+    pub fn before(
+        &mut self,
+        out: &mut impl Write,
+        palette: &Palette,
+        date: Option<DateTime<FixedOffset>>,
+    ) -> io::Result<()> {
+        let Some(date) = date else {
+            return Ok(());
+        };
+        while let Some(&boot) = self.boots.get(self.next).filter(|boot| **boot <= date) {
+            self.write_marker(out, palette, boot, Some(date))?;
+            self.next += 1;
+        }
+        self.last_event = Some(date);
+        Ok(())
+    }
+
+    // Boots after the last event, typically including the current one.
+    pub fn finish(&mut self, out: &mut impl Write, palette: &Palette) -> io::Result<()> {
+        while let Some(&boot) = self.boots.get(self.next) {
+            self.write_marker(out, palette, boot, None)?;
+            self.next += 1;
+        }
+        Ok(())
+    }
+}
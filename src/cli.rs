@@ -0,0 +1,737 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand, ValueEnum};
+use pkglist_core::apt::Apt;
+use pkglist_core::backend::{self, Backend};
+use pkglist_core::cache;
+use pkglist_core::dnf::Dnf;
+use pkglist_core::jump::Jump;
+use pkglist_core::pacman::{InstallScope, Pacman};
+use pkglist_core::size;
+use serde::Deserialize;
+
+use crate::export::Format;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "pkglist",
+    version,
+    propagate_version = true,
+    disable_version_flag = true,
+    about = "Colorized history of pacman packages"
+)]
+pub struct Cli {
+    /// Only list packages whose name contains PATTERN
+    #[arg(value_name = "PATTERN")]
+    pub pattern: Option<String>,
+
+    /// Only list entries with these statuses, comma-separated
+    #[arg(long, value_enum, value_delimiter = ',', ignore_case = true)]
+    pub status: Vec<StatusArg>,
+
+    /// Only list upgrades that jumped this far, comma-separated; judged from the version numbers
+    #[arg(long, value_enum, value_delimiter = ',', ignore_case = true)]
+    pub jump: Vec<JumpArg>,
+
+    /// Only list entries from DATE on: a year, month or day like 2024, 2024-07 or 2024-07-15
+    #[arg(long, value_name = "DATE", value_parser = parse_date_bound)]
+    pub since: Option<String>,
+
+    /// Only list entries up to and including DATE, in the same form as --since
+    #[arg(long, value_name = "DATE", value_parser = parse_date_bound)]
+    pub until: Option<String>,
+
+    /// Order of the listing; `date` and `name` stream entries as they are resolved
+    #[arg(long, value_enum, env = "PKGLIST_SORT", default_value_t = SortKey::Date)]
+    pub sort: SortKey,
+
+    /// Reverse the order, e.g. newest or largest first
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Include every installed package, dependencies too, with its install reason
+    #[arg(long, conflicts_with_all = ["deps_only", "explicit"])]
+    pub all: bool,
+
+    /// Only list installed packages that were pulled in as dependencies
+    #[arg(long, conflicts_with = "explicit")]
+    pub deps_only: bool,
+
+    /// Only list explicitly installed packages
+    #[arg(long)]
+    pub explicit: bool,
+
+    /// Extra columns to show, comma-separated
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub columns: Vec<Column>,
+
+    /// Add the installed size column; same as --columns size
+    #[arg(long)]
+    pub show_size: bool,
+
+    /// Only show installed packages at least SIZE big, like 100MiB or 1.5G
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub min_size: Option<u64>,
+
+    /// Only show packages that changed since the last interactive run
+    #[arg(long)]
+    pub new_only: bool,
+
+    /// Keep running and list what each transaction changed as soon as it finishes
+    #[arg(long, conflicts_with_all = ["out", "copy"])]
+    pub watch: bool,
+
+    /// Only show foreign packages, installed from the AUR or by hand rather than from a repository
+    #[arg(long)]
+    pub foreign: bool,
+
+    /// List apps from these other sources alongside the packages, comma-separated
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "SOURCE")]
+    pub include: Vec<Source>,
+
+    /// Only show packages from these repositories, comma-separated; `foreign` for AUR and other foreign packages
+    #[arg(long, value_delimiter = ',', value_name = "NAME")]
+    pub repo: Vec<String>,
+
+    /// Group the listing under a heading per repository, with counts
+    #[arg(long, value_enum, value_name = "KEY", conflicts_with = "by_day")]
+    pub group_by: Option<GroupBy>,
+
+    /// Print per-status counts and health warnings after the listing
+    #[arg(long)]
+    pub summary: bool,
+
+    /// Mark reboots (from journald or wtmp) between entries of the date-ordered listing
+    #[arg(long)]
+    pub reboots: bool,
+
+    /// Put each day of the date-ordered listing under its own heading
+    #[arg(long)]
+    pub by_day: bool,
+
+    /// Within each day, list the names per status with a count, e.g. "Upgraded (142)"
+    #[arg(long, requires = "by_day", conflicts_with = "reboots")]
+    pub by_status: bool,
+
+    /// Screen-reader friendly output: no colour, words instead of glyphs, one record per line
+    #[arg(long, global = true)]
+    pub accessible: bool,
+
+    /// Match package and group names regardless of case
+    #[arg(long, global = true)]
+    pub ignore_case: bool,
+
+    /// Treat `_` and `-` in package and group names as the same
+    #[arg(long, global = true)]
+    pub loose_match: bool,
+
+    /// Print the listing as FORMAT instead of coloured text
+    #[arg(long, value_enum, env = "PKGLIST_OUTPUT", value_name = "FORMAT")]
+    pub output: Option<Format>,
+
+    /// Write the listing to FILE instead of stdout, never with colour; unless --output or
+    /// --format is given the format follows the extension (.json, .jsonl, .csv, .md, .html,
+    /// anything else plain text)
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+
+    /// Print each package as TEMPLATE, like "{date} {status} {name} {version}"; placeholders
+    /// are date, status, label, name, version, versions, jump, repo, size, reason, url,
+    /// description and note, and \n, \t, \e and \\ are escapes
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["output", "group_by", "by_day", "reboots"]
+    )]
+    pub format: Option<String>,
+
+    /// Also copy the listing as plain text, or just the package names, to the clipboard
+    #[arg(long, value_enum, value_name = "WHAT", num_args = 0..=1, default_missing_value = "listing")]
+    pub copy: Option<CopyKind>,
+
+    /// Link package names to their Arch or AUR page in terminals that support OSC 8
+    #[arg(
+        long,
+        value_enum,
+        global = true,
+        env = "PKGLIST_HYPERLINKS",
+        value_name = "WHEN",
+        default_value_t = HyperlinkMode::Auto
+    )]
+    pub hyperlinks: HyperlinkMode,
+
+    /// Colour scheme of the terminal output
+    #[arg(
+        long,
+        value_enum,
+        global = true,
+        env = "PKGLIST_THEME",
+        default_value_t = Theme::Catppuccin
+    )]
+    pub theme: Theme,
+
+    /// When to colour the output; `auto` honours NO_COLOR
+    #[arg(long, value_enum, global = true, value_name = "WHEN", default_value_t = ColorMode::Auto)]
+    pub color: ColorMode,
+
+    /// Print the files and commands a run would touch instead of touching them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Run no external commands and read the package databases directly; automatic when the package manager isn't executable
+    #[arg(long, global = true, env = "PKGLIST_SANDBOX")]
+    pub sandbox: bool,
+
+    /// Package manager to read; `auto` picks the one installed, pacman first
+    #[arg(long, value_enum, global = true, env = "PKGLIST_BACKEND", default_value_t = BackendChoice::Auto)]
+    pub backend: BackendChoice,
+
+    /// Report progress of long operations (log parsing, downloads) on stderr
+    #[arg(long, value_enum, global = true, value_name = "FORMAT")]
+    pub progress: Option<ProgressFormat>,
+
+    /// Print nothing, warnings included, when a listing, notify or diff has no changes to show; for cron
+    #[arg(long, global = true)]
+    pub quiet_unless_changes: bool,
+
+    /// Exit 1 instead of 0 when audit or doctor find something or a diff finds changes
+    #[arg(long, global = true)]
+    pub exit_code: bool,
+
+    /// Print straight to the terminal instead of through $PKGLIST_PAGER or $PAGER
+    #[arg(long, global = true)]
+    pub no_pager: bool,
+
+    /// Apply the options bundled in `[profile.NAME]` of the config file
+    #[arg(short = 'p', long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Parse the log from scratch without reading or writing the history cache
+    #[arg(long, global = true, conflicts_with = "cache_path")]
+    pub no_cache: bool,
+
+    /// Keep the history cache in FILE instead of $XDG_CACHE_HOME/pkglist/history.json
+    #[arg(long, global = true, env = "PKGLIST_CACHE_PATH", value_name = "FILE")]
+    pub cache_path: Option<PathBuf>,
+
+    /// Read the package manager's log from FILE, e.g. a chroot's, instead of /var/log/pacman.log or dpkg.log
+    #[arg(long, global = true, env = "PKGLIST_LOG", value_name = "FILE")]
+    pub log: Option<PathBuf>,
+
+    /// Print version, build and environment details
+    #[arg(short = 'V', long)]
+    pub version: bool,
+
+    /// Print the JSON Schema of the machine-readable output and exit
+    #[arg(long)]
+    pub schema: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Date,
+    Name,
+    /// Installs, then upgrades, then removals
+    Status,
+    /// Installed size; removed packages count as empty
+    Size,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// The sync repository (core, extra, ...) or `foreign`
+    Repo,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusArg {
+    /// Installed
+    Ins,
+    /// Upgraded
+    Upg,
+    /// Removed
+    Rem,
+    /// Downgraded
+    Dwn,
+    /// Reinstalled at the same version
+    Rns,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Flatpak applications, tagged with the remote they came from
+    Flatpak,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JumpArg {
+    /// A new major version, or a new epoch
+    Major,
+    /// A new minor version
+    Minor,
+    /// A new patch release
+    Patch,
+    /// The same upstream version, rebuilt
+    Rebuild,
+}
+
+impl JumpArg {
+    pub fn jump(self) -> Jump {
+        match self {
+            JumpArg::Major => Jump::Major,
+            JumpArg::Minor => Jump::Minor,
+            JumpArg::Patch => Jump::Patch,
+            JumpArg::Rebuild => Jump::Rebuild,
+        }
+    }
+}
+
+// `YYYY`, `YYYY-MM` or `YYYY-MM-DD`, kept as typed since log dates compare
+// by prefix.
+pub fn parse_date_bound(text: &str) -> Result<String, String> {
+    let valid = match text.len() {
+        4 => text.parse::<u16>().is_ok(),
+        7 => NaiveDate::parse_from_str(&format!("{text}-01"), "%Y-%m-%d").is_ok(),
+        10 => NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok(),
+        _ => false,
+    };
+    if valid {
+        Ok(text.to_string())
+    } else {
+        Err("expected a date like 2024, 2024-07 or 2024-07-15".to_string())
+    }
+}
+
+pub fn parse_month(text: &str) -> Result<String, String> {
+    match parse_date_bound(text) {
+        Ok(month) if month.len() == 7 => Ok(month),
+        _ => Err("expected a month like 2024-07".to_string()),
+    }
+}
+
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    size::parse_size(text).ok_or_else(|| "expected a size like 100MiB, 1.5G or 2048".to_string())
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyKind {
+    /// The listing as printed, without colour
+    Listing,
+    /// One package name per line
+    Names,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendChoice {
+    Auto,
+    Pacman,
+    /// apt and dpkg, on Debian and its derivatives
+    Apt,
+    /// dnf and rpm, on Fedora and its relatives
+    Dnf,
+}
+
+impl BackendChoice {
+    pub fn backend(self) -> &'static dyn Backend {
+        match self {
+            BackendChoice::Auto => backend::detect(),
+            BackendChoice::Pacman => &Pacman,
+            BackendChoice::Apt => &Apt,
+            BackendChoice::Dnf => &Dnf,
+        }
+    }
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HyperlinkMode {
+    /// When stdout is a terminal known to render links
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Catppuccin,
+    Gruvbox,
+    Nord,
+    /// No colour at all
+    None,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// When stdout is a terminal and NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// One JSON object per line: `start`, `progress` and `finish` events
+    Json,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    /// Package description from the local database, fitted to the terminal width
+    Description,
+    /// Upstream project URL
+    Url,
+    /// Sparkline of log events per quarter over the last three years
+    Activity,
+    /// Version installed or removed, or `old → new` for upgrades
+    Versions,
+    /// Why the package is installed, explicitly or as a dependency; added by --all
+    Reason,
+    /// `foreign` for packages no sync repository has, like AUR packages (`pacman -Qm`)
+    Foreign,
+    /// Installed size from the local database; added by --show-size
+    Size,
+    /// How far an upgrade jumped: major, minor, patch or rebuild
+    Jump,
+    /// Your note on the package, from `pkglist note`
+    Note,
+}
+
+impl Cli {
+    // Where the history cache lives, or `None` with `--no-cache`.
+    pub fn cache_file(&self) -> Option<PathBuf> {
+        if self.no_cache {
+            return None;
+        }
+        self.cache_path.clone().or_else(cache::default_path)
+    }
+
+    // The installed packages to load; without --all, the listing still shows
+    // every package the log mentions.
+    pub fn install_scope(&self) -> InstallScope {
+        if self.all {
+            InstallScope::All
+        } else if self.deps_only {
+            InstallScope::Dependencies
+        } else {
+            InstallScope::Explicit
+        }
+    }
+
+    // Whether the listing is narrowed to the installed packages of the scope.
+    pub fn installed_only(&self) -> bool {
+        self.deps_only || self.explicit
+    }
+}
+
+impl Column {
+    // The key of its `[columns.<name>]` layout.
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Description => "description",
+            Column::Url => "url",
+            Column::Activity => "activity",
+            Column::Versions => "versions",
+            Column::Reason => "reason",
+            Column::Foreign => "foreign",
+            Column::Size => "size",
+            Column::Jump => "jump",
+            Column::Note => "note",
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Offer to mark dependency-installed packages you run directly as explicitly installed
+    Adopt {
+        /// Ask about every dependency that installs a program, not just ones seen in the shell history
+        #[arg(long, short)]
+        interactive: bool,
+    },
+
+    /// Check for denylisted packages and, with --role, packages missing from or extra to a role
+    Audit {
+        /// Role from `[role]` in the config; repeat to combine roles
+        #[arg(long)]
+        role: Vec<String>,
+        /// Instead, compare installed versions with the ones declared under `[versions]` in the config
+        #[arg(long, conflicts_with = "role")]
+        versions: bool,
+    },
+
+    /// List packages whose backup (config) files differ from the packaged version
+    Backups {
+        /// Packages to check; all installed packages when omitted
+        packages: Vec<String>,
+        /// Also list backup files that are unmodified
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Maintain pkglist's own history cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+
+    /// Analyze the pacman package cache: size, versions kept and reclaimable space
+    CacheUsage {
+        /// Versions per installed package to consider worth keeping, like `paccache -k`
+        #[arg(long, default_value_t = 3)]
+        keep: usize,
+        /// How many packages to list, most cached versions first
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+
+    /// Show packages installed, upgraded or removed since the previous diff
+    Diff {
+        /// Instead, show what was installed beyond a stock install (base, base-devel, gnome, plasma,
+        /// xfce, sway, a [baseline] from the config or a file listing packages) and what of it is missing
+        #[arg(long, value_name = "NAME")]
+        baseline: Option<String>,
+    },
+
+    /// Check the system for package-management problems
+    Doctor,
+
+    /// Write an offline-installable bundle: a snapshot plus the package file of every installed package
+    Export {
+        /// Directory for the bundle; created when missing
+        #[arg(long, value_name = "DIR")]
+        bundle: PathBuf,
+    },
+
+    /// Compare the packages of several hosts, queried over ssh in parallel
+    Fleet {
+        /// File listing one ssh host per line; `#` starts a comment
+        #[arg(long, value_name = "FILE")]
+        hosts: PathBuf,
+        /// Show package versions per host side by side instead of the report
+        #[arg(long)]
+        matrix: bool,
+        /// Packages for the matrix; every package that differs between hosts when omitted
+        #[arg(requires = "matrix")]
+        packages: Vec<String>,
+        /// Print the matrix as FORMAT
+        #[arg(long, value_enum, value_name = "FORMAT", requires = "matrix")]
+        output: Option<Format>,
+        /// Write the matrix to FILE; the format follows the extension unless --output is given
+        #[arg(long, value_name = "FILE", requires = "matrix")]
+        out: Option<PathBuf>,
+    },
+
+    /// List package groups with installed member counts, or expand one group
+    Groups {
+        /// Group to expand into its members
+        group: Option<String>,
+    },
+
+    /// Show every install, upgrade and removal of a package with its versions
+    History {
+        /// Package name, installed or not
+        package: String,
+    },
+
+    /// Merge the events of a pacman.log from an earlier install into the history
+    ImportLog {
+        file: PathBuf,
+        /// Name shown next to imported entries, e.g. the old machine's host name
+        #[arg(long)]
+        label: String,
+    },
+
+    /// Preview what `pacman -Rns <pkg>` would remove, without removing anything
+    Impact { package: String },
+
+    /// Show installed kernels, their upgrade history and which one is booted
+    Kernels,
+
+    /// Show what pkglist itself has written and run, grouped by invocation
+    Log {
+        /// Only the last N invocations
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+    },
+
+    /// Generate roff man pages from the command-line definition
+    Man {
+        /// Write pkglist.1 and one page per subcommand into this directory
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+
+    /// Set the install reason of packages, or of a selection piped in on stdin
+    #[command(group(clap::ArgGroup::new("reason").required(true).args(["asdeps", "asexplicit"])))]
+    Mark {
+        /// Packages to mark; read from stdin (names or listing rows) when omitted
+        packages: Vec<String>,
+        /// Mark them as installed as dependencies
+        #[arg(long)]
+        asdeps: bool,
+        /// Mark them as explicitly installed
+        #[arg(long)]
+        asexplicit: bool,
+    },
+
+    /// Show, set or remove a note on why a package is installed; all notes without a package
+    Note {
+        package: Option<String>,
+        /// The note, like "installed for work VPN"
+        #[arg(requires = "package")]
+        text: Option<String>,
+        /// Remove the package's note
+        #[arg(long, requires = "package", conflicts_with = "text")]
+        remove: bool,
+    },
+
+    /// Report package changes since the previous check, notify and run the configured hooks
+    Notify,
+
+    /// Run an SQL statement against the log's `events` and `packages` tables
+    #[cfg(feature = "sql")]
+    Query {
+        /// e.g. "SELECT name, count(*) FROM events GROUP BY name ORDER BY 2 DESC LIMIT 10"
+        sql: String,
+    },
+
+    /// Filter and sort the listing interactively, one line of listing options at a time
+    Repl,
+
+    /// Summarise one month of the log: transactions, big upgrades, new packages, removals and size
+    Report {
+        /// The month as YYYY-MM; the current one when omitted
+        #[arg(long, value_parser = parse_month)]
+        month: Option<String>,
+        /// Write a standalone HTML page with a chart instead of text
+        #[arg(long)]
+        html: bool,
+        /// Write the report to FILE instead of stdout
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+
+    /// Print (or run) the command to downgrade a package to an earlier version
+    Rollback {
+        package: String,
+        /// Version to return to, as `pkgver-pkgrel`; lists known versions when omitted
+        #[arg(long)]
+        to: Option<String>,
+        /// Run the downgrade after asking for confirmation
+        #[arg(long, requires = "to")]
+        run: bool,
+    },
+
+    /// Replace this binary with the latest GitHub release
+    ///
+    /// The download is checked against the release's SHA256SUMS, which catches corruption but, coming from the same
+    /// release, doesn't prove who published it; there is no signature check.
+    #[cfg(feature = "self-update")]
+    SelfUpdate {
+        /// Only report whether a newer release exists
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Show one package's latest log event and its local database details
+    Show {
+        /// Package name; virtual names like `awk` resolve to the installed provider
+        package: String,
+    },
+
+    /// Save the installed packages to a file, compare saved lists or turn one into a restore script
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommand,
+    },
+
+    /// Aggregate figures about the installed packages
+    Stats {
+        /// Count installed packages per license family (GPL, MIT, proprietary, ...)
+        #[arg(long)]
+        licenses: bool,
+        /// List the installed packages in one license family
+        #[arg(long, value_name = "FAMILY")]
+        license: Option<String>,
+        /// How the installed size grew: in total, per month and per package, from --since when given
+        #[arg(long, conflicts_with_all = ["licenses", "license"])]
+        growth: bool,
+        /// Count upgrades by how far their versions jumped, per month and in total, within --since and --until
+        #[arg(long, conflicts_with_all = ["licenses", "license", "growth"])]
+        jumps: bool,
+    },
+
+    /// Inspect past pacman transactions recorded in the log
+    Transactions {
+        #[command(subcommand)]
+        command: TransactionsCommand,
+    },
+
+    /// Show the installed dependency tree of a package with dates and statuses
+    Tree {
+        package: String,
+        /// Stop descending after this many levels
+        #[arg(long)]
+        depth: Option<usize>,
+    },
+
+    /// Explain which explicitly installed packages pulled a package in
+    Why { package: String },
+
+    /// Report packages whose files are missing or modified (pacman -Qk)
+    Verify {
+        /// Packages to check; all installed packages when omitted
+        packages: Vec<String>,
+        /// Only check that files exist (default)
+        #[arg(long, conflicts_with = "deep")]
+        quick: bool,
+        /// Also compare size, permissions and checksums against the mtree data
+        #[arg(long)]
+        deep: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// Drop packages removed before a date from the cache, now and on every rebuild
+    Compact {
+        /// Cut-off as a year, month or day like 2020 or 2020-01-01
+        #[arg(long, value_name = "DATE", value_parser = parse_date_bound)]
+        before: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCommand {
+    /// Write every installed package with its version and install reason to FILE
+    Save { file: PathBuf },
+    /// Show what was installed, upgraded or removed from snapshot A to B, or to now without B
+    Diff { a: PathBuf, b: Option<PathBuf> },
+    /// Print a shell script that reinstalls a snapshot's packages with `pacman -S --needed`
+    RestoreScript { file: PathBuf },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TransactionsCommand {
+    /// List every transaction with its number, date, outcome and command
+    List {
+        /// Mark reboots between transactions
+        #[arg(long)]
+        reboots: bool,
+    },
+    /// Replay one transaction: package changes with versions, hooks and warnings
+    Show { id: usize },
+}
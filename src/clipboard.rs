@@ -0,0 +1,70 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use pkglist_core::exec;
+
+use crate::output;
+
+const TTY: &str = "/dev/tty";
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let word = chunk.iter().enumerate().fold(0u32, |word, (i, byte)| {
+            word | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(word >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// The clipboard tools worth trying in this session, Wayland first.
+fn tools() -> Vec<Command> {
+    let mut tools = Vec::new();
+    if env::var_os("WAYLAND_DISPLAY").is_some() {
+        tools.push(Command::new("wl-copy"));
+    }
+    if env::var_os("DISPLAY").is_some() {
+        let mut xclip = Command::new("xclip");
+        xclip.args(["-selection", "clipboard"]);
+        tools.push(xclip);
+    }
+    tools
+}
+
+// OSC 52 asks the terminal itself to set the clipboard, which also works
+// over SSH; terminals that don't support it ignore the sequence.
+fn osc52(text: &str) -> io::Result<()> {
+    if exec::is_dry_run() {
+        exec::describe(&format!("copy {} bytes with OSC 52", text.len()));
+        return Ok(());
+    }
+    let mut tty = OpenOptions::new().write(true).open(TTY)?;
+    write!(tty, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    tty.flush()
+}
+
+// Puts `text` on the system clipboard without colour codes, through
+// wl-copy or xclip when they are installed and OSC 52 otherwise.
+pub fn copy(text: &str) -> io::Result<()> {
+    let text = output::strip_escapes(text);
+    for mut tool in tools() {
+        tool.stdout(Stdio::null()).stderr(Stdio::null());
+        match exec::run(&mut tool, Some(text.as_bytes())) {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    osc52(&text).map_err(|err| io::Error::other(format!("cannot reach the clipboard: {err}")))
+}
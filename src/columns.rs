@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use pkglist_core::backend;
+use pkglist_core::jump::{self, Jump};
+use pkglist_core::packages::PackageRecord;
+use pkglist_core::pacman::LocalDb;
+use pkglist_core::size;
+
+use crate::cli::Column;
+use crate::notes::Notes;
+use crate::output::Palette;
+use crate::pager;
+use crate::sparkline::Activity;
+
+const MIN_FLEX_WIDTH: usize = 12;
+
+// Whether any of `requested` reads the local package database.
+pub fn needs_local(requested: &[Column]) -> bool {
+    requested.iter().any(|column| {
+        matches!(
+            column,
+            Column::Description | Column::Url | Column::Reason | Column::Size
+        )
+    })
+}
+
+// Optional columns backed by the local package database or the log. The
+// database is loaded by the caller, once for the whole listing; the other
+// sources are only read when a requested column needs them.
+pub struct Columns<'a> {
+    fixed: Vec<Column>,
+    flex: Option<Column>,
+    local: &'a LocalDb,
+    activity: Activity,
+    foreign: HashSet<String>,
+    notes: Notes,
+}
+
+impl<'a> Columns<'a> {
+    pub fn new(requested: &[Column], local: &'a LocalDb) -> Self {
+        let mut fixed = Vec::new();
+        let mut flex = None;
+        for &column in requested {
+            match column {
+                Column::Description => flex = Some(column),
+                Column::Url
+                | Column::Activity
+                | Column::Versions
+                | Column::Reason
+                | Column::Foreign
+                | Column::Size
+                | Column::Jump
+                | Column::Note
+                    if !fixed.contains(&column) =>
+                {
+                    fixed.push(column)
+                }
+                Column::Url
+                | Column::Activity
+                | Column::Versions
+                | Column::Reason
+                | Column::Foreign
+                | Column::Size
+                | Column::Jump
+                | Column::Note => {}
+            }
+        }
+
+        let activity = if fixed.contains(&Column::Activity) {
+            Activity::load()
+        } else {
+            Activity::default()
+        };
+
+        let foreign = if fixed.contains(&Column::Foreign) {
+            backend::current().foreign_packages().into_iter().collect()
+        } else {
+            HashSet::new()
+        };
+
+        let notes = if fixed.contains(&Column::Note) {
+            Notes::load()
+        } else {
+            Notes::default()
+        };
+
+        Columns {
+            fixed,
+            flex,
+            local,
+            activity,
+            foreign,
+            notes,
+        }
+    }
+
+    // Names of the values `push_fixed` and `push_flex` add, in order.
+    pub fn fixed_names(&self) -> impl Iterator<Item = &str> {
+        self.fixed.iter().map(|column| column.name())
+    }
+
+    pub fn flex_name(&self) -> Option<&str> {
+        self.flex.map(Column::name)
+    }
+
+    fn value(&self, column: Column, record: &PackageRecord<'_>, palette: &Palette) -> String {
+        let field = |key| {
+            self.local
+                .field(record.name, key)
+                .unwrap_or("-")
+                .to_string()
+        };
+        let value = match column {
+            Column::Description => field("Description"),
+            Column::Url => field("URL"),
+            Column::Activity if palette.accessible => self.activity.counts(record.name),
+            Column::Activity => self.activity.sparkline(record.name),
+            Column::Reason => match self.local.field(record.name, "Install Reason") {
+                Some(_) if self.local.is_explicit(record.name) => "explicit".to_string(),
+                Some(_) => "dependency".to_string(),
+                None => "-".to_string(),
+            },
+            Column::Foreign if self.foreign.contains(record.name) => "foreign".to_string(),
+            Column::Foreign => "-".to_string(),
+            Column::Size => self
+                .local
+                .installed_size(record.name)
+                .map_or_else(|| "-".to_string(), size::format_size),
+            Column::Jump => record
+                .versions
+                .and_then(jump::classify)
+                .map_or("-", Jump::as_str)
+                .to_string(),
+            Column::Note => self.notes.get(record.name).unwrap_or("-").to_string(),
+            Column::Versions => match record.versions {
+                Some(versions) => {
+                    versions.replace(" -> ", &format!(" {} ", palette.glyph("→", "to")))
+                }
+                None => "-".to_string(),
+            },
+        };
+        palette.fit(column.name(), &value).into_owned()
+    }
+
+    pub fn push_fixed(
+        &self,
+        extra: &mut Vec<String>,
+        record: &PackageRecord<'_>,
+        palette: &Palette,
+    ) {
+        extra.extend(
+            self.fixed
+                .iter()
+                .map(|&column| self.value(column, record, palette)),
+        );
+    }
+
+    // The flexible column goes last and is cut to whatever is left of
+    // `width` after the rest of the row, so long text never wraps.
+    pub fn push_flex(
+        &self,
+        extra: &mut Vec<String>,
+        record: &PackageRecord<'_>,
+        palette: &Palette,
+        width: Option<usize>,
+    ) {
+        let Some(column) = self.flex else {
+            return;
+        };
+        let value = self.value(column, record, palette);
+        let value = match width {
+            Some(width) => {
+                let used = row_width(record, palette, extra) + SEPARATOR.len();
+                truncate(
+                    &value,
+                    width.saturating_sub(used).max(MIN_FLEX_WIDTH),
+                    palette.glyph("…", "..."),
+                )
+            }
+            None => value,
+        };
+        extra.push(value);
+    }
+}
+
+const SEPARATOR: &str = " :: ";
+
+fn row_width(record: &PackageRecord<'_>, palette: &Palette, extra: &[String]) -> usize {
+    let base = palette
+        .fit("date", &palette.date_text(record))
+        .chars()
+        .count()
+        + palette
+            .fit("status", palette.label(record.status))
+            .chars()
+            .count()
+        + palette.fit("name", record.name).chars().count()
+        + 2 * SEPARATOR.len();
+    extra
+        .iter()
+        .map(|column| column.chars().count() + SEPARATOR.len())
+        .sum::<usize>()
+        + base
+}
+
+pub fn truncate(text: &str, width: usize, ellipsis: &str) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let keep = width.saturating_sub(ellipsis.chars().count());
+    let mut cut: String = text.chars().take(keep).collect();
+    cut.push_str(ellipsis);
+    cut
+}
+
+// Through the pager stdout is a pipe, but stderr still shows the terminal.
+pub fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size_of(std::io::stdout())
+        .or_else(|| {
+            pager::stdout_is_terminal()
+                .then(|| terminal_size::terminal_size_of(std::io::stderr()))
+                .flatten()
+        })
+        .map(|(width, _)| usize::from(width.0))
+}
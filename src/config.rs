@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use ansi_term::Colour;
+use pkglist_core::dirs;
+use pkglist_core::exec;
+use pkglist_core::history::Status;
+use pkglist_core::pacman::InstallScope;
+
+use crate::cli::{
+    BackendChoice, ColorMode, Column, GroupBy, HyperlinkMode, JumpArg, SortKey, Source, StatusArg,
+    Theme,
+};
+use crate::export::Format;
+use crate::notify::Notifier;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub watchlist: Vec<String>,
+    pub notifiers: Vec<Notifier>,
+    pub script: ScriptConfig,
+    pub mirrors: MirrorConfig,
+    pub exec: ExecConfig,
+    // How `mark`, `adopt` and `rollback --run` get root for pacman.
+    pub elevation: exec::Elevation,
+    pub labels: Labels,
+    pub hooks: HookConfig,
+    pub holdback: HoldbackConfig,
+    pub columns: HashMap<String, ColumnLayout>,
+    pub age_bands: Option<AgeBands>,
+    pub colors: ColorOverrides,
+    pub defaults: Profile,
+    // Expected packages per role, `role.webserver = ["nginx", "certbot"]`.
+    #[serde(rename = "role")]
+    pub roles: HashMap<String, Vec<String>>,
+    // Packages `pkglist audit` fails on when installed, e.g. telnet on servers.
+    pub denylist: Vec<String>,
+    // Versions critical packages must stay at, `linux-lts = "6.6.*"`, for
+    // `pkglist audit --versions`.
+    pub versions: BTreeMap<String, String>,
+    // Stock installs for `pkglist diff --baseline`, beside the built-in ones,
+    // `baseline.workstation = ["base", "linux", "plasma"]`.
+    #[serde(rename = "baseline")]
+    pub baselines: HashMap<String, Vec<String>>,
+    #[serde(rename = "profile")]
+    pub profiles: HashMap<String, Profile>,
+}
+
+// Listing options bundled under `[profile.<name>]` and applied with
+// `--profile <name>`, or under `[defaults]` for every run. A profile wins
+// over the defaults, and environment variables and the command line win
+// over both.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub pattern: Option<String>,
+    pub status: Vec<StatusArg>,
+    pub jump: Vec<JumpArg>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub sort: Option<SortKey>,
+    pub reverse: Option<bool>,
+    pub packages: Option<InstallScope>,
+    pub foreign: Option<bool>,
+    pub include: Vec<Source>,
+    pub repo: Vec<String>,
+    pub group_by: Option<GroupBy>,
+    pub columns: Vec<Column>,
+    pub by_day: Option<bool>,
+    pub summary: Option<bool>,
+    pub reboots: Option<bool>,
+    pub accessible: Option<bool>,
+    pub ignore_case: Option<bool>,
+    pub loose_match: Option<bool>,
+    pub hyperlinks: Option<HyperlinkMode>,
+    pub theme: Option<Theme>,
+    pub color: Option<ColorMode>,
+    pub output: Option<Format>,
+    pub format: Option<String>,
+    pub backend: Option<BackendChoice>,
+    pub cache_path: Option<PathBuf>,
+    pub log: Option<PathBuf>,
+    pub labels: Option<Labels>,
+    pub age_bands: Option<AgeBands>,
+}
+
+// A colour in `[colors]`: `#rrggbb`, a terminal colour name, a 256-colour
+// index, or `none` to leave the field unstyled.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(try_from = "String")]
+pub struct ColorSpec(pub Option<Colour>);
+
+impl TryFrom<String> for ColorSpec {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let colour = match text.to_ascii_lowercase().as_str() {
+            "none" => None,
+            "black" => Some(Colour::Black),
+            "red" => Some(Colour::Red),
+            "green" => Some(Colour::Green),
+            "yellow" => Some(Colour::Yellow),
+            "blue" => Some(Colour::Blue),
+            "purple" | "magenta" => Some(Colour::Purple),
+            "cyan" => Some(Colour::Cyan),
+            "white" => Some(Colour::White),
+            hex if hex.len() == 7 && hex.starts_with('#') => {
+                let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+                match (channel(1), channel(3), channel(5)) {
+                    (Some(r), Some(g), Some(b)) => Some(Colour::RGB(r, g, b)),
+                    _ => return Err(format!("invalid colour `{text}`")),
+                }
+            }
+            index => Some(Colour::Fixed(index.parse().map_err(|_| {
+                format!("unknown colour `{text}`; use #rrggbb, a name like `red`, 0-255 or `none`")
+            })?)),
+        };
+        Ok(ColorSpec(colour))
+    }
+}
+
+// Per-field colours replacing the theme's.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorOverrides {
+    pub date: Option<ColorSpec>,
+    pub pkg: Option<ColorSpec>,
+    pub ins: Option<ColorSpec>,
+    pub upg: Option<ColorSpec>,
+    pub rem: Option<ColorSpec>,
+    pub dwn: Option<ColorSpec>,
+    pub rns: Option<ColorSpec>,
+    pub watched: Option<ColorSpec>,
+    pub recent: Option<ColorSpec>,
+    pub aging: Option<ColorSpec>,
+    pub old: Option<ColorSpec>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    #[default]
+    Left,
+    Right,
+}
+
+// Width limits for one column of the listing (`date`, `status`, `name`, an
+// optional column or a script column), keyed by its name.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnLayout {
+    pub min: Option<usize>,
+    pub max: Option<usize>,
+    pub align: Align,
+}
+
+// Dates younger than `recent_days` are tinted green, younger than
+// `aging_days` yellow and older ones red; off unless `[age_bands]` is set.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgeBands {
+    pub recent_days: u64,
+    pub aging_days: u64,
+}
+
+impl Default for AgeBands {
+    fn default() -> Self {
+        AgeBands {
+            recent_days: 7,
+            aging_days: 180,
+        }
+    }
+}
+
+// Bounds on every external command and download, so a hung mirror or
+// host can't stall a run; `timeout_secs = 0` waits forever.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecConfig {
+    pub timeout_secs: u64,
+    pub retries: u32,
+}
+
+impl Default for ExecConfig {
+    fn default() -> Self {
+        ExecConfig {
+            timeout_secs: 60,
+            retries: 1,
+        }
+    }
+}
+
+impl ExecConfig {
+    pub fn limits(&self) -> exec::Limits {
+        exec::Limits {
+            timeout: (self.timeout_secs > 0).then(|| Duration::from_secs(self.timeout_secs)),
+            retries: self.retries,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct HoldbackConfig {
+    pub max_age_days: u64,
+}
+
+impl Default for HoldbackConfig {
+    fn default() -> Self {
+        HoldbackConfig { max_age_days: 30 }
+    }
+}
+
+// Shell commands run by `pkglist notify` for each kind of detected change.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct HookConfig {
+    pub on_install: Option<String>,
+    pub on_upgrade: Option<String>,
+    pub on_remove: Option<String>,
+    pub on_downgrade: Option<String>,
+    pub on_reinstall: Option<String>,
+}
+
+impl HookConfig {
+    pub fn command(&self, status: Status) -> Option<&str> {
+        match status {
+            Status::Installed => self.on_install.as_deref(),
+            Status::Upgraded => self.on_upgrade.as_deref(),
+            Status::Removed => self.on_remove.as_deref(),
+            Status::Downgraded => self.on_downgrade.as_deref(),
+            Status::Reinstalled => self.on_reinstall.as_deref(),
+        }
+    }
+}
+
+// Status labels for human-readable output; machine-readable formats keep the
+// stable INS/UPG/REM/DWN/RNS codes.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Labels {
+    pub installed: String,
+    pub upgraded: String,
+    pub removed: String,
+    pub downgraded: String,
+    pub reinstalled: String,
+}
+
+impl Labels {
+    pub fn label(&self, status: Status) -> &str {
+        match status {
+            Status::Installed => &self.installed,
+            Status::Upgraded => &self.upgraded,
+            Status::Removed => &self.removed,
+            Status::Downgraded => &self.downgraded,
+            Status::Reinstalled => &self.reinstalled,
+        }
+    }
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Labels {
+            installed: "INS".to_string(),
+            upgraded: "UPG".to_string(),
+            removed: "REM".to_string(),
+            downgraded: "DWN".to_string(),
+            reinstalled: "RNS".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct MirrorConfig {
+    pub max_age_days: u64,
+}
+
+impl Default for MirrorConfig {
+    fn default() -> Self {
+        MirrorConfig { max_age_days: 3 }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScriptConfig {
+    pub filter: Option<String>,
+    pub columns: Vec<ScriptColumn>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptColumn {
+    pub name: String,
+    #[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+    pub expr: String,
+}
+
+impl Config {
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = dirs::config_dir().map(|dir| dir.join("config.toml")) else {
+            return Ok(Config::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e.message()),
+                )
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use pkglist_core::history::{Status, UNKNOWN_DATE};
+use pkglist_core::packages::PackageRecord;
+
+use crate::i18n::tr;
+use crate::output::{self, Highlight, Palette};
+
+fn status_heading(status: Status, count: usize) -> String {
+    match status {
+        Status::Installed => tr!("group-installed", count = count),
+        Status::Upgraded => tr!("group-upgraded", count = count),
+        Status::Removed => tr!("group-removed", count = count),
+        Status::Downgraded => tr!("group-downgraded", count = count),
+        Status::Reinstalled => tr!("group-reinstalled", count = count),
+    }
+}
+
+// Splits the date-ordered listing into days, each under a heading. With
+// `by_status` a day's entries are collected first and printed per status,
+// so a large upgrade reads as one block of names rather than interleaved
+// with the installs and removals of the same day.
+pub struct DayGroups {
+    enabled: bool,
+    by_status: bool,
+    day: Option<String>,
+    // status → entries and their rendered rows, for the current day
+    statuses: BTreeMap<Status, (usize, Vec<u8>)>,
+}
+
+impl DayGroups {
+    pub fn new(enabled: bool, by_status: bool) -> Self {
+        DayGroups {
+            enabled,
+            by_status: enabled && by_status,
+            day: None,
+            statuses: BTreeMap::new(),
+        }
+    }
+
+    fn write_heading(out: &mut impl Write, palette: &Palette, day: &str) -> io::Result<()> {
+        let edge = palette.glyph("──", "");
+        let text = format!("{edge} {day} {edge}");
+        writeln!(out, "{}", palette.date.bold().paint(text.trim()))
+    }
+
+    fn flush(&mut self, out: &mut impl Write, palette: &Palette) -> io::Result<()> {
+        for (status, (count, rows)) in std::mem::take(&mut self.statuses) {
+            writeln!(
+                out,
+                "  {}",
+                palette
+                    .status(status)
+                    .bold()
+                    .paint(status_heading(status, count))
+            )?;
+            out.write_all(&rows)?;
+        }
+        Ok(())
+    }
+
+    pub fn write(
+        &mut self,
+        out: &mut impl Write,
+        palette: &Palette,
+        record: &PackageRecord<'_>,
+        extra: &[String],
+        highlight: Highlight,
+    ) -> io::Result<()> {
+        if !self.enabled {
+            return output::write_record(out, palette, record, extra, highlight);
+        }
+        let day = record.date.map_or_else(
+            || UNKNOWN_DATE.to_string(),
+            |date| date.format("%Y-%m-%d").to_string(),
+        );
+        if self.day.as_deref() != Some(day.as_str()) {
+            self.flush(out, palette)?;
+            Self::write_heading(out, palette, &day)?;
+            self.day = Some(day);
+        }
+        if !self.by_status {
+            return output::write_record(out, palette, record, extra, highlight);
+        }
+        let (count, rows) = self.statuses.entry(record.status).or_default();
+        *count += 1;
+        output::write_name_row(rows, palette, record, extra, highlight)
+    }
+
+    pub fn finish(&mut self, out: &mut impl Write, palette: &Palette) -> io::Result<()> {
+        self.flush(out, palette)
+    }
+}
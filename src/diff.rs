@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+
+use chrono::Local;
+use pkglist_core::backend;
+use pkglist_core::dirs;
+use pkglist_core::exec;
+use pkglist_core::history::{self, Status};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::tr;
+use crate::output::Palette;
+
+const STATE_FILE: &str = "snapshot.json";
+
+// Every installed package with its version, as of the previous diff.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    taken: String,
+    packages: BTreeMap<String, String>,
+}
+
+// One line of the diff: the status it shows as and the versions involved,
+// `1.0-1 -> 1.1-1` for upgrades like in the log. A downgrade is a version
+// change too and shows as an upgrade.
+struct Change<'a> {
+    status: Status,
+    name: &'a str,
+    versions: String,
+}
+
+fn changes<'a>(
+    previous: &'a BTreeMap<String, String>,
+    current: &'a BTreeMap<String, String>,
+) -> Vec<Change<'a>> {
+    let mut changes: Vec<Change> = current
+        .iter()
+        .filter_map(|(name, version)| {
+            let (status, versions) = match previous.get(name) {
+                None => (Status::Installed, version.clone()),
+                Some(old) if old != version => (Status::Upgraded, format!("{old} -> {version}")),
+                Some(_) => return None,
+            };
+            Some(Change {
+                status,
+                name,
+                versions,
+            })
+        })
+        .chain(
+            previous
+                .iter()
+                .filter(|(name, _)| !current.contains_key(*name))
+                .map(|(name, version)| Change {
+                    status: Status::Removed,
+                    name,
+                    versions: version.clone(),
+                }),
+        )
+        .collect();
+    changes.sort_by_key(|change| (change.status, change.name));
+    changes
+}
+
+fn count(changes: &[Change], status: Status) -> usize {
+    changes
+        .iter()
+        .filter(|change| change.status == status)
+        .count()
+}
+
+// Prints each change from `previous` to `current` and the counts, or that
+// nothing changed unless `quiet`; true when something did. `since` is when
+// `previous` was taken.
+pub fn print_changes(
+    out: &mut impl Write,
+    palette: &Palette,
+    previous: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+    since: &str,
+    quiet: bool,
+) -> io::Result<bool> {
+    let changes = changes(previous, current);
+    if changes.is_empty() {
+        if !quiet {
+            writeln!(out, "{}", tr!("diff-none", date = since))?;
+        }
+        return Ok(false);
+    }
+    for change in &changes {
+        writeln!(
+            out,
+            "{} :: {} :: {}",
+            palette
+                .status(change.status)
+                .paint(palette.label(change.status)),
+            palette.pkg.paint(change.name),
+            change.versions
+        )?;
+    }
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "diff-summary",
+            installed = count(&changes, Status::Installed),
+            upgraded = count(&changes, Status::Upgraded),
+            removed = count(&changes, Status::Removed),
+            date = since
+        )
+    )?;
+    Ok(true)
+}
+
+// Compares the installed packages against the snapshot the previous diff
+// left and replaces it. The first run only records the snapshot. `quiet`
+// drops the output when nothing changed; true when something did.
+pub fn run(palette: &Palette, quiet: bool) -> io::Result<bool> {
+    let state_file = dirs::state_dir()
+        .map(|dir| dir.join(STATE_FILE))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no state directory"))?;
+    let current = backend::current().installed_versions();
+    if current.is_empty() {
+        return Err(io::Error::other("pacman -Q listed no packages"));
+    }
+    let previous: Option<Snapshot> = fs::read(&state_file)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let found = match &previous {
+        None => {
+            if !quiet {
+                writeln!(out, "{}", tr!("diff-baseline", count = current.len()))?;
+            }
+            false
+        }
+        Some(previous) => print_changes(
+            &mut out,
+            palette,
+            &previous.packages,
+            &current,
+            &previous.taken,
+            quiet,
+        )?,
+    };
+    out.flush()?;
+
+    let snapshot = Snapshot {
+        taken: history::format_log_date(&Local::now().fixed_offset()),
+        packages: current,
+    };
+    exec::write_file(&state_file, &serde_json::to_vec(&snapshot)?, None)?;
+    Ok(found)
+}
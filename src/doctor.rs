@@ -0,0 +1,619 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, TimeDelta};
+use pkglist_core::exec;
+use pkglist_core::history::{self, Status};
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::{self, LocalDb, Update};
+
+use crate::boots;
+use crate::config::Config;
+use crate::holdback;
+use crate::i18n::tr;
+use crate::ignored::Ignored;
+use crate::kernels;
+use crate::mirrors;
+use crate::output::{Ink, Palette};
+use crate::risk;
+use crate::transactions::{self, Action, Outcome, Transaction};
+
+// Repositories Arch has folded into others; a pacman.conf still listing
+// them keeps serving stale databases.
+const RETIRED_REPOS: &[&str] = &[
+    "community",
+    "community-testing",
+    "community-staging",
+    "testing",
+    "staging",
+];
+
+// Upgrading any of these only takes full effect after a reboot.
+const REBOOT_PACKAGES: &[&str] = &[
+    "linux",
+    "linux-lts",
+    "linux-zen",
+    "linux-hardened",
+    "systemd",
+    "glibc",
+    "nvidia",
+    "nvidia-dkms",
+    "amd-ucode",
+    "intel-ucode",
+];
+
+const CONFIG_DIR: &str = "/etc";
+const RECENT_DAYS: i64 = 30;
+const LISTED_FILES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Problem,
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub check: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    // What to do about it, shown under the finding.
+    pub action: Option<String>,
+}
+
+impl Finding {
+    fn new(check: &'static str, severity: Severity, message: String) -> Self {
+        Finding {
+            check,
+            severity,
+            message,
+            action: None,
+        }
+    }
+
+    fn action(mut self, action: impl Into<String>) -> Self {
+        self.action = Some(action.into());
+        self
+    }
+}
+
+impl Severity {
+    fn label(self) -> String {
+        match self {
+            Severity::Info => tr!("severity-info"),
+            Severity::Warning => tr!("severity-warning"),
+            Severity::Problem => tr!("severity-problem"),
+        }
+    }
+
+    fn colour(self, palette: &Palette) -> Ink {
+        match self {
+            Severity::Info => palette.pkg,
+            Severity::Warning => palette.upg,
+            Severity::Problem => palette.watched,
+        }
+    }
+}
+
+// Everything the detectors share, loaded once per run.
+struct Context<'a> {
+    data: &'a PackageData,
+    config: &'a Config,
+    local: LocalDb,
+    transactions: Vec<Transaction>,
+    commands: Vec<(String, String)>,
+    updates: Vec<Update>,
+    ignored: Ignored,
+}
+
+type Detector = fn(&Context<'_>) -> Vec<Finding>;
+
+const DETECTORS: &[Detector] = &[
+    partial_upgrade,
+    reboot_needed,
+    mirror_freshness,
+    repo_reorganization,
+    dropped_packages,
+    pacnew_backlog,
+    failed_hooks,
+    orphans,
+    held_back,
+    stale_updates,
+    install_reason_transitions,
+    sandbox,
+];
+
+fn partial_upgrade(cx: &Context<'_>) -> Vec<Finding> {
+    risk::partial_upgrade_warnings(cx.data)
+        .into_iter()
+        .map(|warning| {
+            Finding::new("partial-upgrade", Severity::Problem, warning)
+                .action(tr!("doctor-partial-upgrade-action"))
+        })
+        .collect()
+}
+
+fn reboot_needed(cx: &Context<'_>) -> Vec<Finding> {
+    let check = "reboot";
+    if let Some(release) = kernels::missing_booted_modules() {
+        return vec![
+            Finding::new(
+                check,
+                Severity::Problem,
+                tr!("doctor-modules-removed", release = release),
+            )
+            .action(tr!("doctor-modules-removed-action")),
+        ];
+    }
+
+    let Some(booted) = boots::current_boot() else {
+        return Vec::new();
+    };
+    let upgraded: Vec<&str> = REBOOT_PACKAGES
+        .iter()
+        .copied()
+        .filter(|name| {
+            cx.data.history.get(*name).is_some_and(|info| {
+                info.status != Status::Removed && info.date.is_some_and(|date| date > booted)
+            })
+        })
+        .collect();
+    if upgraded.is_empty() {
+        return Vec::new();
+    }
+    vec![
+        Finding::new(
+            check,
+            Severity::Warning,
+            tr!(
+                "doctor-reboot",
+                packages = upgraded.join(", "),
+                date = booted.format(history::LOG_DATE_FORMAT)
+            ),
+        )
+        .action(tr!("doctor-reboot-action")),
+    ]
+}
+
+fn mirror_freshness(cx: &Context<'_>) -> Vec<Finding> {
+    mirrors::staleness(cx.config.mirrors.max_age_days)
+        .into_iter()
+        .map(|warning| {
+            Finding::new("mirrors", Severity::Warning, warning.message).action(warning.action)
+        })
+        .collect()
+}
+
+fn repo_reorganization(cx: &Context<'_>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let check = "repo-reorg";
+
+    for repo in pacman::configured_repos() {
+        if RETIRED_REPOS.contains(&repo.as_str()) {
+            findings.push(
+                Finding::new(
+                    check,
+                    Severity::Problem,
+                    tr!("doctor-retired-repo", repo = repo),
+                )
+                .action(tr!("doctor-retired-repo-action", repo = repo)),
+            );
+        }
+    }
+
+    let installed: BTreeSet<&str> = cx.data.installed.iter().map(String::as_str).collect();
+    for block in pacman::sync_info(&[]) {
+        let Some(name) = block.get("Name") else {
+            continue;
+        };
+        if installed.contains(name.as_str()) {
+            continue;
+        }
+        let repo = block.get("Repository").map(String::as_str).unwrap_or("?");
+        for replaced in pacman::field_list(&block, "Replaces") {
+            let replaced = replaced.split(['<', '>', '=']).next().unwrap_or_default();
+            if installed.contains(replaced) {
+                findings.push(
+                    Finding::new(
+                        check,
+                        Severity::Warning,
+                        tr!(
+                            "doctor-replaced",
+                            package = replaced,
+                            replacement = format!("{repo}/{name}")
+                        ),
+                    )
+                    .action(tr!("doctor-replaced-action", name = name)),
+                );
+            }
+        }
+    }
+
+    findings
+}
+
+// A foreign package that still has a copy in the pacman cache, or that the
+// log shows being installed with `pacman -S`, came from a repository which
+// has since stopped carrying it.
+fn dropped_packages(cx: &Context<'_>) -> Vec<Finding> {
+    let cached: HashSet<String> = pacman::cached_package_names().into_iter().collect();
+    let from_repo: HashSet<&str> = cx
+        .transactions
+        .iter()
+        .filter(|tx| {
+            tx.command.as_deref().is_some_and(|command| {
+                command
+                    .split_whitespace()
+                    .any(|word| word.starts_with("-S") && !word.starts_with("-Sc"))
+            })
+        })
+        .flat_map(|tx| &tx.changes)
+        .filter(|change| change.action == Action::Installed)
+        .map(|change| change.name.as_str())
+        .collect();
+
+    pacman::foreign_packages()
+        .into_iter()
+        .filter(|name| cached.contains(name) || from_repo.contains(name.as_str()))
+        .map(|name| {
+            Finding::new(
+                "dropped",
+                Severity::Warning,
+                tr!("doctor-dropped", package = name),
+            )
+            .action(tr!("doctor-dropped-action"))
+        })
+        .collect()
+}
+
+fn find_pacnew(dir: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            find_pacnew(&path, found);
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == "pacnew" || ext == "pacsave")
+        {
+            found.push(path);
+        }
+    }
+}
+
+fn pacnew_backlog(_: &Context<'_>) -> Vec<Finding> {
+    let mut found = Vec::new();
+    find_pacnew(Path::new(CONFIG_DIR), &mut found);
+    if found.is_empty() {
+        return Vec::new();
+    }
+    found.sort();
+
+    let mut listed: Vec<String> = found
+        .iter()
+        .take(LISTED_FILES)
+        .map(|path| path.display().to_string())
+        .collect();
+    if found.len() > LISTED_FILES {
+        listed.push(tr!(
+            "doctor-pacnew-more",
+            count = found.len() - LISTED_FILES
+        ));
+    }
+    vec![
+        Finding::new(
+            "pacnew",
+            Severity::Warning,
+            tr!(
+                "doctor-pacnew",
+                count = found.len(),
+                files = listed.join(", ")
+            ),
+        )
+        .action(tr!("doctor-pacnew-action")),
+    ]
+}
+
+fn failed_hooks(cx: &Context<'_>) -> Vec<Finding> {
+    let since = Local::now().fixed_offset() - TimeDelta::days(RECENT_DAYS);
+    cx.transactions
+        .iter()
+        .filter(|tx| history::parse_log_date(&tx.started).is_some_and(|date| date >= since))
+        .filter_map(|tx| {
+            let message = if tx.outcome != Outcome::Completed {
+                tr!(
+                    "doctor-transaction-ended",
+                    id = tx.id,
+                    date = tx.started,
+                    outcome = tx.outcome.label()
+                )
+            } else if !tx.failed_hooks.is_empty() {
+                tr!(
+                    "doctor-hooks-failed",
+                    id = tx.id,
+                    date = tx.started,
+                    hooks = tx.failed_hooks.join(", ")
+                )
+            } else {
+                return None;
+            };
+            Some(
+                Finding::new("hooks", Severity::Problem, message)
+                    .action(tr!("doctor-transaction-action", id = tx.id)),
+            )
+        })
+        .collect()
+}
+
+fn orphans(cx: &Context<'_>) -> Vec<Finding> {
+    let mut names: Vec<&str> = cx
+        .local
+        .names()
+        .filter(|name| !cx.local.is_explicit(name) && cx.local.required_by(name).is_empty())
+        .collect();
+    names.sort_unstable();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let install_tx = cx.transactions.iter().rev().find(|tx| {
+                tx.changes
+                    .iter()
+                    .any(|change| change.name == name && change.action == Action::Installed)
+            });
+            let departed: Vec<String> = install_tx
+                .into_iter()
+                .flat_map(|tx| &tx.changes)
+                .filter(|change| change.name != name)
+                .filter_map(|change| {
+                    let info = cx.data.history.get(&change.name)?;
+                    (info.status == Status::Removed).then(|| {
+                        tr!(
+                            "doctor-orphan-removed",
+                            package = change.name,
+                            date = history::date_or_unknown(info.date)
+                        )
+                    })
+                })
+                .collect();
+            let message = if departed.is_empty() {
+                tr!("doctor-orphan", package = name)
+            } else {
+                tr!(
+                    "doctor-orphan-came-with",
+                    package = name,
+                    packages = departed.join(", ")
+                )
+            };
+            Finding::new("orphans", Severity::Warning, message)
+                .action(tr!("doctor-orphan-action", package = name))
+        })
+        .collect()
+}
+
+// Ignored packages never show up in `pacman -Syu` again, so nothing else
+// reminds anyone that they are falling behind.
+fn held_back(cx: &Context<'_>) -> Vec<Finding> {
+    let stale = TimeDelta::days(RECENT_DAYS);
+    cx.ignored
+        .held_back(&cx.updates)
+        .into_iter()
+        .map(|held| {
+            let severity = if held.age().is_some_and(|age| age > stale) {
+                Severity::Warning
+            } else {
+                Severity::Info
+            };
+            Finding::new(
+                "ignored",
+                severity,
+                tr!(
+                    "doctor-ignored",
+                    package = held.update.name,
+                    version = held.update.installed,
+                    held = held.describe()
+                ),
+            )
+            .action(tr!("doctor-ignored-action", package = held.update.name))
+        })
+        .collect()
+}
+
+fn stale_updates(cx: &Context<'_>) -> Vec<Finding> {
+    holdback::stale_updates(cx.data, &cx.updates, cx.config.holdback.max_age_days)
+        .into_iter()
+        // Ignored packages are already reported by `held_back`.
+        .filter(|stale| !cx.ignored.contains(&stale.update.name))
+        .map(|stale| {
+            let name = &stale.update.name;
+            Finding::new(
+                "holdback",
+                Severity::Warning,
+                tr!(
+                    "doctor-holdback",
+                    package = name,
+                    version = stale.update.installed,
+                    pending = stale.describe()
+                ),
+            )
+            .action(tr!("doctor-holdback-action", package = name))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reason {
+    Explicit,
+    Dependency,
+}
+
+impl Reason {
+    fn describe(self) -> String {
+        match self {
+            Reason::Explicit => tr!("doctor-reason-explicit"),
+            Reason::Dependency => tr!("doctor-reason-dependency"),
+        }
+    }
+}
+
+// `extra/foo`, `foo` or `/path/foo-1.0-1-x86_64.pkg.tar.zst` → `foo`.
+fn target_name(target: &str) -> &str {
+    let base = target.rsplit('/').next().unwrap_or(target);
+    pacman::split_package_file(base).map_or(base, |(name, _)| name)
+}
+
+// The install reason a pacman command line gives `package`: targets named on
+// the command line (directly or through a group) are explicit unless
+// `--asdeps` is passed; everything else it pulls in is a dependency.
+fn reason_from_command(
+    command: &str,
+    package: &str,
+    groups: &HashMap<&str, Vec<&str>>,
+) -> Option<Reason> {
+    let mut words = command.split_whitespace();
+    if !words.next()?.ends_with("pacman") {
+        return None;
+    }
+    let mut named = false;
+    let mut reason = None;
+    for word in words {
+        match word {
+            "--asdeps" => reason = Some(Reason::Dependency),
+            "--asexplicit" => reason = Some(Reason::Explicit),
+            _ if word.starts_with('-') => {}
+            _ => {
+                let target = target_name(word);
+                named |= target == package
+                    || groups
+                        .get(target)
+                        .is_some_and(|members| members.contains(&package));
+            }
+        }
+    }
+    Some(if named {
+        reason.unwrap_or(Reason::Explicit)
+    } else {
+        Reason::Dependency
+    })
+}
+
+// Compares the reason each package was installed with against its current
+// one.
+fn install_reason_transitions(cx: &Context<'_>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let group_pairs = pacman::group_members(None, true);
+    let mut groups: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (group, member) in &group_pairs {
+        groups.entry(group).or_default().push(member);
+    }
+
+    let mut names: Vec<&str> = cx.local.names().collect();
+    names.sort_unstable();
+    for name in names {
+        let current = if cx.local.is_explicit(name) {
+            Reason::Explicit
+        } else {
+            Reason::Dependency
+        };
+
+        let Some((install_date, initial)) = cx
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.changes.iter().map(move |change| (tx, change)))
+            .rfind(|(_, change)| change.name == name && change.action == Action::Installed)
+            .and_then(|(tx, change)| {
+                let reason = reason_from_command(tx.command.as_deref()?, name, &groups)?;
+                Some((change.date.as_str(), reason))
+            })
+        else {
+            continue;
+        };
+        if current == initial {
+            continue;
+        }
+
+        let marked = cx.commands.iter().rev().find(|(date, command)| {
+            date.as_str() > install_date
+                && command
+                    .split_whitespace()
+                    .any(|word| word == "-D" || word == "--database")
+                && reason_from_command(command, name, &groups) == Some(current)
+        });
+        let how = match marked {
+            Some((date, _)) => tr!("doctor-reason-marked", date = date),
+            None => tr!("doctor-reason-outside-log"),
+        };
+        findings.push(Finding::new(
+            "install-reason",
+            Severity::Info,
+            tr!(
+                "doctor-reason-changed",
+                package = name,
+                initial = initial.describe(),
+                date = install_date,
+                current = current.describe(),
+                how = how
+            ),
+        ));
+    }
+    findings
+}
+
+// Said once so the checks that need pacman aren't mistaken for passing.
+fn sandbox(_: &Context<'_>) -> Vec<Finding> {
+    if !exec::is_sandboxed() {
+        return Vec::new();
+    }
+    vec![
+        Finding::new("sandbox", Severity::Info, tr!("doctor-sandbox"))
+            .action(tr!("doctor-sandbox-action")),
+    ]
+}
+
+// Whether any check found more than information.
+pub fn run(data: &PackageData, config: &Config, palette: &Palette) -> io::Result<bool> {
+    let log = history::read_log_file().unwrap_or_default();
+    let cx = Context {
+        data,
+        config,
+        local: LocalDb::load(),
+        transactions: transactions::parse_transactions(&log),
+        commands: history::scan_commands(&log),
+        updates: pacman::pending_updates(),
+        ignored: Ignored::load(),
+    };
+    let mut findings: Vec<Finding> = DETECTORS.iter().flat_map(|detect| detect(&cx)).collect();
+    findings.sort_by_key(|finding| Reverse(finding.severity));
+
+    let mut out = io::stdout().lock();
+    let fix = tr!("doctor-fix");
+    let healthy = findings
+        .iter()
+        .all(|finding| finding.severity == Severity::Info);
+    if healthy {
+        writeln!(out, "{}", palette.ins.paint(tr!("doctor-ok")))?;
+    }
+    for finding in &findings {
+        let colour = finding.severity.colour(palette);
+        writeln!(
+            out,
+            "{} :: {} :: {}",
+            colour.bold().paint(finding.severity.label()),
+            palette.date.paint(finding.check),
+            finding.message
+        )?;
+        if let Some(action) = &finding.action {
+            writeln!(out, "     {} {action}", palette.glyph("↳", &fix))?;
+        }
+    }
+    Ok(!healthy)
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::{DateTime, FixedOffset};
+use clap::ValueEnum;
+use pkglist_core::history::{self, Status};
+use pkglist_core::packages::PackageRecord;
+use serde::Deserialize;
+
+use crate::output::Palette;
+use crate::schema::{ListedPackage, PackageList};
+
+// What the listing is rendered as: chosen with `--output`, or for `--out
+// FILE` by the file's extension. Anything unrecognised gets the plain
+// listing without escape codes.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// The listing as on the terminal
+    Text,
+    /// One document with a `schema_version` and the package records
+    Json,
+    /// One package record per line
+    Jsonl,
+    /// A header row, then one row per entry with the stable status codes
+    Csv,
+    /// A table for forum posts and issue trackers
+    Markdown,
+    /// A standalone page with one table
+    Html,
+}
+
+impl Format {
+    pub fn for_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => Format::Json,
+            Some("jsonl" | "ndjson") => Format::Jsonl,
+            Some("csv") => Format::Csv,
+            Some("md" | "markdown") => Format::Markdown,
+            Some("html" | "htm") => Format::Html,
+            _ => Format::Text,
+        }
+    }
+}
+
+struct Row {
+    date: Option<DateTime<FixedOffset>>,
+    status: Status,
+    name: String,
+    url: Option<String>,
+    versions: Option<String>,
+    estimated: bool,
+    cells: HashMap<String, String>,
+}
+
+impl Row {
+    fn record(&self) -> PackageRecord<'_> {
+        PackageRecord {
+            url: self.url.as_deref(),
+            versions: self.versions.as_deref(),
+            estimated: self.estimated,
+            ..PackageRecord::new(&self.name, self.date, self.status)
+        }
+    }
+}
+
+// Listing rows kept for one of the structured formats. Optional columns are
+// gathered by name, since markers like `ignored` only appear on some rows.
+#[derive(Default)]
+pub struct Table {
+    columns: Vec<String>,
+    rows: Vec<Row>,
+}
+
+pub fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Table {
+    pub fn push(&mut self, record: &PackageRecord<'_>, names: &[&str], values: &[String]) {
+        for name in names {
+            if !self.columns.iter().any(|column| column == name) {
+                self.columns.push(name.to_string());
+            }
+        }
+        self.rows.push(Row {
+            date: record.date,
+            status: record.status,
+            name: record.name.to_string(),
+            url: record.url.map(str::to_string),
+            versions: record.versions.map(str::to_string),
+            estimated: record.estimated,
+            cells: names
+                .iter()
+                .map(|name| name.to_string())
+                .zip(values.iter().cloned())
+                .collect(),
+        });
+    }
+
+    // The url and versions columns are fields of the record already.
+    fn records(&self) -> Vec<ListedPackage<'_>> {
+        self.rows
+            .iter()
+            .map(|row| ListedPackage {
+                record: row.record(),
+                columns: row
+                    .cells
+                    .iter()
+                    .filter(|(name, _)| !matches!(name.as_str(), "url" | "versions"))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    // Header and cells of every row; JSON and CSV keep the stable status
+    // codes, the formats meant for people use the configured labels.
+    fn grid(&self, palette: &Palette, labels: bool) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut header = vec!["date".to_string(), "status".to_string(), "name".to_string()];
+        header.extend(self.columns.iter().cloned());
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let status = if labels {
+                    palette.label(row.status).to_string()
+                } else {
+                    row.status.as_str().to_string()
+                };
+                // CSV leaves unknown dates empty rather than spelling them out.
+                let date = if labels {
+                    palette.date_text(&row.record())
+                } else {
+                    row.date
+                        .map(|date| history::format_log_date(&date))
+                        .unwrap_or_default()
+                };
+                let mut cells = vec![date, status, row.name.clone()];
+                cells.extend(
+                    self.columns
+                        .iter()
+                        .map(|column| row.cells.get(column).cloned().unwrap_or_default()),
+                );
+                cells
+            })
+            .collect();
+        (header, rows)
+    }
+
+    pub fn render(&self, format: Format, palette: &Palette) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match format {
+            Format::Text => unreachable!("text output is written record by record"),
+            Format::Json => {
+                serde_json::to_writer_pretty(&mut out, &PackageList::new(self.records()))?;
+                writeln!(out)?;
+            }
+            Format::Jsonl => {
+                for record in self.records() {
+                    serde_json::to_writer(&mut out, &record)?;
+                    writeln!(out)?;
+                }
+            }
+            Format::Csv => {
+                let (header, rows) = self.grid(palette, false);
+                for line in std::iter::once(&header).chain(&rows) {
+                    let fields: Vec<String> = line.iter().map(|field| csv_field(field)).collect();
+                    writeln!(out, "{}", fields.join(","))?;
+                }
+            }
+            Format::Markdown => {
+                let (header, rows) = self.grid(palette, true);
+                let escape = |cells: &[String]| -> String {
+                    let cells: Vec<String> =
+                        cells.iter().map(|cell| cell.replace('|', r"\|")).collect();
+                    format!("| {} |", cells.join(" | "))
+                };
+                writeln!(out, "{}", escape(&header))?;
+                writeln!(out, "|{}", "---|".repeat(header.len()))?;
+                for row in &rows {
+                    writeln!(out, "{}", escape(row))?;
+                }
+            }
+            Format::Html => {
+                let (header, rows) = self.grid(palette, true);
+                writeln!(out, "<!DOCTYPE html>")?;
+                writeln!(
+                    out,
+                    "<html><head><meta charset=\"utf-8\"><title>pkglist</title></head><body>"
+                )?;
+                writeln!(out, "<table>")?;
+                let cells = |tag: &str, cells: &[String]| -> String {
+                    cells
+                        .iter()
+                        .map(|cell| format!("<{tag}>{}</{tag}>", html_escape(cell)))
+                        .collect()
+                };
+                writeln!(out, "<thead><tr>{}</tr></thead>", cells("th", &header))?;
+                writeln!(out, "<tbody>")?;
+                for row in &rows {
+                    writeln!(out, "<tr>{}</tr>", cells("td", row))?;
+                }
+                writeln!(out, "</tbody>\n</table>\n</body></html>")?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url_table() -> Table {
+        let record = PackageRecord {
+            url: Some("https://example.org/foo"),
+            ..PackageRecord::new("foo", None, Status::Installed)
+        };
+        let mut table = Table::default();
+        table.push(&record, &["url"], &["https://example.org/foo".to_string()]);
+        table
+    }
+
+    #[test]
+    fn json_keeps_url() {
+        let out = url_table().render(Format::Json, &Palette::plain()).unwrap();
+        let list: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(list["packages"][0]["url"], "https://example.org/foo");
+
+        let out = url_table()
+            .render(Format::Jsonl, &Palette::plain())
+            .unwrap();
+        let record: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(record["url"], "https://example.org/foo");
+    }
+
+    #[test]
+    fn json_keeps_selected_columns() {
+        let record = PackageRecord::new("foo", None, Status::Installed);
+        let mut table = Table::default();
+        table.push(
+            &record,
+            &["size", "ignored"],
+            &["1.50 KiB".to_string(), "ignored".to_string()],
+        );
+        let out = table.render(Format::Jsonl, &Palette::plain()).unwrap();
+        let record: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(record["columns"]["size"], "1.50 KiB");
+        assert_eq!(record["columns"]["ignored"], "ignored");
+
+        let out = url_table().render(Format::Json, &Palette::plain()).unwrap();
+        let list: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert!(list["packages"][0].get("columns").is_none());
+    }
+}
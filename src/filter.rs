@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+
+use pkglist_core::backend;
+use pkglist_core::history::{self, Status};
+use pkglist_core::jump::{self, Jump};
+use pkglist_core::packages::PackageRecord;
+use pkglist_core::pacman::LocalDb;
+
+use crate::cli::{Cli, StatusArg};
+use crate::matching;
+use crate::repos::Repos;
+
+// The listing filters from the command line. Date bounds are prefixes of
+// the log's timestamps, so `--until 2024-07` takes in all of July.
+pub struct Filter<'a> {
+    statuses: Vec<Status>,
+    // Only upgrades count for --jump; everything else has no jump to match.
+    jumps: Vec<Jump>,
+    since: Option<&'a str>,
+    until: Option<&'a str>,
+    pattern: Option<String>,
+    // The packages of --explicit or --deps-only, sorted.
+    installed: Option<&'a [String]>,
+    // `pacman -Qm`, read only for --foreign.
+    foreign: Option<HashSet<String>>,
+    // The repositories of --repo, with where each package comes from.
+    repos: Option<(&'a [String], &'a Repos)>,
+    // --min-size in bytes, checked against the local database.
+    min_size: Option<(u64, &'a LocalDb)>,
+}
+
+impl<'a> Filter<'a> {
+    pub fn new(
+        cli: &'a Cli,
+        installed: &'a [String],
+        repos: Option<&'a Repos>,
+        local: &'a LocalDb,
+    ) -> Self {
+        Filter {
+            statuses: cli
+                .status
+                .iter()
+                .map(|status| match status {
+                    StatusArg::Ins => Status::Installed,
+                    StatusArg::Upg => Status::Upgraded,
+                    StatusArg::Rem => Status::Removed,
+                    StatusArg::Dwn => Status::Downgraded,
+                    StatusArg::Rns => Status::Reinstalled,
+                })
+                .collect(),
+            jumps: cli.jump.iter().map(|jump| jump.jump()).collect(),
+            since: cli.since.as_deref(),
+            until: cli.until.as_deref(),
+            pattern: cli
+                .pattern
+                .as_deref()
+                .map(|pattern| matching::normalize(pattern).into_owned()),
+            installed: Some(installed).filter(|_| cli.installed_only()),
+            foreign: cli
+                .foreign
+                .then(|| backend::current().foreign_packages().into_iter().collect()),
+            repos: repos
+                .filter(|_| !cli.repo.is_empty())
+                .map(|repos| (cli.repo.as_slice(), repos)),
+            min_size: cli.min_size.map(|min_size| (min_size, local)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty()
+            && self.jumps.is_empty()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.pattern.is_none()
+            && self.installed.is_none()
+            && self.foreign.is_none()
+            && self.repos.is_none()
+            && self.min_size.is_none()
+    }
+
+    pub fn accepts(&self, record: &PackageRecord<'_>) -> bool {
+        if let Some(installed) = self.installed
+            && installed
+                .binary_search_by(|name| name.as_str().cmp(record.name))
+                .is_err()
+        {
+            return false;
+        }
+        if self
+            .foreign
+            .as_ref()
+            .is_some_and(|foreign| !foreign.contains(record.name))
+        {
+            return false;
+        }
+        if let Some((wanted, repos)) = self.repos
+            && !wanted.iter().any(|repo| repo == repos.repo_of(record.name))
+        {
+            return false;
+        }
+        // Removed packages have no size and never pass.
+        if let Some((min_size, local)) = self.min_size
+            && local
+                .installed_size(record.name)
+                .is_none_or(|size| size < min_size)
+        {
+            return false;
+        }
+        if !self.statuses.is_empty() && !self.statuses.contains(&record.status) {
+            return false;
+        }
+        if !self.jumps.is_empty()
+            && (record.status != Status::Upgraded
+                || record
+                    .versions
+                    .and_then(jump::classify)
+                    .is_none_or(|jump| !self.jumps.contains(&jump)))
+        {
+            return false;
+        }
+        if self.since.is_some() || self.until.is_some() {
+            // Packages with no known date have nothing to compare.
+            let Some(date) = record.date else {
+                return false;
+            };
+            let date = history::format_log_date(&date);
+            if self.since.is_some_and(|since| date.as_str() < since) {
+                return false;
+            }
+            if self
+                .until
+                .is_some_and(|until| date.get(..until.len()).unwrap_or(&date) > until)
+            {
+                return false;
+            }
+        }
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| matching::normalize(record.name).contains(pattern.as_str()))
+    }
+}
@@ -0,0 +1,466 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+
+use pkglist_core::exec;
+use pkglist_core::pacman;
+use serde_json::json;
+
+use crate::export::{self, Format};
+use crate::i18n::tr;
+use crate::output::Palette;
+
+const CONNECT_TIMEOUT_SECS: u32 = 10;
+
+// What one host reported: package name → version, or why it couldn't.
+pub struct Host {
+    pub name: String,
+    pub packages: Result<BTreeMap<String, String>, String>,
+}
+
+// One host per line; blank lines and `#` comments are skipped, so the file
+// can be shared with other tools' inventories.
+pub fn read_hosts(path: &Path) -> io::Result<Vec<String>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    let hosts: Vec<String> = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    if hosts.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            tr!("fleet-no-hosts", file = path.display()),
+        ));
+    }
+    Ok(hosts)
+}
+
+// `pacman -Q` over ssh, without prompts so an unreachable or unknown host
+// fails instead of hanging the whole run. ssh exits with 255 for its own
+// connection errors, which are worth another try; anything else came from
+// the remote pacman.
+fn query(host: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut command = Command::new("ssh");
+    command
+        .args(["-o", "BatchMode=yes", "-o"])
+        .arg(format!("ConnectTimeout={CONNECT_TIMEOUT_SECS}"))
+        .args([host, "LC_ALL=C", "pacman", "-Q"]);
+    let output = exec::query(&mut command, |output| output.status.code() == Some(255))
+        .map_err(|e| format!("ssh: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().last().unwrap_or("").trim();
+        return Err(if reason.is_empty() {
+            tr!("fleet-ssh-exited", status = output.status)
+        } else {
+            reason.to_string()
+        });
+    }
+    Ok(pacman::parse_versions(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+// Every host is asked at once; a few dozen ssh processes are cheap next to
+// waiting on each connection in turn. Results keep the order of `hosts`.
+pub fn gather(hosts: &[String]) -> Vec<Host> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = hosts
+            .iter()
+            .map(|host| scope.spawn(move || query(host)))
+            .collect();
+        hosts
+            .iter()
+            .zip(handles)
+            .map(|(host, handle)| Host {
+                name: host.clone(),
+                packages: handle
+                    .join()
+                    .unwrap_or_else(|_| Err("query panicked".to_string())),
+            })
+            .collect()
+    })
+}
+
+fn heading(out: &mut impl Write, palette: &Palette, title: &str) -> io::Result<()> {
+    let edge = palette.glyph("──", "");
+    let text = format!("{edge} {title} {edge}");
+    writeln!(out, "{}", palette.date.bold().paint(text.trim()))
+}
+
+// Package name → version → hosts with that version, over the hosts that
+// answered.
+fn versions_by_package(hosts: &[Host]) -> BTreeMap<&str, BTreeMap<&str, Vec<&str>>> {
+    let mut packages: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+    for host in hosts {
+        let Ok(installed) = &host.packages else {
+            continue;
+        };
+        for (name, version) in installed {
+            packages
+                .entry(name)
+                .or_default()
+                .entry(version)
+                .or_default()
+                .push(&host.name);
+        }
+    }
+    packages
+}
+
+fn print_report(out: &mut impl Write, palette: &Palette, hosts: &[Host]) -> io::Result<()> {
+    heading(out, palette, &tr!("fleet-hosts"))?;
+    let width = hosts.iter().map(|host| host.name.len()).max().unwrap_or(0);
+    for host in hosts {
+        let name = palette.pkg.paint(format!("{:<width$}", host.name));
+        match &host.packages {
+            Ok(installed) => writeln!(
+                out,
+                "{name} :: {}",
+                tr!("fleet-host-packages", count = installed.len())
+            )?,
+            Err(reason) => writeln!(out, "{name} :: {}", palette.rem.paint(reason))?,
+        }
+    }
+
+    let reachable: Vec<&str> = hosts
+        .iter()
+        .filter(|host| host.packages.is_ok())
+        .map(|host| host.name.as_str())
+        .collect();
+    if reachable.len() < 2 {
+        return Ok(());
+    }
+    let packages = versions_by_package(hosts);
+
+    let partial: Vec<_> = packages
+        .iter()
+        .filter_map(|(name, versions)| {
+            let present: BTreeSet<&str> = versions.values().flatten().copied().collect();
+            let missing: Vec<&str> = reachable
+                .iter()
+                .filter(|host| !present.contains(*host))
+                .copied()
+                .collect();
+            (!missing.is_empty()).then_some((name, missing))
+        })
+        .collect();
+    if !partial.is_empty() {
+        writeln!(out)?;
+        heading(out, palette, &tr!("fleet-partial"))?;
+        for (name, missing) in partial {
+            writeln!(
+                out,
+                "{} :: {}",
+                palette.pkg.paint(*name),
+                palette
+                    .rem
+                    .paint(tr!("fleet-missing-on", hosts = missing.join(", ")))
+            )?;
+        }
+    }
+
+    let divergent: Vec<_> = packages
+        .iter()
+        .filter(|(_, versions)| versions.len() > 1)
+        .collect();
+    if !divergent.is_empty() {
+        writeln!(out)?;
+        heading(out, palette, &tr!("fleet-divergent"))?;
+        for (name, versions) in divergent {
+            let versions: Vec<String> = versions
+                .iter()
+                .map(|(version, hosts)| {
+                    format!("{} ({})", palette.upg.paint(*version), hosts.join(", "))
+                })
+                .collect();
+            writeln!(
+                out,
+                "{} :: {}",
+                palette.pkg.paint(*name),
+                versions.join(palette.glyph(" · ", "; "))
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub struct MatrixOptions<'a> {
+    pub packages: &'a [String],
+    pub format: Option<Format>,
+    pub out: Option<&'a Path>,
+}
+
+// One package across the hosts that answered. A cell differs when it isn't
+// the version most hosts have, a missing package included.
+struct MatrixRow<'a> {
+    package: &'a str,
+    versions: Vec<Option<&'a str>>,
+    differs: Vec<bool>,
+}
+
+struct Matrix<'a> {
+    hosts: Vec<&'a str>,
+    rows: Vec<MatrixRow<'a>>,
+}
+
+impl<'a> Matrix<'a> {
+    fn new(hosts: &'a [Host], selected: &'a [String]) -> Self {
+        let reachable: Vec<(&str, &BTreeMap<String, String>)> = hosts
+            .iter()
+            .filter_map(|host| Some((host.name.as_str(), host.packages.as_ref().ok()?)))
+            .collect();
+        let packages: Vec<&str> = if selected.is_empty() {
+            versions_by_package(hosts)
+                .into_iter()
+                .filter(|(_, versions)| {
+                    versions.len() > 1 || versions.values().flatten().count() < reachable.len()
+                })
+                .map(|(name, _)| name)
+                .collect()
+        } else {
+            selected.iter().map(String::as_str).collect()
+        };
+        let rows = packages
+            .into_iter()
+            .map(|package| {
+                let versions: Vec<Option<&str>> = reachable
+                    .iter()
+                    .map(|(_, installed)| installed.get(package).map(String::as_str))
+                    .collect();
+                let mut counts: BTreeMap<Option<&str>, usize> = BTreeMap::new();
+                for version in &versions {
+                    *counts.entry(*version).or_default() += 1;
+                }
+                let common = counts
+                    .into_iter()
+                    .max_by_key(|(_, count)| *count)
+                    .and_then(|(version, _)| version);
+                let differs = versions.iter().map(|version| *version != common).collect();
+                MatrixRow {
+                    package,
+                    versions,
+                    differs,
+                }
+            })
+            .collect();
+        Matrix {
+            hosts: reachable.into_iter().map(|(name, _)| name).collect(),
+            rows,
+        }
+    }
+
+    fn differing_hosts(&self, row: &MatrixRow<'_>) -> Vec<&str> {
+        self.hosts
+            .iter()
+            .zip(&row.differs)
+            .filter(|(_, differs)| **differs)
+            .map(|(host, _)| *host)
+            .collect()
+    }
+
+    fn write_text(&self, out: &mut impl Write, palette: &Palette) -> io::Result<()> {
+        // Marked as well as coloured, so mismatches survive plain output.
+        let cell = |version: Option<&str>, differs: bool| {
+            let version = version.unwrap_or("-");
+            if differs {
+                format!("{version}*")
+            } else {
+                version.to_string()
+            }
+        };
+        let name_width = self
+            .rows
+            .iter()
+            .map(|row| row.package.chars().count())
+            .chain(["package".len()])
+            .max()
+            .unwrap_or(0);
+        let widths: Vec<usize> = self
+            .hosts
+            .iter()
+            .enumerate()
+            .map(|(column, host)| {
+                self.rows
+                    .iter()
+                    .map(|row| {
+                        cell(row.versions[column], row.differs[column])
+                            .chars()
+                            .count()
+                    })
+                    .chain([host.chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut header = format!("{:<name_width$}", "package");
+        for (host, width) in self.hosts.iter().zip(&widths) {
+            header.push_str(&format!("  {host:<width$}"));
+        }
+        writeln!(out, "{}", palette.date.bold().paint(header.trim_end()))?;
+        for row in &self.rows {
+            let mut line = palette
+                .pkg
+                .paint(format!("{:<name_width$}", row.package))
+                .to_string();
+            for (column, width) in widths.iter().enumerate() {
+                let text = format!(
+                    "{:<width$}",
+                    cell(row.versions[column], row.differs[column])
+                );
+                let ink = if row.differs[column] {
+                    palette.rem
+                } else {
+                    palette.upg
+                };
+                line.push_str(&format!("  {}", ink.paint(text)));
+            }
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+
+    fn render(&self, format: Format, palette: &Palette) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match format {
+            Format::Text => self.write_text(&mut out, palette)?,
+            Format::Json | Format::Jsonl => {
+                let packages: Vec<_> = self
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let versions: serde_json::Map<_, _> = self
+                            .hosts
+                            .iter()
+                            .zip(&row.versions)
+                            .map(|(host, version)| (host.to_string(), json!(version)))
+                            .collect();
+                        json!({
+                            "name": row.package,
+                            "versions": versions,
+                            "differs": self.differing_hosts(row),
+                        })
+                    })
+                    .collect();
+                if format == Format::Json {
+                    let document = json!({ "hosts": self.hosts, "packages": packages });
+                    serde_json::to_writer_pretty(&mut out, &document)?;
+                    writeln!(out)?;
+                } else {
+                    for package in packages {
+                        serde_json::to_writer(&mut out, &package)?;
+                        writeln!(out)?;
+                    }
+                }
+            }
+            Format::Csv => {
+                let header = std::iter::once("package")
+                    .chain(self.hosts.iter().copied())
+                    .chain(["differs"]);
+                let header: Vec<String> = header.map(export::csv_field).collect();
+                writeln!(out, "{}", header.join(","))?;
+                for row in &self.rows {
+                    let fields: Vec<String> = std::iter::once(row.package)
+                        .chain(row.versions.iter().map(|version| version.unwrap_or("")))
+                        .map(export::csv_field)
+                        .chain([export::csv_field(&self.differing_hosts(row).join(" "))])
+                        .collect();
+                    writeln!(out, "{}", fields.join(","))?;
+                }
+            }
+            Format::Markdown => {
+                writeln!(out, "| package | {} |", self.hosts.join(" | "))?;
+                writeln!(out, "|{}", "---|".repeat(self.hosts.len() + 1))?;
+                for row in &self.rows {
+                    let cells: Vec<String> = row
+                        .versions
+                        .iter()
+                        .zip(&row.differs)
+                        .map(|(version, differs)| {
+                            let version = version.unwrap_or("-").replace('|', r"\|");
+                            if *differs {
+                                format!("**{version}**")
+                            } else {
+                                version
+                            }
+                        })
+                        .collect();
+                    writeln!(out, "| {} | {} |", row.package, cells.join(" | "))?;
+                }
+            }
+            Format::Html => {
+                writeln!(out, "<!DOCTYPE html>")?;
+                writeln!(
+                    out,
+                    "<html><head><meta charset=\"utf-8\"><title>pkglist fleet</title>"
+                )?;
+                writeln!(
+                    out,
+                    "<style>td.differs {{ background: #fde2e1; font-weight: bold; }}</style>"
+                )?;
+                writeln!(out, "</head><body>\n<table>")?;
+                let header: String = std::iter::once("package")
+                    .chain(self.hosts.iter().copied())
+                    .map(|cell| format!("<th>{}</th>", export::html_escape(cell)))
+                    .collect();
+                writeln!(out, "<thead><tr>{header}</tr></thead>\n<tbody>")?;
+                for row in &self.rows {
+                    let cells: String = row
+                        .versions
+                        .iter()
+                        .zip(&row.differs)
+                        .map(|(version, differs)| {
+                            let class = if *differs { " class=\"differs\"" } else { "" };
+                            let version = export::html_escape(version.unwrap_or("-"));
+                            format!("<td{class}>{version}</td>")
+                        })
+                        .collect();
+                    writeln!(
+                        out,
+                        "<tr><th>{}</th>{cells}</tr>",
+                        export::html_escape(row.package)
+                    )?;
+                }
+                writeln!(out, "</tbody>\n</table>\n</body></html>")?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn print_matrix(palette: &Palette, hosts: &[Host], options: &MatrixOptions) -> io::Result<()> {
+    for host in hosts {
+        if let Err(reason) = &host.packages {
+            eprintln!("pkglist: {}: {reason}", host.name);
+        }
+    }
+    let matrix = Matrix::new(hosts, options.packages);
+    let format = options.format.unwrap_or(Format::Text);
+    match options.out {
+        Some(path) => {
+            let bytes = matrix.render(format, &palette.uncoloured())?;
+            exec::write_file(path, &bytes, None)
+        }
+        None => {
+            let bytes = matrix.render(format, palette)?;
+            io::stdout().lock().write_all(&bytes)
+        }
+    }
+}
+
+pub fn run(palette: &Palette, hosts_file: &Path, matrix: Option<&MatrixOptions>) -> io::Result<()> {
+    let hosts = gather(&read_hosts(hosts_file)?);
+    if let Some(options) = matrix {
+        return print_matrix(palette, &hosts, options);
+    }
+    let mut out = BufWriter::new(io::stdout().lock());
+    print_report(&mut out, palette, &hosts)?;
+    out.flush()
+}
@@ -0,0 +1,121 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::history::{self, UNKNOWN_DATE};
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman;
+
+use crate::i18n::tr;
+use crate::matching;
+use crate::output::{self, Highlight, Palette};
+
+fn collect(pairs: Vec<(String, String)>) -> BTreeMap<String, BTreeSet<String>> {
+    let mut groups: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for (group, pkg) in pairs {
+        groups.entry(group).or_default().insert(pkg);
+    }
+    groups
+}
+
+fn list_groups(out: &mut impl Write, palette: &Palette, data: &PackageData) -> io::Result<()> {
+    let mut groups = collect(pacman::group_members(None, false));
+    let installed = collect(pacman::group_members(None, true));
+    // Groups of foreign or dropped packages only show up locally.
+    for group in installed.keys() {
+        groups.entry(group.clone()).or_default();
+    }
+
+    let width = groups.keys().map(String::len).max().unwrap_or(0);
+    for (group, members) in &groups {
+        let installed = installed.get(group);
+        let count = installed.map_or(0, BTreeSet::len);
+        let total = members.len().max(count);
+
+        let dates: Vec<_> = installed
+            .into_iter()
+            .flatten()
+            .filter_map(|pkg| data.history.get(pkg)?.date)
+            .collect();
+        let range = match (dates.iter().min(), dates.iter().max()) {
+            (Some(first), Some(last)) => format!(
+                "{} .. {}",
+                history::format_log_date(first),
+                history::format_log_date(last)
+            ),
+            _ => "-".to_string(),
+        };
+
+        let count_colour = if count == 0 {
+            palette.rem
+        } else if count < total {
+            palette.upg
+        } else {
+            palette.ins
+        };
+        writeln!(
+            out,
+            "{} :: {} :: {}",
+            palette.pkg.paint(format!("{group:<width$}")),
+            count_colour.paint(format!("{count:>3}/{total:<3}")),
+            palette.date.paint(range)
+        )?;
+    }
+    Ok(())
+}
+
+fn expand_group(
+    out: &mut impl Write,
+    palette: &Palette,
+    data: &PackageData,
+    group: &str,
+) -> io::Result<()> {
+    let known;
+    let group = if matching::is_exact() {
+        group
+    } else {
+        known = collect(pacman::group_members(None, false));
+        matching::find(known.keys().map(String::as_str), group).unwrap_or(group)
+    };
+    let members: BTreeSet<String> = pacman::group_members(Some(group), false)
+        .into_iter()
+        .chain(pacman::group_members(Some(group), true))
+        .map(|(_, pkg)| pkg)
+        .collect();
+    if members.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            tr!("error-group-not-found", group = group),
+        ));
+    }
+    let installed: BTreeSet<String> = pacman::group_members(Some(group), true)
+        .into_iter()
+        .map(|(_, pkg)| pkg)
+        .collect();
+
+    for name in &members {
+        if !installed.contains(name) {
+            writeln!(
+                out,
+                "{} :: --- :: {}",
+                palette.date.dimmed().paint(UNKNOWN_DATE),
+                palette
+                    .pkg
+                    .dimmed()
+                    .paint(format!("{name} (not installed)"))
+            )?;
+            continue;
+        }
+        let record = data.record(name);
+        output::write_record(out, palette, &record, &[], Highlight::default())?;
+    }
+    Ok(())
+}
+
+pub fn run(data: &PackageData, palette: &Palette, group: Option<&str>) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    match group {
+        Some(group) => expand_group(&mut out, palette, data, group)?,
+        None => list_groups(&mut out, palette, data)?,
+    }
+    out.flush()
+}
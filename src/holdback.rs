@@ -0,0 +1,44 @@
+use chrono::{Local, TimeDelta};
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::Update;
+
+use crate::boots::format_duration;
+use crate::i18n::tr;
+
+// A pending update on a package the log has not seen change for longer than
+// the configured age: usually something (IgnorePkg, a conflict, a skipped
+// `-Syu`) keeps it from going through.
+pub struct StaleUpdate {
+    pub update: Update,
+    pub unchanged: TimeDelta,
+}
+
+impl StaleUpdate {
+    pub fn describe(&self) -> String {
+        tr!(
+            "holdback-pending",
+            version = self.update.available,
+            duration = format_duration(self.unchanged)
+        )
+    }
+}
+
+pub fn stale_updates(
+    data: &PackageData,
+    updates: &[Update],
+    max_age_days: u64,
+) -> Vec<StaleUpdate> {
+    let now = Local::now().fixed_offset();
+    let max_age = TimeDelta::days(max_age_days as i64);
+    updates
+        .iter()
+        .filter_map(|update| {
+            let changed = data.history.get(&update.name).and_then(|info| info.date)?;
+            let unchanged = now - changed;
+            (unchanged > max_age).then(|| StaleUpdate {
+                update: update.clone(),
+                unchanged,
+            })
+        })
+        .collect()
+}
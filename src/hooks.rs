@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use pkglist_core::exec;
+use pkglist_core::history::{PackageInfo, Status};
+
+use crate::config::HookConfig;
+
+fn event_name(status: Status) -> &'static str {
+    match status {
+        Status::Installed => "install",
+        Status::Upgraded => "upgrade",
+        Status::Removed => "remove",
+        Status::Downgraded => "downgrade",
+        Status::Reinstalled => "reinstall",
+    }
+}
+
+// Runs each configured hook once for all packages with its kind of change.
+// The command sees the package names space-separated in $PKGLIST_PACKAGES;
+// failures are reported and the remaining hooks still run.
+pub fn run_all(hooks: &HookConfig, changes: &BTreeMap<String, PackageInfo>) {
+    for status in Status::ALL {
+        let Some(command) = hooks.command(status) else {
+            continue;
+        };
+        let names: Vec<&str> = changes
+            .iter()
+            .filter(|(_, info)| info.status == status)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if names.is_empty() {
+            continue;
+        }
+
+        let result = exec::run(
+            Command::new("sh")
+                .args(["-c", command])
+                .env("PKGLIST_EVENT", event_name(status))
+                .env("PKGLIST_PACKAGES", names.join(" "))
+                .env("PKGLIST_COUNT", names.len().to_string()),
+            None,
+        );
+        match result {
+            Ok(status) if status.success() => {}
+            Ok(exit) => eprintln!("pkglist: on_{} hook exited with {exit}", event_name(status)),
+            Err(e) => eprintln!("pkglist: on_{} hook failed: {e}", event_name(status)),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+use std::env;
+use std::io::{self, IsTerminal};
+
+use pkglist_core::backend;
+
+use crate::cli::HyperlinkMode;
+
+const ARCH_PACKAGES: &str = "https://archlinux.org/packages/?name=";
+const AUR_PACKAGES: &str = "https://aur.archlinux.org/packages/";
+
+// Terminals known to render OSC 8 links; others would show them as plain
+// text anyway, but some older ones print the escape codes.
+fn terminal_supports_links() -> bool {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    if env::var_os("KITTY_WINDOW_ID").is_some()
+        || env::var_os("WT_SESSION").is_some()
+        || env::var_os("DOMTERM").is_some()
+    {
+        return true;
+    }
+    if var("VTE_VERSION")
+        .parse::<u32>()
+        .is_ok_and(|version| version >= 5000)
+    {
+        return true;
+    }
+    let program = var("TERM_PROGRAM");
+    if ["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"].contains(&program.as_str()) {
+        return true;
+    }
+    let term = var("TERM");
+    ["kitty", "alacritty", "foot", "wezterm", "ghostty"]
+        .iter()
+        .any(|name| term.contains(name))
+}
+
+// Turns package names into clickable links to their Arch or AUR page.
+pub struct Links {
+    // Installed packages from no configured repository live in the AUR.
+    foreign: HashSet<String>,
+}
+
+impl Links {
+    pub fn detect(mode: HyperlinkMode) -> Option<Links> {
+        let enabled = match mode {
+            HyperlinkMode::Always => true,
+            HyperlinkMode::Never => false,
+            HyperlinkMode::Auto => io::stdout().is_terminal() && terminal_supports_links(),
+        };
+        enabled.then(|| Links {
+            foreign: backend::current().foreign_packages().into_iter().collect(),
+        })
+    }
+
+    pub fn url(&self, name: &str) -> String {
+        if self.foreign.contains(name) {
+            format!("{AUR_PACKAGES}{name}")
+        } else {
+            format!("{ARCH_PACKAGES}{name}")
+        }
+    }
+
+    // `text` (usually the styled name) wrapped in an OSC 8 link to `name`.
+    pub fn wrap(&self, name: &str, text: &str) -> String {
+        format!("\x1b]8;;{}\x1b\\{text}\x1b]8;;\x1b\\", self.url(name))
+    }
+}
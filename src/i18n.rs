@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+// Catalogs use a small subset of Fluent: `key = text` lines with `{ $name }`
+// placeables and `#` comments. English is the fallback for missing keys.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+];
+
+struct Catalogs {
+    selected: HashMap<&'static str, &'static str>,
+    fallback: HashMap<&'static str, &'static str>,
+}
+
+fn parse(source: &'static str) -> HashMap<&'static str, &'static str> {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(key, text)| (key.trim(), text.trim()))
+        .collect()
+}
+
+// `de_DE.UTF-8` → `de`, honouring the usual LC_ALL > LC_MESSAGES > LANG order.
+fn language() -> Option<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+    let language = locale.split(['_', '.', '@']).next()?;
+    (!matches!(language, "C" | "POSIX" | "")).then(|| language.to_string())
+}
+
+fn catalogs() -> &'static Catalogs {
+    static CATALOGS_CELL: OnceLock<Catalogs> = OnceLock::new();
+    CATALOGS_CELL.get_or_init(|| {
+        let find = |language: &str| {
+            CATALOGS
+                .iter()
+                .find(|(name, _)| *name == language)
+                .map(|(_, source)| parse(source))
+        };
+        Catalogs {
+            selected: language()
+                .and_then(|language| find(&language))
+                .unwrap_or_default(),
+            fallback: find("en").unwrap_or_default(),
+        }
+    })
+}
+
+pub fn translate(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let catalogs = catalogs();
+    let Some(template) = catalogs
+        .selected
+        .get(key)
+        .or_else(|| catalogs.fallback.get(key))
+    else {
+        return key.to_string();
+    };
+
+    let mut text = String::with_capacity(template.len());
+    let mut rest = *template;
+    while let Some(start) = rest.find("{ $") {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find(" }") else {
+            break;
+        };
+        let name = &rest[start + 3..start + end];
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => text.push_str(&value.to_string()),
+            None => text.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    text.push_str(rest);
+    text
+}
+
+// `tr!("summary-packages", count = total)`
+macro_rules! tr {
+    ($key:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::translate($key, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),*])
+    };
+}
+pub(crate) use tr;
@@ -0,0 +1,107 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta, TimeZone};
+use pkglist_core::pacman::{self, Update};
+use regex::Regex;
+
+use crate::boots::format_duration;
+use crate::i18n::tr;
+
+// `%c` in the C locale, which is how `pacman -Si` prints dates under
+// `LC_ALL=C`.
+const BUILD_DATE_FORMAT: &str = "%a %b %e %H:%M:%S %Y";
+
+// A package pacman.conf keeps from being upgraded although the sync
+// databases have another version.
+pub struct HeldBack {
+    pub update: Update,
+    // Since the available version was built, i.e. roughly since the upgrade
+    // has been skipped.
+    pub since: Option<DateTime<Local>>,
+}
+
+impl HeldBack {
+    pub fn age(&self) -> Option<TimeDelta> {
+        self.since.map(|since| Local::now() - since)
+    }
+
+    pub fn describe(&self) -> String {
+        match self.age() {
+            Some(age) => tr!(
+                "ignored-held-back-for",
+                duration = format_duration(age),
+                version = self.update.available
+            ),
+            None => tr!("ignored-held-back", version = self.update.available),
+        }
+    }
+}
+
+// IgnorePkg and IgnoreGroup from pacman.conf. IgnorePkg entries are shell
+// globs; groups are expanded through the sync databases.
+#[derive(Default)]
+pub struct Ignored {
+    patterns: Vec<Regex>,
+    members: HashSet<String>,
+}
+
+fn glob_regex(glob: &str) -> Option<Regex> {
+    let pattern = regex::escape(glob).replace(r"\*", ".*").replace(r"\?", ".");
+    Regex::new(&format!("^{pattern}$")).ok()
+}
+
+fn conf_list(option: &str) -> Vec<String> {
+    pacman::run("pacman-conf", &[option])
+        .map(|text| text.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+impl Ignored {
+    pub fn load() -> Self {
+        let patterns = conf_list("IgnorePkg")
+            .iter()
+            .filter_map(|glob| glob_regex(glob))
+            .collect();
+        let members = conf_list("IgnoreGroup")
+            .iter()
+            .flat_map(|group| pacman::group_members(Some(group), false))
+            .map(|(_, name)| name)
+            .collect();
+        Ignored { patterns, members }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.members.contains(name) || self.patterns.iter().any(|re| re.is_match(name))
+    }
+
+    pub fn held_back(&self, updates: &[Update]) -> Vec<HeldBack> {
+        let updates: Vec<Update> = updates
+            .iter()
+            .filter(|update| self.contains(&update.name))
+            .cloned()
+            .collect();
+        if updates.is_empty() {
+            return Vec::new();
+        }
+        let names: Vec<String> = updates.iter().map(|update| update.name.clone()).collect();
+        let built: HashMap<String, DateTime<Local>> = pacman::sync_info(&names)
+            .into_iter()
+            .filter_map(|block| {
+                let date =
+                    NaiveDateTime::parse_from_str(block.get("Build Date")?, BUILD_DATE_FORMAT)
+                        .ok()?;
+                Some((
+                    block.get("Name")?.clone(),
+                    Local.from_local_datetime(&date).earliest()?,
+                ))
+            })
+            .collect();
+        updates
+            .into_iter()
+            .map(|update| HeldBack {
+                since: built.get(&update.name).copied(),
+                update,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,36 @@
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::{self, LocalDb};
+use pkglist_core::size;
+
+use crate::lookup;
+use crate::output::{self, Highlight, Palette};
+
+pub fn run(data: &PackageData, palette: &Palette, package: &str) -> io::Result<()> {
+    let local = LocalDb::load();
+    let package = lookup::find(&local, package)?.name();
+    let targets = pacman::removal_targets(package)
+        .map_err(|message| io::Error::other(format!("pacman -Rns {package}: {message}")))?;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut total = 0;
+    for name in &targets {
+        let bytes = local.installed_size(name).unwrap_or(0);
+        total += bytes;
+        output::write_record(
+            &mut out,
+            palette,
+            &data.record(name),
+            &[size::format_size(bytes)],
+            Highlight::default(),
+        )?;
+    }
+    writeln!(
+        out,
+        "{} package(s) would be removed, freeing {}",
+        targets.len(),
+        palette.upg.paint(size::format_size(total))
+    )?;
+    out.flush()
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use pkglist_core::pacman::LocalDb;
+
+use crate::output::Palette;
+use crate::transactions;
+
+const MODULES_DIR: &str = "/usr/lib/modules";
+const OSRELEASE_PATH: &str = "/proc/sys/kernel/osrelease";
+
+// The official kernels; custom ones are picked up through their `pkgbase`.
+const KNOWN_KERNELS: &[&str] = &[
+    "linux",
+    "linux-lts",
+    "linux-zen",
+    "linux-hardened",
+    "linux-rt",
+    "linux-rt-lts",
+];
+
+// Every kernel package installs `/usr/lib/modules/<release>/pkgbase`
+// containing its own name, which maps module trees back to packages.
+fn module_trees() -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(MODULES_DIR) else {
+        return Vec::new();
+    };
+    let mut trees: Vec<(String, String)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let release = entry.file_name().into_string().ok()?;
+            let pkgbase = fs::read_to_string(entry.path().join("pkgbase")).ok()?;
+            Some((release, pkgbase.trim().to_string()))
+        })
+        .collect();
+    trees.sort();
+    trees
+}
+
+fn booted_release() -> Option<String> {
+    fs::read_to_string(OSRELEASE_PATH)
+        .ok()
+        .map(|release| release.trim().to_string())
+}
+
+// After an upgrade the running kernel's module tree is gone, so any module
+// not loaded yet (USB storage, filesystems, ...) fails until a reboot.
+pub fn missing_booted_modules() -> Option<String> {
+    let release = booted_release()?;
+    let modules = Path::new(MODULES_DIR);
+    (modules.is_dir() && !modules.join(&release).is_dir()).then_some(release)
+}
+
+pub fn run(palette: &Palette) -> io::Result<()> {
+    let local = LocalDb::load();
+    let trees = module_trees();
+    let booted = booted_release();
+    let booted_pkg = booted.as_ref().and_then(|release| {
+        trees
+            .iter()
+            .find(|(tree, _)| tree == release)
+            .map(|(_, pkg)| pkg.as_str())
+    });
+
+    let mut kernels: Vec<&str> = KNOWN_KERNELS
+        .iter()
+        .copied()
+        .filter(|name| local.field(name, "Version").is_some())
+        .collect();
+    for (_, pkg) in &trees {
+        if !kernels.contains(&pkg.as_str()) {
+            kernels.push(pkg);
+        }
+    }
+
+    let transactions = transactions::load().unwrap_or_default();
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    if kernels.is_empty() {
+        writeln!(out, "no kernel packages installed")?;
+    }
+    for kernel in &kernels {
+        let version = local.field(kernel, "Version").unwrap_or("not installed");
+        write!(
+            out,
+            "{} :: {}",
+            palette.pkg.paint(*kernel),
+            palette.upg.paint(version)
+        )?;
+        if booted_pkg == Some(*kernel) {
+            write!(out, " :: {}", palette.ins.bold().paint("booted"))?;
+        }
+        writeln!(out)?;
+
+        let changes = transactions
+            .iter()
+            .flat_map(|tx| &tx.changes)
+            .filter(|change| change.name == *kernel);
+        for change in changes {
+            writeln!(
+                out,
+                "  {} :: {} :: {}",
+                palette.date.paint(&change.date),
+                change
+                    .action
+                    .colour(palette)
+                    .paint(change.action.label(&palette.labels)),
+                change.versions()
+            )?;
+        }
+    }
+
+    if let Some(release) = missing_booted_modules() {
+        writeln!(
+            out,
+            "{}",
+            palette.rem.bold().paint(format!(
+                "warning: modules for the booted kernel {release} were removed by an upgrade; reboot to load new modules"
+            ))
+        )?;
+    }
+    out.flush()
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::{DateTime, FixedOffset};
+use pkglist_core::dirs;
+use pkglist_core::exec;
+use pkglist_core::history;
+
+const MARKER_FILE: &str = "last_viewed";
+const DIGEST_MARKER_FILE: &str = "last_digest";
+
+// The marker is the newest event date shown by the previous run of the same
+// kind; anything logged after it is new to the reader. Runs on a terminal
+// and `--new-only` digests for cron or pipes keep separate markers, so a
+// digest never hides changes from the person at the terminal or the other
+// way round.
+pub struct LastViewed {
+    path: Option<PathBuf>,
+    marker: Option<i64>,
+}
+
+fn timestamp(date: &str) -> Option<i64> {
+    history::parse_log_date(date).map(|date| date.timestamp())
+}
+
+impl LastViewed {
+    pub fn load(interactive: bool) -> Self {
+        let file = if interactive {
+            MARKER_FILE
+        } else {
+            DIGEST_MARKER_FILE
+        };
+        let path = dirs::state_dir().map(|dir| dir.join(file));
+        let marker = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|date| timestamp(date.trim()));
+        LastViewed { path, marker }
+    }
+
+    pub fn is_recorded(&self) -> bool {
+        self.marker.is_some()
+    }
+
+    pub fn is_new(&self, date: Option<DateTime<FixedOffset>>) -> bool {
+        match (self.marker, date) {
+            (Some(marker), Some(date)) => date.timestamp() > marker,
+            _ => false,
+        }
+    }
+
+    pub fn save(&self, dates: impl IntoIterator<Item = DateTime<FixedOffset>>) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let Some(newest) = dates.into_iter().max() else {
+            return Ok(());
+        };
+        let newest = history::format_log_date(&newest);
+        exec::write_file(path, newest.as_bytes(), None)
+    }
+}
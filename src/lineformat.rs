@@ -0,0 +1,103 @@
+use pkglist_core::jump::{self, Jump};
+use pkglist_core::packages::PackageRecord;
+use pkglist_core::pacman::LocalDb;
+use pkglist_core::size;
+
+use crate::notes::Notes;
+use crate::output::Palette;
+use crate::repos::Repos;
+use crate::template;
+
+// Placeholders read from the local package database, which the listing then
+// loads once up front.
+const LOCAL: &[&str] = &["size", "reason", "url", "description"];
+
+fn uses(template: &str, name: &str) -> bool {
+    template.contains(&format!("{{{name}}}")) || template.contains(&format!("{{{name}|"))
+}
+
+// `--format`: every record on a line of its own, laid out by a template like
+// expac's. Values a package doesn't have, like the size of a removed one,
+// are `-`, as in the columns.
+pub struct LineFormat {
+    template: String,
+    notes: Notes,
+}
+
+impl LineFormat {
+    pub fn new(template: &str) -> Self {
+        let template = template::unescape(template);
+        let notes = if uses(&template, "note") {
+            Notes::load()
+        } else {
+            Notes::default()
+        };
+        LineFormat { template, notes }
+    }
+
+    pub fn needs_local(&self) -> bool {
+        LOCAL.iter().any(|name| uses(&self.template, name))
+    }
+
+    pub fn needs_repos(&self) -> bool {
+        uses(&self.template, "repo")
+    }
+
+    pub fn render(
+        &self,
+        record: &PackageRecord<'_>,
+        palette: &Palette,
+        local: &LocalDb,
+        repos: Option<&Repos>,
+    ) -> String {
+        let field = |key| local.field(record.name, key).unwrap_or("-");
+        let reason = match local.field(record.name, "Install Reason") {
+            Some(_) if local.is_explicit(record.name) => "explicit",
+            Some(_) => "dependency",
+            None => "-",
+        };
+        let size = local
+            .installed_size(record.name)
+            .map_or_else(|| "-".to_string(), size::format_size);
+        // The version the event left behind: the new one of an upgrade.
+        let version = record
+            .versions
+            .and_then(|versions| versions.rsplit(" -> ").next())
+            .unwrap_or("-");
+        let values = [
+            ("date", palette.date_text(record)),
+            ("status", record.status.as_str().to_string()),
+            ("label", palette.label(record.status).to_string()),
+            ("name", record.name.to_string()),
+            ("version", version.to_string()),
+            ("versions", record.versions.unwrap_or("-").to_string()),
+            (
+                "jump",
+                record
+                    .versions
+                    .and_then(jump::classify)
+                    .map_or("-", Jump::as_str)
+                    .to_string(),
+            ),
+            (
+                "repo",
+                repos
+                    .map_or("-", |repos| repos.repo_of(record.name))
+                    .to_string(),
+            ),
+            ("size", size),
+            ("reason", reason.to_string()),
+            ("url", field("URL").to_string()),
+            ("description", field("Description").to_string()),
+            (
+                "note",
+                self.notes.get(record.name).unwrap_or("-").to_string(),
+            ),
+        ];
+        let values: Vec<(&str, &str)> = values
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        template::render(&self.template, &values)
+    }
+}
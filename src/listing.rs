@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::backend;
+use pkglist_core::exec;
+use pkglist_core::packages::{PackageData, PackageRecord};
+use pkglist_core::pacman::LocalDb;
+
+use crate::boots::RebootMarkers;
+use crate::cli::{Cli, Column, CopyKind, GroupBy, SortKey};
+use crate::clipboard;
+use crate::columns::{self, Columns};
+use crate::config::Config;
+use crate::days::DayGroups;
+use crate::export::{Format, Table};
+use crate::filter::Filter;
+use crate::holdback;
+use crate::i18n::tr;
+use crate::ignored::Ignored;
+use crate::lastrun::LastViewed;
+use crate::lineformat::LineFormat;
+use crate::mirrors;
+use crate::output::{self, Highlight, Palette};
+use crate::pager;
+use crate::repos::{RepoGroups, Repos};
+use crate::risk;
+use crate::script::ScriptHooks;
+use crate::summary::Summary;
+use crate::watchlist::Watchlist;
+
+// The records in an order that can't be streamed. The sorts are stable over
+// the date order, so ties stay chronological (or, reversed, newest first).
+fn sorted_records<'a>(
+    data: &'a PackageData,
+    local: &LocalDb,
+    sort: SortKey,
+    reverse: bool,
+) -> Vec<PackageRecord<'a>> {
+    let mut records = data.records_by_date();
+    match sort {
+        SortKey::Date => {}
+        SortKey::Name => records.sort_by_key(|record| record.name),
+        SortKey::Status => records.sort_by_key(|record| record.status),
+        SortKey::Size => records.sort_by_cached_key(|record| local.installed_size(record.name)),
+    }
+    if reverse {
+        records.reverse();
+    }
+    records
+}
+
+// What the listing needs besides the log and the command line, gathered
+// once so `pkglist repl` can render again without asking pacman each time.
+pub struct Listing<'a> {
+    config: &'a Config,
+    hooks: ScriptHooks,
+    watchlist: Watchlist<'a>,
+    ignored: Ignored,
+    held_back: HashMap<String, String>,
+    stale: HashMap<String, String>,
+    // Whether a full listing on the terminal moves the last-viewed marker.
+    remember: bool,
+}
+
+impl<'a> Listing<'a> {
+    pub fn new(config: &'a Config, data: &PackageData, remember: bool) -> io::Result<Self> {
+        let ignored = Ignored::load();
+        let updates = backend::current().pending_updates();
+        let held_back = ignored
+            .held_back(&updates)
+            .into_iter()
+            .map(|held| (held.update.name.clone(), held.describe()))
+            .collect();
+        let stale = holdback::stale_updates(data, &updates, config.holdback.max_age_days)
+            .into_iter()
+            .map(|stale| (stale.update.name.clone(), stale.describe()))
+            .collect();
+        Ok(Listing {
+            config,
+            hooks: ScriptHooks::new(&config.script)?,
+            watchlist: Watchlist::new(config),
+            ignored,
+            held_back,
+            stale,
+            remember,
+        })
+    }
+
+    pub fn print(&self, cli: &Cli, palette: &Palette, data: &PackageData) -> io::Result<()> {
+        let line_format = cli.format.as_deref().map(LineFormat::new);
+        let repos = (cli.group_by == Some(GroupBy::Repo)
+            || !cli.repo.is_empty()
+            || line_format.as_ref().is_some_and(LineFormat::needs_repos))
+        .then(Repos::load);
+        let mut requested = cli.columns.clone();
+        if cli.all {
+            requested.push(Column::Reason);
+        }
+        if cli.show_size {
+            requested.push(Column::Size);
+        }
+        // Sizes, sorting by them and the columns share one `pacman -Qi`.
+        let local = if cli.sort == SortKey::Size
+            || cli.min_size.is_some()
+            || columns::needs_local(&requested)
+            || line_format.as_ref().is_some_and(LineFormat::needs_local)
+        {
+            LocalDb::load()
+        } else {
+            LocalDb::default()
+        };
+        let filter = Filter::new(cli, &data.installed, repos.as_ref(), &local);
+        let format = output_format(cli);
+        let to_terminal = cli.out.is_none() && format == Format::Text;
+        let interactive = pager::stdout_is_terminal() && to_terminal;
+        let plain_palette;
+        let palette = if to_terminal {
+            palette
+        } else {
+            plain_palette = palette.uncoloured();
+            &plain_palette
+        };
+        let mut table = Table::default();
+        let mut buffer = Vec::new();
+        let mut clip = Vec::new();
+        let columns = Columns::new(&requested, &local);
+        let width = columns::terminal_width().filter(|_| interactive);
+        let mut reboots = RebootMarkers::new(
+            cli.reboots && cli.sort == SortKey::Date && !cli.reverse && cli.group_by.is_none(),
+        );
+        let mut days = DayGroups::new(cli.by_day && cli.sort == SortKey::Date, cli.by_status);
+        let mut repo_groups = RepoGroups::new(repos.as_ref().filter(|_| cli.group_by.is_some()));
+        let mut summary = Summary::default();
+        let mut listed = 0;
+        let mut out: Box<dyn Write> = if to_terminal {
+            Box::new(BufWriter::new(io::stdout().lock()))
+        } else {
+            Box::new(&mut buffer)
+        };
+
+        let last_viewed = LastViewed::load(interactive);
+        if cli.new_only && !last_viewed.is_recorded() && !cli.quiet_unless_changes {
+            eprintln!("pkglist: {}", tr!("listing-no-previous-run"));
+        }
+
+        let mut emit = |record: PackageRecord<'_>| -> io::Result<()> {
+            let fresh = last_viewed.is_new(record.date);
+            if !filter.accepts(&record)
+                || (cli.new_only && !fresh)
+                || !self.hooks.accepts(&record)?
+            {
+                return Ok(());
+            }
+            listed += 1;
+            reboots.before(&mut out, palette, record.date)?;
+            summary.record(record.status);
+            if let Some(line_format) = &line_format {
+                let line = line_format.render(&record, palette, &local, repos.as_ref());
+                match cli.copy {
+                    Some(CopyKind::Listing) => writeln!(clip, "{line}")?,
+                    Some(CopyKind::Names) => writeln!(clip, "{}", record.name)?,
+                    None => {}
+                }
+                return writeln!(out, "{line}");
+            }
+            let mut extra = Vec::new();
+            let mut names: Vec<&str> = Vec::new();
+            columns.push_fixed(&mut extra, &record, palette);
+            names.extend(columns.fixed_names());
+            for (column, value) in self
+                .config
+                .script
+                .columns
+                .iter()
+                .zip(self.hooks.columns(&record)?)
+            {
+                extra.push(palette.fit(&column.name, &value).into_owned());
+                names.push(&column.name);
+            }
+            if self.ignored.contains(record.name) {
+                extra.push(match self.held_back.get(record.name) {
+                    Some(held) => format!("ignored, {held}"),
+                    None => "ignored".to_string(),
+                });
+                names.push("ignored");
+            }
+            if let Some(source) = data
+                .history
+                .get(record.name)
+                .and_then(|info| info.source.as_deref())
+            {
+                extra.push(format!("from {source}"));
+                names.push("source");
+            }
+            if let Some(stale) = self.stale.get(record.name) {
+                extra.push(format!("{} {stale}", palette.glyph("!!", "warning:")));
+                names.push("holdback");
+            }
+            columns.push_flex(&mut extra, &record, palette, width);
+            names.extend(columns.flex_name());
+            let highlight = Highlight {
+                watched: self.watchlist.contains(record.name),
+                fresh,
+            };
+            match cli.copy {
+                Some(CopyKind::Listing) => {
+                    output::write_record(&mut clip, palette, &record, &extra, highlight)?
+                }
+                Some(CopyKind::Names) => writeln!(clip, "{}", record.name)?,
+                None => {}
+            }
+            if format != Format::Text {
+                // The JSON formats carry the url as a field of the record.
+                let record = if requested.contains(&Column::Url) {
+                    record.with_local(&local)
+                } else {
+                    record
+                };
+                table.push(&record, &names, &extra);
+                return Ok(());
+            }
+            if repo_groups.enabled() {
+                return repo_groups.write(palette, &record, &extra, highlight);
+            }
+            days.write(&mut out, palette, &record, &extra, highlight)
+        };
+
+        match (cli.sort, cli.reverse) {
+            (SortKey::Date, false) => data.records_by_date().into_iter().try_for_each(&mut emit)?,
+            (SortKey::Name, false) => data.for_each_by_name(&mut emit)?,
+            (sort, reverse) => sorted_records(data, &local, sort, reverse)
+                .into_iter()
+                .try_for_each(&mut emit)?,
+        }
+        // For cron: no changes, no output, so no mail.
+        let silent = cli.quiet_unless_changes && listed == 0;
+        days.finish(&mut out, palette)?;
+        repo_groups.finish(&mut out, palette)?;
+        if !silent {
+            reboots.finish(&mut out, palette)?;
+        }
+        out.flush()?;
+        drop(out);
+
+        if format != Format::Text {
+            buffer = table.render(format, palette)?;
+        }
+        match &cli.out {
+            Some(path) => exec::write_file(path, &buffer, None)?,
+            None if !to_terminal && !silent => io::stdout().lock().write_all(&buffer)?,
+            None => {}
+        }
+        if cli.copy.is_some() {
+            clipboard::copy(&String::from_utf8_lossy(&clip))?;
+        }
+
+        // Only full listings a person looked at move the terminal's marker, and
+        // only full `--new-only` digests move theirs, so other pipelines and
+        // filtered listings don't swallow the changes.
+        if self.remember && (interactive || cli.new_only) && filter.is_empty() {
+            last_viewed.save(data.history.values().filter_map(|info| info.date))?;
+        }
+
+        // Warnings go to stderr so they stay visible without polluting the listing.
+        summary.warnings = risk::partial_upgrade_warnings(data)
+            .into_iter()
+            .map(|warning| tr!("risk-partial-upgrade", warning = warning))
+            .collect();
+        // Asking the mirror needs the network, so only an explicit --summary does.
+        if cli.summary && !silent {
+            let stale = mirrors::staleness(self.config.mirrors.max_age_days);
+            summary
+                .warnings
+                .extend(stale.into_iter().map(|warning| warning.message));
+        }
+        if !silent && (cli.summary || !summary.warnings.is_empty()) {
+            summary.write(&mut io::stderr().lock(), palette)?;
+        }
+        Ok(())
+    }
+}
+
+// A `--format` template writes its own lines, so `--out` keeps them as they
+// are whatever the file's extension.
+fn output_format(cli: &Cli) -> Format {
+    if cli.format.is_some() {
+        return Format::Text;
+    }
+    cli.output
+        .or_else(|| cli.out.as_deref().map(Format::for_path))
+        .unwrap_or(Format::Text)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn template_keeps_its_lines_in_structured_files() {
+        let cli = Cli::parse_from([
+            "pkglist",
+            "--format",
+            "{name} {version}",
+            "--out",
+            "out.json",
+        ]);
+        assert_eq!(output_format(&cli), Format::Text);
+
+        let cli = Cli::parse_from(["pkglist", "--out", "out.json"]);
+        assert_eq!(output_format(&cli), Format::Json);
+    }
+}
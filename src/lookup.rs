@@ -0,0 +1,115 @@
+use std::io;
+
+use pkglist_core::pacman::LocalDb;
+
+use crate::i18n::tr;
+use crate::matching;
+
+const MAX_SUGGESTIONS: usize = 5;
+const MAX_DISTANCE: usize = 2;
+
+// How a name typed on the command line maps onto an installed package.
+#[derive(Debug, Clone, Copy)]
+pub enum Match<'a> {
+    Exact(&'a str),
+    // `Python_Foo` → python-foo with --ignore-case/--loose-match
+    Spelled(&'a str),
+    // `awk` → gawk
+    Provided(&'a str),
+    // `vim` → gvim, which conflicts with (and so stands in for) vim
+    Conflicting(&'a str),
+}
+
+impl<'a> Match<'a> {
+    pub fn name(self) -> &'a str {
+        match self {
+            Match::Exact(name)
+            | Match::Spelled(name)
+            | Match::Provided(name)
+            | Match::Conflicting(name) => name,
+        }
+    }
+
+    // A line explaining the substitution, if there was one.
+    pub fn note(self, query: &str) -> Option<String> {
+        match self {
+            Match::Exact(_) => None,
+            Match::Spelled(name) => Some(format!("{query} matched {name}")),
+            Match::Provided(name) => Some(format!("{query} is provided by {name}")),
+            Match::Conflicting(name) => Some(format!(
+                "{query} is not installed; {name} conflicts with it"
+            )),
+        }
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// Installed names close to `query`: typos first, then names containing it.
+fn suggestions<'a>(local: &'a LocalDb, query: &str) -> Vec<&'a str> {
+    let mut close: Vec<(usize, &str)> = local
+        .names()
+        .chain(local.provided_names())
+        .filter_map(|name| {
+            let distance = edit_distance(query, name);
+            if distance <= MAX_DISTANCE {
+                Some((distance, name))
+            } else if name.contains(query) {
+                Some((MAX_DISTANCE + 1, name))
+            } else {
+                None
+            }
+        })
+        .collect();
+    close.sort_unstable();
+    close.dedup_by_key(|(_, name)| *name);
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+// Resolves `query` through names, `Provides` and `Conflicts With`; when
+// nothing matches, the error lists the nearest installed names.
+pub fn find<'a>(local: &'a LocalDb, query: &str) -> io::Result<Match<'a>> {
+    if let Some(name) = local.resolve(query) {
+        return Ok(if name == query {
+            Match::Exact(name)
+        } else {
+            Match::Provided(name)
+        });
+    }
+    if let Some(name) = matching::find(local.names(), query) {
+        return Ok(Match::Spelled(name));
+    }
+    if let Some(name) =
+        matching::find(local.provided_names(), query).and_then(|name| local.resolve(name))
+    {
+        return Ok(Match::Provided(name));
+    }
+    if let Some(name) = local.conflicting(query) {
+        return Ok(Match::Conflicting(name));
+    }
+
+    let mut message = tr!("error-not-installed", package = query);
+    let close = suggestions(local, query);
+    if !close.is_empty() {
+        message.push_str("; ");
+        message.push_str(&tr!("error-did-you-mean", names = close.join(", ")));
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, message))
+}
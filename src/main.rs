@@ -3,203 +3,327 @@
 // 2025-08-28
 //
 
-use std::collections::HashMap;
-use std::fs;
-use std::io::{self, Read};
-use std::path::Path;
-use std::process::Command;
-
-use ansi_term::Colour::RGB;
-use lazy_static::lazy_static;
-use memchr::memchr;
-use regex::Regex;
-use serde::{Deserialize, Serialize};
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct PackageInfo {
-    date: String,
-    status: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct CacheData {
-    pkg_hash: u64,
-    last_log_size: u64,
-    data: HashMap<String, PackageInfo>,
-}
-
-lazy_static! {
-    static ref LOG_REGEX: Regex =
-        Regex::new(r"\[([0-9T:+-]+)\] \[ALPM\] (installed|upgraded|removed) ([^\s(]+)").unwrap();
+mod actionlog;
+mod adopt;
+mod audit;
+mod backups;
+mod baseline;
+mod boots;
+mod cli;
+mod clipboard;
+mod columns;
+mod config;
+mod days;
+mod diff;
+mod doctor;
+mod export;
+mod filter;
+mod fleet;
+mod groups;
+mod holdback;
+mod hooks;
+mod hyperlinks;
+mod i18n;
+mod ignored;
+mod impact;
+mod kernels;
+mod lastrun;
+mod lineformat;
+mod listing;
+mod lookup;
+mod manpage;
+mod mark;
+mod matching;
+mod mirrors;
+mod notes;
+mod notify;
+mod output;
+mod pager;
+mod pkgcache;
+mod pkghistory;
+mod plugin;
+mod profile;
+#[cfg(feature = "sql")]
+mod query;
+mod repl;
+mod replacements;
+mod report;
+mod repos;
+mod risk;
+mod rollback;
+mod schema;
+mod script;
+#[cfg(feature = "self-update")]
+mod selfupdate;
+mod show;
+mod snapshot;
+mod sparkline;
+mod stats;
+mod summary;
+mod template;
+mod transactions;
+mod tree;
+mod verify;
+mod version;
+mod watch;
+mod watchlist;
+mod why;
+
+use std::env;
+use std::ffi::OsString;
+use std::io;
+use std::process;
+
+use chrono::Local;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use pkglist_core::packages::PackageData;
+use pkglist_core::{actions, backend, cache, exec, flatpak, growth, history, imports, progress};
+
+use cli::{
+    CacheCommand, Cli, Commands, ProgressFormat, SnapshotCommand, Source, TransactionsCommand,
+};
+use config::Config;
+use export::Format;
+use hyperlinks::Links;
+use listing::Listing;
+use output::Palette;
+use pager::Pager;
+
+// The exit codes scripts can rely on. Findings only count with --exit-code.
+const EXIT_OK: i32 = 0;
+const EXIT_FINDINGS: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+
+fn findings_code(cli: &Cli, found: bool) -> i32 {
+    if found && cli.exit_code {
+        EXIT_FINDINGS
+    } else {
+        EXIT_OK
+    }
 }
 
-fn get_log_size() -> u64 {
-    fs::metadata("/var/log/pacman.log")
-        .map(|m| m.len())
-        .unwrap_or(0)
+fn load_data(cli: &Cli) -> PackageData {
+    let mut data = PackageData::load(cli.cache_file().as_deref(), cli.install_scope());
+    if cli.include.contains(&Source::Flatpak) {
+        flatpak::merge(&mut data, cli.install_scope());
+    }
+    data
 }
 
-fn calculate_pkg_hash(pkgs: &[String]) -> u64 {
-    use std::hash::{Hash, Hasher};
-    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-    pkgs.hash(&mut hasher);
-    hasher.finish()
-}
+fn run(mut cli: Cli, matches: &ArgMatches, plugin_args: &[OsString]) -> io::Result<i32> {
+    if cli.schema {
+        return schema::print_schema().map(|()| 0);
+    }
 
-fn parse_log_entries(log_content: &[u8]) -> HashMap<String, PackageInfo> {
-    let mut map = HashMap::new();
-    let mut pos = 0;
+    let mut config = Config::load()?;
+    profile::apply(&mut cli, matches, &mut config)?;
+    let cli = &cli;
+    backend::set(cli.backend.backend());
+    if let Some(log) = &cli.log {
+        history::set_log_path(log.clone());
+    }
+    if let Some(path) = actions::default_path() {
+        actions::set_path(path);
+    }
+    if let Some(path) = growth::default_path() {
+        growth::set_path(path);
+    }
+    exec::set_limits(config.exec.limits());
+    exec::set_elevation(config.elevation);
+    if cli.sandbox || !exec::on_path(backend::current().program()) {
+        exec::enable_sandbox();
+    }
+    if cli.version {
+        return version::print_version(cli.cache_file().as_deref()).map(|()| 0);
+    }
 
-    while let Some(newline_pos) = memchr(b'\n', &log_content[pos..]) {
-        let line_end = pos + newline_pos;
-        let line = &log_content[pos..line_end];
-        pos = line_end + 1;
+    if !plugin_args.is_empty() {
+        return plugin::run(plugin_args, &load_data(cli));
+    }
 
-        if line.len() < 50 {
-            continue;
+    matching::configure(cli.ignore_case, cli.loose_match);
+    if cli.dry_run {
+        exec::enable_dry_run();
+    }
+    if cli.progress == Some(ProgressFormat::Json) {
+        progress::enable();
+    }
+    let palette = Palette {
+        links: Links::detect(cli.hyperlinks),
+        ..Palette::from_config(&config, cli.accessible, cli.theme, cli.color)
+    };
+    // Dropped at the end of the run, after the last of the output.
+    let _pager = Pager::start(cli);
+
+    match &cli.command {
+        Some(Commands::Adopt { interactive }) => adopt::run(&palette, *interactive).map(|()| 0),
+        Some(Commands::Audit { role, versions }) => if *versions {
+            audit::run_versions(&config, &palette, &load_data(cli))
+        } else {
+            audit::run(&config, &palette, &load_data(cli), role)
         }
-
-        if let Some(caps) = LOG_REGEX.captures(std::str::from_utf8(line).unwrap_or("")) {
-            let date_str = caps.get(1).unwrap().as_str();
-            let action = caps.get(2).unwrap().as_str();
-            let pkg_name = caps.get(3).unwrap().as_str();
-
-            let status = match action {
-                "installed" => "INS".to_string(),
-                "upgraded" => "UPG".to_string(),
-                "removed" => "REM".to_string(),
-                _ => continue,
-            };
-
-            map.insert(
-                pkg_name.to_string(),
-                PackageInfo {
-                    date: date_str.to_string(),
-                    status,
-                },
+        .map(|found| findings_code(cli, found)),
+        Some(Commands::Backups { packages, all }) => {
+            backups::run(&load_data(cli), &palette, packages, *all).map(|()| 0)
+        }
+        Some(Commands::Cache { command }) => match command {
+            CacheCommand::Compact { before } => {
+                let cache_file = cli.cache_file().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "--no-cache leaves nothing to compact",
+                    )
+                })?;
+                // Brings the cache up to date with the log first.
+                load_data(cli);
+                cache::compact(&cache_file, before)
+            }
+        }
+        .map(|()| 0),
+        Some(Commands::CacheUsage { keep, top }) => {
+            pkgcache::run(&palette, *keep, *top).map(|()| 0)
+        }
+        Some(Commands::Diff { baseline }) => match baseline {
+            Some(baseline) => baseline::run(&config, &palette, baseline, cli.quiet_unless_changes),
+            None => diff::run(&palette, cli.quiet_unless_changes),
+        }
+        .map(|found| findings_code(cli, found)),
+        Some(Commands::Doctor) => {
+            doctor::run(&load_data(cli), &config, &palette).map(|found| findings_code(cli, found))
+        }
+        Some(Commands::Export { bundle }) => snapshot::bundle(bundle).map(|()| 0),
+        Some(Commands::Fleet {
+            hosts,
+            matrix,
+            packages,
+            output,
+            out,
+        }) => {
+            let matrix = matrix.then(|| fleet::MatrixOptions {
+                packages,
+                format: output.or_else(|| out.as_deref().map(Format::for_path)),
+                out: out.as_deref(),
+            });
+            fleet::run(&palette, hosts, matrix.as_ref()).map(|()| 0)
+        }
+        Some(Commands::Groups { group }) => {
+            groups::run(&load_data(cli), &palette, group.as_deref()).map(|()| 0)
+        }
+        Some(Commands::History { package }) => pkghistory::run(&palette, package).map(|()| 0),
+        Some(Commands::ImportLog { file, label }) => imports::import(file, label).map(|count| {
+            println!(
+                "imported {count} package(s) from {} as '{label}'",
+                file.display()
             );
+            0
+        }),
+        Some(Commands::Impact { package }) => {
+            impact::run(&load_data(cli), &palette, package).map(|()| 0)
         }
-    }
-    map
-}
-
-fn read_current_packages() -> Vec<String> {
-    Command::new("pacman")
-        .args(["-Qeq"])
-        .output()
-        .ok()
-        .and_then(|output| {
-            if output.status.success() {
-                Some(
-                    String::from_utf8_lossy(&output.stdout)
-                        .lines()
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
-                        .collect(),
-                )
-            } else {
-                None
+        Some(Commands::Kernels) => kernels::run(&palette).map(|()| 0),
+        Some(Commands::Log { limit }) => actionlog::run(&palette, *limit).map(|()| 0),
+        Some(Commands::Man { out_dir }) => manpage::generate(out_dir.as_deref()).map(|()| 0),
+        Some(Commands::Mark {
+            packages,
+            asexplicit,
+            ..
+        }) => mark::run(&palette, packages, *asexplicit).map(|()| 0),
+        Some(Commands::Note {
+            package,
+            text,
+            remove,
+        }) => notes::run(&palette, package.as_deref(), text.as_deref(), *remove).map(|()| 0),
+        Some(Commands::Notify) => notify::run(&config, &load_data(cli), &palette).map(|()| 0),
+        #[cfg(feature = "sql")]
+        Some(Commands::Query { sql }) => query::run(&load_data(cli), &palette, sql).map(|()| 0),
+        Some(Commands::Repl) => {
+            repl::run(&config, &palette, &load_data(cli), cli.profile.as_deref()).map(|()| 0)
+        }
+        Some(Commands::Report { month, html, out }) => {
+            let month = month
+                .clone()
+                .unwrap_or_else(|| Local::now().format("%Y-%m").to_string());
+            report::run(&palette, &month, *html, out.as_deref()).map(|()| 0)
+        }
+        Some(Commands::Rollback { package, to, run }) => {
+            rollback::run(&palette, package, to.as_deref(), *run).map(|()| 0)
+        }
+        Some(Commands::Show { package }) => {
+            show::run(&load_data(cli), &palette, package).map(|()| 0)
+        }
+        Some(Commands::Snapshot { command }) => match command {
+            SnapshotCommand::Save { file } => snapshot::save(file).map(|()| 0),
+            SnapshotCommand::Diff { a, b } => {
+                snapshot::diff(&palette, a, b.as_deref(), cli.quiet_unless_changes)
+                    .map(|found| findings_code(cli, found))
             }
-        })
-        .unwrap_or_default()
-}
-
-fn load_cache(cache_file: &Path) -> Option<CacheData> {
-    fs::read(cache_file)
-        .ok()
-        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
-}
-
-fn save_cache(cache_file: &Path, data: &CacheData) -> io::Result<()> {
-    let temp_file = cache_file.with_extension("tmp");
-    fs::write(&temp_file, serde_json::to_vec(data)?)?;
-    fs::rename(temp_file, cache_file)?;
-    Ok(())
-}
-
-fn read_log_file() -> io::Result<Vec<u8>> {
-    let mut file = fs::File::open("/var/log/pacman.log")?;
-    let metadata = file.metadata()?;
-    let mut buffer = Vec::with_capacity(metadata.len() as usize);
-    file.read_to_end(&mut buffer)?;
-    Ok(buffer)
-}
-
-fn main() -> io::Result<()> {
-    let date_color = RGB(203, 166, 247);
-    let pkg_color = RGB(137, 180, 250);
-    let ins_color = RGB(166, 227, 161);
-    let upg_color = RGB(249, 226, 175);
-    let rem_color = RGB(250, 179, 135);
-    let err_color = RGB(243, 139, 168);
-
-    let cache_file = Path::new("/tmp/pkglist_cache.json");
-    let current_pkgs = read_current_packages();
-
-    if current_pkgs.is_empty() {
-        return Ok(());
-    }
-
-    let current_pkg_hash = calculate_pkg_hash(&current_pkgs);
-    let current_log_size = get_log_size();
-
-    let mut cache_data = match load_cache(cache_file) {
-        Some(data)
-            if data.pkg_hash == current_pkg_hash && data.last_log_size == current_log_size =>
-        {
-            data
-        }
-        _ => {
-            let log_content = read_log_file().unwrap_or_default();
-            let parsed_data = parse_log_entries(&log_content);
-
-            CacheData {
-                pkg_hash: current_pkg_hash,
-                last_log_size: current_log_size,
-                data: parsed_data,
+            SnapshotCommand::RestoreScript { file } => snapshot::restore_script(file).map(|()| 0),
+        },
+        Some(Commands::Stats {
+            licenses,
+            license,
+            growth,
+            jumps,
+        }) => {
+            let options = stats::StatsOptions {
+                licenses: *licenses,
+                license: license.as_deref(),
+                growth: *growth,
+                jumps: *jumps,
+                since: cli.since.as_deref(),
+                until: cli.until.as_deref(),
+            };
+            stats::run(&load_data(cli), &palette, &options).map(|()| 0)
+        }
+        Some(Commands::Transactions { command }) => match command {
+            TransactionsCommand::List { reboots } => transactions::list(&palette, *reboots),
+            TransactionsCommand::Show { id } => transactions::show(&palette, *id),
+        }
+        .map(|()| 0),
+        Some(Commands::Tree { package, depth }) => {
+            tree::run(&load_data(cli), &palette, package, *depth).map(|()| 0)
+        }
+        Some(Commands::Why { package }) => why::run(&load_data(cli), &palette, package).map(|()| 0),
+        Some(Commands::Verify { packages, deep, .. }) => {
+            verify::run(&load_data(cli), &palette, packages, *deep).map(|()| 0)
+        }
+        #[cfg(feature = "self-update")]
+        Some(Commands::SelfUpdate { check }) => selfupdate::run(*check).map(|()| 0),
+        None => {
+            let data = load_data(cli);
+            if let Err(e) = watchlist::check(&config, &data) {
+                eprintln!("pkglist: watchlist: {e}");
             }
+            if !data.is_empty() {
+                Listing::new(&config, &data, true)?.print(cli, &palette, &data)?;
+            }
+            if cli.watch {
+                watch::run(cli, &config, &palette, data, || load_data(cli))?;
+            }
+            Ok(0)
         }
-    };
-
-    if cache_data.pkg_hash != current_pkg_hash || cache_data.last_log_size != current_log_size {
-        let log_content = read_log_file().unwrap_or_default();
-        cache_data.data = parse_log_entries(&log_content);
-        cache_data.last_log_size = current_log_size;
-        cache_data.pkg_hash = current_pkg_hash;
-
-        let _ = save_cache(cache_file, &cache_data);
-    }
-
-    let mut pkg_set = HashMap::with_capacity(cache_data.data.len() + current_pkgs.len());
-
-    for (pkg, info) in &cache_data.data {
-        pkg_set.insert(pkg.clone(), (info.date.clone(), info.status.clone()));
-    }
-
-    for pkg in &current_pkgs {
-        pkg_set
-            .entry(pkg.clone())
-            .or_insert_with(|| ("0000-00-00T00:00:00+0000".to_string(), "INS".to_string()));
-    }
-
-    let mut pkg_list: Vec<_> = pkg_set.into_iter().collect();
-    pkg_list.sort_unstable_by(|(_, (date1, _)), (_, (date2, _))| date1.cmp(date2));
-
-    for (pkg, (date, status)) in pkg_list {
-        let status_colored = match status.as_str() {
-            "INS" => ins_color.paint(&status),
-            "UPG" => upg_color.paint(&status),
-            "REM" => rem_color.paint(&status),
-            _ => err_color.paint(&status),
-        };
-
-        println!(
-            "{} :: {} :: {}",
-            date_color.paint(date),
-            status_colored,
-            pkg_color.paint(pkg)
-        );
     }
+}
 
-    Ok(())
+fn main() {
+    let args: Vec<OsString> = env::args_os().collect();
+    // A plugin gets the data the options before its name select, and its own
+    // arguments after it.
+    let (options, plugin_args) = match plugin::requested(&args) {
+        Some(at) => args.split_at(at),
+        None => (args.as_slice(), &[][..]),
+    };
+    let matches = Cli::command().get_matches_from(options);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let result = run(cli, &matches, plugin_args);
+
+    let code = match result {
+        Ok(code) => code,
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => EXIT_OK,
+        Err(e) => {
+            eprintln!("pkglist: {e}");
+            EXIT_ERROR
+        }
+    };
+    process::exit(code);
 }
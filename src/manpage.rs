@@ -0,0 +1,37 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use clap::CommandFactory;
+use clap_mangen::Man;
+use pkglist_core::exec;
+
+use crate::cli::Cli;
+
+// Without a directory only the top-level page goes to stdout; with one, a
+// page per visible subcommand is written next to it as `pkglist-<cmd>.1`.
+pub fn generate(out_dir: Option<&Path>) -> io::Result<()> {
+    let mut cmd = Cli::command();
+    cmd.build();
+
+    let Some(out_dir) = out_dir else {
+        let mut out = io::stdout().lock();
+        Man::new(cmd).render(&mut out)?;
+        return out.flush();
+    };
+
+    exec::create_dir_all(out_dir)?;
+    write_page(out_dir, Man::new(cmd.clone()))?;
+    for sub in cmd
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+    {
+        write_page(out_dir, Man::new(sub.clone()))?;
+    }
+    Ok(())
+}
+
+fn write_page(out_dir: &Path, man: Man) -> io::Result<()> {
+    let mut page = Vec::new();
+    man.render(&mut page)?;
+    exec::write_file(&out_dir.join(man.get_filename()), &page, None)
+}
@@ -0,0 +1,121 @@
+use std::io::{self, BufRead, BufWriter, IsTerminal, Write};
+use std::process::Command;
+
+use pkglist_core::exec;
+use pkglist_core::pacman::LocalDb;
+
+use crate::i18n::tr;
+use crate::matching;
+use crate::output::{self, Palette};
+
+// Keeps each `pacman -D` command line well below ARG_MAX.
+const BATCH_SIZE: usize = 200;
+
+// Accepts listing rows (`date :: status :: name :: ...`, coloured or not)
+// as well as bare package names, one per line.
+fn selection_name(line: &str) -> Option<String> {
+    let line = output::strip_escapes(line);
+    let name = if line.contains(" :: ") {
+        line.split(" :: ").nth(2)?.trim()
+    } else {
+        line.split_whitespace().next()?
+    };
+    Some(name.to_string())
+}
+
+fn read_selection() -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for line in io::stdin().lock().lines() {
+        names.extend(selection_name(&line?));
+    }
+    Ok(names)
+}
+
+fn reason(explicit: bool) -> String {
+    if explicit {
+        tr!("mark-explicit")
+    } else {
+        tr!("mark-dependency")
+    }
+}
+
+// Sets the install reason of the named packages, or of the selection piped
+// in on stdin, in as few `pacman -D` calls as possible after one
+// confirmation. Packages that already have the reason are left alone.
+pub fn run(palette: &Palette, packages: &[String], explicit: bool) -> io::Result<()> {
+    let names = if packages.is_empty() && !io::stdin().is_terminal() {
+        read_selection()?
+    } else {
+        packages.to_vec()
+    };
+    let local = LocalDb::load();
+    let mut names: Vec<String> = names
+        .iter()
+        .map(|name| {
+            matching::find(local.names(), name)
+                .unwrap_or(name)
+                .to_string()
+        })
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut targets: Vec<&str> = Vec::new();
+    let mut unchanged = 0;
+    for name in &names {
+        if local.field(name, "Name").is_none() {
+            eprintln!("pkglist: {}", tr!("mark-not-installed", package = name));
+        } else if local.is_explicit(name) == explicit {
+            unchanged += 1;
+        } else {
+            targets.push(name);
+        }
+    }
+
+    let to = tr!("mark-to");
+    let mut out = BufWriter::new(io::stdout().lock());
+    for name in &targets {
+        writeln!(
+            out,
+            "{} :: {} {} {}",
+            palette.pkg.paint(*name),
+            reason(!explicit),
+            palette.glyph("→", &to),
+            palette.upg.paint(reason(explicit))
+        )?;
+    }
+    if unchanged > 0 {
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "mark-unchanged",
+                count = unchanged,
+                reason = reason(explicit)
+            )
+        )?;
+    }
+    out.flush()?;
+
+    let flag = if explicit { "--asexplicit" } else { "--asdeps" };
+    let mut commands: Vec<Command> = targets
+        .chunks(BATCH_SIZE)
+        .map(|batch| {
+            let mut command = exec::privileged("pacman");
+            command.args(["-D", flag]).args(batch);
+            command
+        })
+        .collect();
+    let prompt = tr!(
+        "mark-prompt",
+        count = targets.len(),
+        reason = reason(explicit)
+    );
+    if targets.is_empty() || !exec::confirm_commands(&prompt, &commands)? {
+        return Ok(());
+    }
+    for command in &mut commands {
+        exec::run_checked(command)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// How names typed on the command line are compared with package and group
+// names: exactly by default, set once from `--ignore-case`/`--loose-match`.
+static IGNORE_CASE: AtomicBool = AtomicBool::new(false);
+static LOOSE: AtomicBool = AtomicBool::new(false);
+
+pub fn configure(ignore_case: bool, loose: bool) {
+    IGNORE_CASE.store(ignore_case, Ordering::Relaxed);
+    LOOSE.store(loose, Ordering::Relaxed);
+}
+
+pub fn is_exact() -> bool {
+    !IGNORE_CASE.load(Ordering::Relaxed) && !LOOSE.load(Ordering::Relaxed)
+}
+
+// `Python_Foo` → `python_foo` with --ignore-case, `Python-Foo` with
+// --loose-match, `python-foo` with both.
+pub fn normalize(name: &str) -> Cow<'_, str> {
+    let mut name = Cow::Borrowed(name);
+    if IGNORE_CASE.load(Ordering::Relaxed) && name.chars().any(char::is_uppercase) {
+        name = Cow::Owned(name.to_lowercase());
+    }
+    if LOOSE.load(Ordering::Relaxed) && name.contains('_') {
+        name = Cow::Owned(name.replace('_', "-"));
+    }
+    name
+}
+
+pub fn matches(candidate: &str, query: &str) -> bool {
+    candidate == query || (!is_exact() && normalize(candidate) == normalize(query))
+}
+
+// The first of `candidates` that `query` names, preferring an exact match.
+pub fn find<'a>(candidates: impl IntoIterator<Item = &'a str>, query: &str) -> Option<&'a str> {
+    let mut loose = None;
+    for candidate in candidates {
+        if candidate == query {
+            return Some(candidate);
+        }
+        if loose.is_none() && matches(candidate, query) {
+            loose = Some(candidate);
+        }
+    }
+    loose
+}
@@ -0,0 +1,92 @@
+use std::process::Command;
+
+use chrono::{DateTime, Utc};
+use pkglist_core::exec;
+use pkglist_core::pacman;
+use pkglist_core::progress;
+
+use crate::boots::format_duration;
+use crate::i18n::tr;
+
+// Tier 1 and 2 mirrors publish the time of their last upstream sync next to
+// the repositories.
+const LASTUPDATE_FILE: &str = "lastupdate";
+const FETCH_TIMEOUT_SECS: &str = "10";
+// curl's exit codes for failing to resolve, connect or finish in time; an
+// HTTP error (22) is the mirror's answer and isn't retried.
+const CURL_TRANSIENT: [i32; 3] = [6, 7, 28];
+
+pub struct MirrorWarning {
+    pub message: String,
+    pub action: String,
+}
+
+// `https://host/archlinux/core/os/x86_64` → `https://host/archlinux`
+fn mirror_root(server: &str, repo: &str) -> Option<String> {
+    if !server.starts_with("http") {
+        return None;
+    }
+    let marker = format!("/{repo}/os/");
+    server.find(&marker).map(|end| server[..end].to_string())
+}
+
+fn upstream_lastupdate(root: &str) -> Option<DateTime<Utc>> {
+    let url = format!("{root}/{LASTUPDATE_FILE}");
+    let mut task = progress::Task::start("fetch-mirror-lastupdate", "requests", Some(1));
+    let output = exec::query(
+        Command::new("curl").args(["-fsSL", "--max-time", FETCH_TIMEOUT_SECS, &url]),
+        |output| {
+            output
+                .status
+                .code()
+                .is_some_and(|code| CURL_TRANSIENT.contains(&code))
+        },
+    )
+    .ok()
+    .filter(|output| output.status.success())?;
+    task.set(1);
+    let text = String::from_utf8_lossy(&output.stdout);
+    DateTime::from_timestamp(text.trim().parse().ok()?, 0)
+}
+
+// Warns when the local databases or the primary mirror have not been
+// refreshed for `max_age_days`: either makes "no updates" misleading.
+pub fn staleness(max_age_days: u64) -> Vec<MirrorWarning> {
+    let mut warnings = Vec::new();
+    let now = Utc::now();
+    let max_age = chrono::TimeDelta::days(max_age_days as i64);
+
+    if let Some(synced) = pacman::sync_db_mtime().map(DateTime::<Utc>::from) {
+        let age = now - synced;
+        if age > max_age {
+            warnings.push(MirrorWarning {
+                message: tr!("mirrors-db-stale", age = format_duration(age)),
+                action: tr!("mirrors-db-stale-action"),
+            });
+        }
+    }
+
+    let Some(repo) = pacman::configured_repos().into_iter().next() else {
+        return warnings;
+    };
+    let Some(root) = pacman::servers(&repo)
+        .first()
+        .and_then(|server| mirror_root(server, &repo))
+    else {
+        return warnings;
+    };
+    if let Some(updated) = upstream_lastupdate(&root) {
+        let age = now - updated;
+        if age > max_age {
+            warnings.push(MirrorWarning {
+                message: tr!(
+                    "mirrors-upstream-stale",
+                    mirror = root,
+                    age = format_duration(age)
+                ),
+                action: tr!("mirrors-upstream-stale-action"),
+            });
+        }
+    }
+    warnings
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use pkglist_core::dirs;
+use pkglist_core::exec;
+use pkglist_core::pacman::LocalDb;
+
+use crate::matching;
+use crate::output::Palette;
+
+const NOTES_FILE: &str = "notes.json";
+
+// Why packages are installed, in the user's words, keyed by package name.
+// The file is pretty-printed and sorted so it can live in a dotfiles repo.
+#[derive(Default)]
+pub struct Notes {
+    notes: BTreeMap<String, String>,
+}
+
+fn notes_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(NOTES_FILE))
+}
+
+impl Notes {
+    // A missing or unreadable file is no notes at all, so the listing and
+    // `show` never fail over them.
+    pub fn load() -> Self {
+        let notes = notes_file()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Notes { notes }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.notes.get(name).map(String::as_str)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let path = notes_file()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+        let mut json = serde_json::to_vec_pretty(&self.notes)?;
+        json.push(b'\n');
+        exec::write_file(&path, &json, None)
+    }
+}
+
+// `pkglist note`: every note without a package, the package's note without
+// text, and otherwise sets it. Names resolve like everywhere else, but a
+// package that isn't installed can carry a note too, for when it returns.
+pub fn run(
+    palette: &Palette,
+    package: Option<&str>,
+    text: Option<&str>,
+    remove: bool,
+) -> io::Result<()> {
+    let mut notes = Notes::load();
+    let mut out = BufWriter::new(io::stdout().lock());
+    let Some(package) = package else {
+        for (name, note) in &notes.notes {
+            writeln!(out, "{} :: {note}", palette.pkg.paint(name))?;
+        }
+        return out.flush();
+    };
+    let local = LocalDb::load();
+    let name = matching::find(local.names(), package)
+        .unwrap_or(package)
+        .to_string();
+    if remove {
+        if notes.notes.remove(&name).is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{name} has no note"),
+            ));
+        }
+        return notes.save();
+    }
+    match text {
+        Some(text) => {
+            notes.notes.insert(name, text.trim().to_string());
+            notes.save()
+        }
+        None => {
+            if let Some(note) = notes.get(&name) {
+                writeln!(out, "{note}")?;
+            }
+            out.flush()
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use chrono::{DateTime, FixedOffset};
+use pkglist_core::dirs;
+use pkglist_core::exec;
+use pkglist_core::history::{self, PackageInfo};
+use pkglist_core::packages::PackageData;
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::hooks;
+use crate::i18n::tr;
+use crate::output::Palette;
+use crate::template;
+
+const STATE_FILE: &str = "notify.json";
+
+// Seconds a webhook may take before curl gives up on it.
+const WEBHOOK_TIMEOUT_SECS: &str = "30";
+const DEFAULT_WEBHOOK_TEMPLATE: &str =
+    r#"{"title": {title|json}, "body": {body|json}, "count": {count}}"#;
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Notifier {
+    Desktop,
+    Command {
+        command: String,
+        // Replaces the changes on stdin.
+        template: Option<String>,
+    },
+    // POSTs the rendered template, JSON unless `content_type` says
+    // otherwise, e.g. to a Slack, Discord or Home Assistant webhook.
+    Webhook {
+        url: String,
+        template: Option<String>,
+        content_type: Option<String>,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+/// One changed package as notifications describe it.
+pub struct Change<'a> {
+    pub name: &'a str,
+    pub label: &'a str,
+    pub date: Option<DateTime<FixedOffset>>,
+}
+
+// curl reads its URL, headers and payload from a config on stdin, so tokens
+// in them show up in neither the process list nor the audit log.
+fn curl_config(url: &str, headers: &[String], payload: &str) -> String {
+    let quote = |value: &str| {
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+            .replace('\t', "\\t");
+        format!("\"{escaped}\"")
+    };
+    let mut config = format!("url = {}\n", quote(url));
+    for header in headers {
+        config.push_str(&format!("header = {}\n", quote(header)));
+    }
+    config.push_str(&format!("data-binary = {}\n", quote(payload)));
+    config
+}
+
+impl Notifier {
+    // For error messages, which mustn't show a webhook's URL or headers.
+    fn describe(&self) -> &str {
+        match self {
+            Notifier::Desktop => "desktop",
+            Notifier::Command { command, .. } => command,
+            Notifier::Webhook { .. } => "webhook",
+        }
+    }
+
+    fn send(&self, title: &str, changes: &[Change<'_>]) -> io::Result<()> {
+        let body: Vec<String> = changes
+            .iter()
+            .map(|change| {
+                format!(
+                    "{} :: {} :: {}",
+                    change.name,
+                    change.label,
+                    history::date_or_unknown(change.date)
+                )
+            })
+            .collect();
+        let body = body.join("\n");
+        let count = changes.len().to_string();
+        let packages: Vec<&str> = changes.iter().map(|change| change.name).collect();
+        let packages = packages.join(" ");
+        let values = [
+            ("title", title),
+            ("body", body.as_str()),
+            ("count", count.as_str()),
+            ("packages", packages.as_str()),
+        ];
+        let status = match self {
+            Notifier::Desktop => exec::run(
+                Command::new("notify-send")
+                    .args(["--app-name=pkglist", title, &body])
+                    .stdin(Stdio::null()),
+                None,
+            )?,
+            Notifier::Command { command, template } => {
+                let input = match template {
+                    Some(template) => template::render(template, &values),
+                    None => format!("{body}\n"),
+                };
+                exec::run(
+                    Command::new("sh")
+                        .args(["-c", command])
+                        .env("PKGLIST_TITLE", title),
+                    Some(input.as_bytes()),
+                )?
+            }
+            Notifier::Webhook {
+                url,
+                template,
+                content_type,
+                headers,
+            } => {
+                let template = template.as_deref().unwrap_or(DEFAULT_WEBHOOK_TEMPLATE);
+                let content_type = content_type.as_deref().unwrap_or("application/json");
+                let headers: Vec<String> = std::iter::once(format!("Content-Type: {content_type}"))
+                    .chain(
+                        headers
+                            .iter()
+                            .map(|(name, value)| format!("{name}: {value}")),
+                    )
+                    .collect();
+                let config = curl_config(url, &headers, &template::render(template, &values));
+                exec::run(
+                    Command::new("curl").args([
+                        "--config",
+                        "-",
+                        "--fail",
+                        "--silent",
+                        "--show-error",
+                        "--max-time",
+                        WEBHOOK_TIMEOUT_SECS,
+                    ]),
+                    Some(config.as_bytes()),
+                )?
+            }
+        };
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("exited with {status}")))
+        }
+    }
+}
+
+// Notification failures are reported but never abort the run that found
+// the changes; the listing itself is still useful.
+pub fn send_all(notifiers: &[Notifier], title: &str, changes: &[Change<'_>]) {
+    for notifier in notifiers {
+        if let Err(e) = notifier.send(title, changes) {
+            eprintln!("pkglist: notifier {} failed: {e}", notifier.describe());
+        }
+    }
+}
+
+// The latest event of every package as of the previous `pkglist notify`.
+fn changes_since_last_check(
+    state_file: &Path,
+    data: &PackageData,
+) -> Option<BTreeMap<String, PackageInfo>> {
+    let previous: BTreeMap<String, PackageInfo> =
+        serde_json::from_slice(&fs::read(state_file).ok()?).ok()?;
+    Some(
+        data.history
+            .iter()
+            .filter(|(name, info)| previous.get(*name) != Some(info))
+            .map(|(name, info)| (name.clone(), info.clone()))
+            .collect(),
+    )
+}
+
+// Compares every package against the previous check, prints what changed,
+// fires the notifiers and runs the configured hooks. Like the watchlist, the
+// first check only records a baseline.
+pub fn run(config: &Config, data: &PackageData, palette: &Palette) -> io::Result<()> {
+    let Some(state_file) = dirs::state_dir().map(|dir| dir.join(STATE_FILE)) else {
+        return Ok(());
+    };
+
+    if let Some(changes) = changes_since_last_check(&state_file, data)
+        && !changes.is_empty()
+    {
+        let mut out = BufWriter::new(io::stdout().lock());
+        for (name, info) in &changes {
+            writeln!(
+                out,
+                "{} :: {} :: {}",
+                palette.date.paint(history::date_or_unknown(info.date)),
+                palette
+                    .status(info.status)
+                    .paint(palette.label(info.status)),
+                palette.pkg.paint(name)
+            )?;
+        }
+        out.flush()?;
+
+        let changed: Vec<Change<'_>> = changes
+            .iter()
+            .map(|(name, info)| Change {
+                name,
+                label: palette.label(info.status),
+                date: info.date,
+            })
+            .collect();
+        let title = tr!("notify-changed", count = changes.len());
+        send_all(&config.notifiers, &title, &changed);
+        hooks::run_all(&config.hooks, &changes);
+    }
+
+    exec::write_file(&state_file, &serde_json::to_vec(&data.history)?, None)
+}
@@ -0,0 +1,382 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+
+use ansi_term::Colour::{self, RGB};
+use ansi_term::{ANSIString, Style};
+use chrono::{DateTime, FixedOffset, Local};
+use lazy_static::lazy_static;
+use pkglist_core::history::{self, Status, UNKNOWN_DATE};
+use pkglist_core::packages::PackageRecord;
+use regex::Regex;
+
+use crate::cli::{ColorMode, Theme};
+use crate::columns;
+use crate::config::{AgeBands, Align, ColorOverrides, ColorSpec, ColumnLayout, Config, Labels};
+use crate::hyperlinks::Links;
+
+lazy_static! {
+    static ref ANSI_ESCAPE: Regex =
+        Regex::new(r"\x1b\[[0-9;]*m|\x1b\]8;[^\x07\x1b]*(?:\x07|\x1b\\)").unwrap();
+}
+
+// Drops colour codes and hyperlinks from text that was painted for the terminal.
+pub fn strip_escapes(text: &str) -> Cow<'_, str> {
+    ANSI_ESCAPE.replace_all(text, "")
+}
+
+// `auto` colours a terminal unless NO_COLOR is set to anything non-empty
+// (https://no-color.org).
+fn use_colour(mode: ColorMode) -> bool {
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => {
+            env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && io::stdout().is_terminal()
+        }
+    }
+}
+
+// A palette colour that can be switched off; with `None` every style it
+// produces is plain and paints no escape codes.
+#[derive(Debug, Clone, Copy)]
+pub struct Ink(Option<Colour>);
+
+impl Ink {
+    pub fn normal(self) -> Style {
+        self.0.map(Colour::normal).unwrap_or_default()
+    }
+
+    pub fn bold(self) -> Style {
+        self.0.map(Colour::bold).unwrap_or_default()
+    }
+
+    pub fn dimmed(self) -> Style {
+        self.0.map(Colour::dimmed).unwrap_or_default()
+    }
+
+    pub fn paint<'a, I: Into<Cow<'a, str>>>(self, input: I) -> ANSIString<'a> {
+        self.normal().paint(input)
+    }
+}
+
+pub struct Palette {
+    pub date: Ink,
+    pub pkg: Ink,
+    pub ins: Ink,
+    pub upg: Ink,
+    pub rem: Ink,
+    pub dwn: Ink,
+    pub rns: Ink,
+    pub watched: Ink,
+    pub recent: Ink,
+    pub aging: Ink,
+    pub old: Ink,
+    pub labels: Labels,
+    pub layouts: HashMap<String, ColumnLayout>,
+    pub age_bands: Option<AgeBands>,
+    // Screen-reader friendly output: no colour, words instead of glyphs and
+    // box drawing, one record per line.
+    pub accessible: bool,
+    // OSC 8 links on package names, when the terminal renders them.
+    pub links: Option<Links>,
+}
+
+impl Palette {
+    pub fn catppuccin() -> Self {
+        Palette {
+            date: Ink(Some(RGB(203, 166, 247))),
+            pkg: Ink(Some(RGB(137, 180, 250))),
+            ins: Ink(Some(RGB(166, 227, 161))),
+            upg: Ink(Some(RGB(249, 226, 175))),
+            rem: Ink(Some(RGB(250, 179, 135))),
+            dwn: Ink(Some(RGB(235, 160, 172))),
+            rns: Ink(Some(RGB(137, 220, 235))),
+            watched: Ink(Some(RGB(243, 139, 168))),
+            recent: Ink(Some(RGB(166, 227, 161))),
+            aging: Ink(Some(RGB(249, 226, 175))),
+            old: Ink(Some(RGB(243, 139, 168))),
+            labels: Labels::default(),
+            layouts: HashMap::new(),
+            age_bands: None,
+            accessible: false,
+            links: None,
+        }
+    }
+
+    pub fn gruvbox() -> Self {
+        Palette {
+            date: Ink(Some(RGB(211, 134, 155))),
+            pkg: Ink(Some(RGB(131, 165, 152))),
+            ins: Ink(Some(RGB(184, 187, 38))),
+            upg: Ink(Some(RGB(250, 189, 47))),
+            rem: Ink(Some(RGB(254, 128, 25))),
+            dwn: Ink(Some(RGB(204, 36, 29))),
+            rns: Ink(Some(RGB(142, 192, 124))),
+            watched: Ink(Some(RGB(251, 73, 52))),
+            recent: Ink(Some(RGB(184, 187, 38))),
+            aging: Ink(Some(RGB(250, 189, 47))),
+            old: Ink(Some(RGB(251, 73, 52))),
+            ..Palette::catppuccin()
+        }
+    }
+
+    pub fn nord() -> Self {
+        Palette {
+            date: Ink(Some(RGB(180, 142, 173))),
+            pkg: Ink(Some(RGB(136, 192, 208))),
+            ins: Ink(Some(RGB(163, 190, 140))),
+            upg: Ink(Some(RGB(235, 203, 139))),
+            rem: Ink(Some(RGB(208, 135, 112))),
+            dwn: Ink(Some(RGB(191, 97, 106))),
+            rns: Ink(Some(RGB(143, 188, 187))),
+            watched: Ink(Some(RGB(191, 97, 106))),
+            recent: Ink(Some(RGB(163, 190, 140))),
+            aging: Ink(Some(RGB(235, 203, 139))),
+            old: Ink(Some(RGB(191, 97, 106))),
+            ..Palette::catppuccin()
+        }
+    }
+
+    pub fn plain() -> Self {
+        Palette {
+            date: Ink(None),
+            pkg: Ink(None),
+            ins: Ink(None),
+            upg: Ink(None),
+            rem: Ink(None),
+            dwn: Ink(None),
+            rns: Ink(None),
+            watched: Ink(None),
+            recent: Ink(None),
+            aging: Ink(None),
+            old: Ink(None),
+            ..Palette::catppuccin()
+        }
+    }
+
+    // The theme with the config's `[colors]` on top, or no colour at all for
+    // accessible output and when `color` says so.
+    pub fn from_config(config: &Config, accessible: bool, theme: Theme, color: ColorMode) -> Self {
+        let base = if accessible || !use_colour(color) {
+            Palette::plain()
+        } else {
+            let themed = match theme {
+                Theme::Catppuccin => Palette::catppuccin(),
+                Theme::Gruvbox => Palette::gruvbox(),
+                Theme::Nord => Palette::nord(),
+                Theme::None => Palette::plain(),
+            };
+            themed.with_overrides(&config.colors)
+        };
+        Palette {
+            labels: config.labels.clone(),
+            layouts: config.columns.clone(),
+            age_bands: config.age_bands,
+            accessible,
+            ..base
+        }
+    }
+
+    fn with_overrides(self, colors: &ColorOverrides) -> Self {
+        let ink = |spec: Option<ColorSpec>, ink: Ink| spec.map_or(ink, |spec| Ink(spec.0));
+        Palette {
+            date: ink(colors.date, self.date),
+            pkg: ink(colors.pkg, self.pkg),
+            ins: ink(colors.ins, self.ins),
+            upg: ink(colors.upg, self.upg),
+            rem: ink(colors.rem, self.rem),
+            dwn: ink(colors.dwn, self.dwn),
+            rns: ink(colors.rns, self.rns),
+            watched: ink(colors.watched, self.watched),
+            recent: ink(colors.recent, self.recent),
+            aging: ink(colors.aging, self.aging),
+            old: ink(colors.old, self.old),
+            ..self
+        }
+    }
+
+    // The same labels and layouts without colour, for output going to files.
+    pub fn uncoloured(&self) -> Self {
+        Palette {
+            labels: self.labels.clone(),
+            layouts: self.layouts.clone(),
+            accessible: self.accessible,
+            ..Palette::plain()
+        }
+    }
+
+    // A package name already styled as `painted`, linked to its page when
+    // hyperlinks are on.
+    pub fn name(&self, name: &str, painted: ANSIString<'_>) -> String {
+        match &self.links {
+            Some(links) => links.wrap(name, &painted.to_string()),
+            None => painted.to_string(),
+        }
+    }
+
+    // The date colour, or with `[age_bands]` the colour of the date's band.
+    pub fn date_ink(&self, date: Option<DateTime<FixedOffset>>) -> Ink {
+        let (Some(bands), Some(date)) = (self.age_bands, date) else {
+            return self.date;
+        };
+        let days = (Local::now().fixed_offset() - date).num_days();
+        match u64::try_from(days).unwrap_or(0) {
+            days if days < bands.recent_days => self.recent,
+            days if days < bands.aging_days => self.aging,
+            _ => self.old,
+        }
+    }
+
+    // A record's date as listed: dates from the local database are marked
+    // as approximate, and missing ones say so.
+    pub fn date_text(&self, record: &PackageRecord<'_>) -> String {
+        match record.date {
+            None => UNKNOWN_DATE.to_string(),
+            Some(date) if record.estimated => {
+                format!(
+                    "{}{}",
+                    self.glyph("~", "est. "),
+                    history::format_log_date(&date)
+                )
+            }
+            Some(date) => history::format_log_date(&date),
+        }
+    }
+
+    pub fn label(&self, status: Status) -> &str {
+        self.labels.label(status)
+    }
+
+    pub fn status(&self, status: Status) -> Ink {
+        match status {
+            Status::Installed => self.ins,
+            Status::Upgraded => self.upg,
+            Status::Removed => self.rem,
+            Status::Downgraded => self.dwn,
+            Status::Reinstalled => self.rns,
+        }
+    }
+
+    // `text` cut to the column's configured maximum and padded to its
+    // minimum; columns without a layout pass through unchanged.
+    pub fn fit<'a>(&self, column: &str, text: &'a str) -> Cow<'a, str> {
+        let Some(layout) = self.layouts.get(column) else {
+            return Cow::Borrowed(text);
+        };
+        let text = match layout.max {
+            Some(max) => Cow::Owned(columns::truncate(text, max, self.glyph("…", "..."))),
+            None => Cow::Borrowed(text),
+        };
+        let width = layout.min.unwrap_or(0);
+        match layout.align {
+            _ if text.chars().count() >= width => text,
+            Align::Left => Cow::Owned(format!("{text:<width$}")),
+            Align::Right => Cow::Owned(format!("{text:>width$}")),
+        }
+    }
+
+    // Glyph or its spelled-out form in accessible mode.
+    pub fn glyph<'a>(&self, glyph: &'a str, words: &'a str) -> &'a str {
+        if self.accessible { words } else { glyph }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Highlight {
+    pub watched: bool,
+    pub fresh: bool,
+}
+
+impl Highlight {
+    fn name_style(self, palette: &Palette) -> Style {
+        if self.watched {
+            palette.watched.bold().underline()
+        } else {
+            palette.pkg.normal()
+        }
+    }
+}
+
+// The columns after the name and, in accessible mode, the markers that
+// styling alone would carry.
+fn write_tail(
+    out: &mut impl Write,
+    palette: &Palette,
+    extra: &[String],
+    highlight: Highlight,
+) -> io::Result<()> {
+    for column in extra {
+        write!(out, " :: {column}")?;
+    }
+    if palette.accessible {
+        if highlight.watched {
+            write!(out, " :: watched")?;
+        }
+        if highlight.fresh {
+            write!(out, " :: new")?;
+        }
+    }
+    writeln!(out)
+}
+
+pub fn write_record(
+    out: &mut impl Write,
+    palette: &Palette,
+    record: &PackageRecord<'_>,
+    extra: &[String],
+    highlight: Highlight,
+) -> io::Result<()> {
+    let (date_style, status_style) = if record.date.is_none() {
+        (
+            palette.date.dimmed(),
+            palette.status(record.status).normal(),
+        )
+    } else if highlight.fresh {
+        (
+            palette.date_ink(record.date).bold(),
+            palette.status(record.status).bold(),
+        )
+    } else {
+        (
+            palette.date_ink(record.date).normal(),
+            palette.status(record.status).normal(),
+        )
+    };
+    let date = palette.date_text(record);
+    write!(
+        out,
+        "{} :: {} :: {}",
+        date_style.paint(palette.fit("date", &date)),
+        status_style.paint(palette.fit("status", palette.label(record.status))),
+        palette.name(
+            record.name,
+            highlight
+                .name_style(palette)
+                .paint(palette.fit("name", record.name))
+        )
+    )?;
+    write_tail(out, palette, extra, highlight)
+}
+
+// A record under a status heading, which already says the date and status.
+pub fn write_name_row(
+    out: &mut impl Write,
+    palette: &Palette,
+    record: &PackageRecord<'_>,
+    extra: &[String],
+    highlight: Highlight,
+) -> io::Result<()> {
+    let mut style = highlight.name_style(palette);
+    if highlight.fresh {
+        style = style.bold();
+    }
+    write!(
+        out,
+        "    {}",
+        palette.name(record.name, style.paint(record.name))
+    )?;
+    write_tail(out, palette, extra, highlight)
+}
@@ -0,0 +1,104 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use pkglist_core::exec;
+
+use crate::cli::{Cli, Commands};
+
+const DEFAULT_PAGER: &str = "less";
+// Like git: quit when the output fits on one screen, pass colours and
+// hyperlinks through, and leave the output on the screen afterwards.
+const DEFAULT_LESS: &str = "FRX";
+
+static PAGING: AtomicBool = AtomicBool::new(false);
+
+// Whether stdout ends up on a terminal, directly or through the pager; the
+// terminal width and the last-viewed marker go by this.
+pub fn stdout_is_terminal() -> bool {
+    PAGING.load(Ordering::Relaxed) || io::stdout().is_terminal()
+}
+
+// Commands whose output is only read, and can grow long. Ones that prompt,
+// run pacman or keep running stay on the terminal.
+fn pages(cli: &Cli) -> bool {
+    match &cli.command {
+        None => !cli.watch,
+        Some(command) => matches!(
+            command,
+            Commands::Audit { .. }
+                | Commands::Backups { .. }
+                | Commands::CacheUsage { .. }
+                | Commands::Diff { .. }
+                | Commands::Doctor
+                | Commands::Fleet { .. }
+                | Commands::Groups { .. }
+                | Commands::History { .. }
+                | Commands::Kernels
+                | Commands::Log { .. }
+                | Commands::Report { .. }
+                | Commands::Show { .. }
+                | Commands::Snapshot { .. }
+                | Commands::Stats { .. }
+                | Commands::Transactions { .. }
+                | Commands::Tree { .. }
+                | Commands::Verify { .. }
+                | Commands::Why { .. }
+        ),
+    }
+}
+
+// The pager the output is piped into while it lives; dropping it hands the
+// pager the end of the output and waits until the user quits it.
+pub struct Pager {
+    child: Child,
+}
+
+impl Pager {
+    // Starts `$PKGLIST_PAGER`, `$PAGER` or less when stdout is a terminal and
+    // the command is one that pages. An empty pager or `cat` means none, as
+    // for git.
+    pub fn start(cli: &Cli) -> Option<Self> {
+        if cli.no_pager || exec::is_sandboxed() || !pages(cli) || !io::stdout().is_terminal() {
+            return None;
+        }
+        let pager = env::var("PKGLIST_PAGER")
+            .or_else(|_| env::var("PAGER"))
+            .unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+        if pager.trim().is_empty() || pager.trim() == "cat" {
+            return None;
+        }
+        let mut command = Command::new("sh");
+        command.args(["-c", &pager]).stdin(Stdio::piped());
+        if env::var_os("LESS").is_none() {
+            command.env("LESS", DEFAULT_LESS);
+        }
+        if env::var_os("LV").is_none() {
+            command.env("LV", "-c");
+        }
+        let mut child = command.spawn().ok()?;
+        let stdin = child.stdin.take()?;
+        if rustix::stdio::dup2_stdout(&stdin).is_err() {
+            drop(stdin);
+            let _ = child.wait();
+            return None;
+        }
+        PAGING.store(true, Ordering::Relaxed);
+        Some(Pager { child })
+    }
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        // Stdout is the last end of the pipe left open; pointing it elsewhere
+        // lets the pager see the end of the output.
+        if let Ok(null) = File::create("/dev/null") {
+            let _ = rustix::stdio::dup2_stdout(&null);
+        }
+        let _ = self.child.wait();
+        PAGING.store(false, Ordering::Relaxed);
+    }
+}
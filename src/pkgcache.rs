@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use pkglist_core::pacman::{self, LocalDb};
+use pkglist_core::size::format_size;
+
+use crate::output::Palette;
+
+struct CachedFile {
+    version: String,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+#[derive(Default)]
+struct PackageUsage {
+    versions: usize,
+    bytes: u64,
+    reclaimable: usize,
+    reclaimable_bytes: u64,
+}
+
+// Package files plus their detached signatures, which paccache removes
+// together.
+fn file_size(path: &PathBuf) -> u64 {
+    let sig = PathBuf::from(format!("{}.sig", path.display()));
+    [path, &sig]
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+pub fn run(palette: &Palette, keep: usize, top: usize) -> io::Result<()> {
+    let mut by_package: BTreeMap<String, Vec<CachedFile>> = BTreeMap::new();
+    for (name, version, path) in pacman::cached_packages() {
+        let modified = fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        by_package.entry(name).or_default().push(CachedFile {
+            version,
+            bytes: file_size(&path),
+            modified,
+        });
+    }
+    let local = LocalDb::load();
+
+    // Like `paccache -rk<keep>`: the newest `keep` builds of installed
+    // packages stay, everything else (and every build of packages no longer
+    // installed, like `paccache -ruk0`) can go.
+    let mut usage: Vec<(String, PackageUsage)> = Vec::new();
+    let mut uninstalled_bytes = 0;
+    for (name, mut files) in by_package {
+        files.sort_by_key(|file| std::cmp::Reverse(file.modified));
+        let installed = local.field(&name, "Version");
+        let mut entry = PackageUsage {
+            versions: files.len(),
+            ..PackageUsage::default()
+        };
+        for (index, file) in files.iter().enumerate() {
+            entry.bytes += file.bytes;
+            let kept = installed.is_some() && (index < keep || installed == Some(&file.version));
+            if !kept {
+                entry.reclaimable += 1;
+                entry.reclaimable_bytes += file.bytes;
+            }
+        }
+        if installed.is_none() {
+            uninstalled_bytes += entry.reclaimable_bytes;
+        }
+        usage.push((name, entry));
+    }
+
+    let total: u64 = usage.iter().map(|(_, entry)| entry.bytes).sum();
+    let files: usize = usage.iter().map(|(_, entry)| entry.versions).sum();
+    let reclaimable: u64 = usage.iter().map(|(_, entry)| entry.reclaimable_bytes).sum();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(
+        out,
+        "{} :: {} in {files} package file(s) for {} package(s)",
+        palette.date.paint(pacman::PKG_CACHE_DIR),
+        palette.upg.paint(format_size(total)),
+        usage.len()
+    )?;
+    writeln!(
+        out,
+        "reclaimable :: {} ({} from old versions beyond {keep} per package, {} from packages no longer installed)",
+        palette.ins.paint(format_size(reclaimable)),
+        format_size(reclaimable - uninstalled_bytes),
+        format_size(uninstalled_bytes)
+    )?;
+
+    usage.sort_by_key(|(_, entry)| std::cmp::Reverse((entry.versions, entry.bytes)));
+    if top > 0 && !usage.is_empty() {
+        writeln!(out, "most versions kept:")?;
+    }
+    for (name, entry) in usage.iter().take(top) {
+        writeln!(
+            out,
+            "  {} :: {} version(s) :: {} :: {} reclaimable ({})",
+            palette.pkg.paint(name),
+            entry.versions,
+            format_size(entry.bytes),
+            entry.reclaimable,
+            format_size(entry.reclaimable_bytes)
+        )?;
+    }
+    out.flush()
+}
@@ -0,0 +1,39 @@
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::history;
+use pkglist_core::packages::PackageRecord;
+
+use crate::i18n::tr;
+use crate::matching;
+use crate::output::{self, Highlight, Palette};
+
+// Every install, upgrade and removal of one package with its versions,
+// oldest first. Works for removed packages too, since it only reads the log.
+pub fn run(palette: &Palette, package: &str) -> io::Result<()> {
+    let log = history::read_log_file()?;
+    let events = history::parse_events(&log);
+    let Some((name, events)) = matching::find(events.keys().map(String::as_str), package)
+        .and_then(|name| events.get_key_value(name))
+    else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            tr!("error-no-history", package = package),
+        ));
+    };
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    for event in events {
+        let record = PackageRecord::new(name, history::parse_log_date(&event.date), event.status);
+        let versions = event
+            .versions
+            .replace(" -> ", &format!(" {} ", palette.glyph("→", "to")));
+        output::write_record(
+            &mut out,
+            palette,
+            &record,
+            &[versions],
+            Highlight::default(),
+        )?;
+    }
+    out.flush()
+}
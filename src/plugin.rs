@@ -0,0 +1,100 @@
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::process::{Command, Stdio};
+
+use clap::CommandFactory;
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::LocalDb;
+
+use crate::cli::Cli;
+use crate::schema::PackageList;
+
+pub const PLUGIN_PREFIX: &str = "pkglist-";
+
+fn program(name: &OsString) -> OsString {
+    let mut program = OsString::from(PLUGIN_PREFIX);
+    program.push(name);
+    program
+}
+
+fn installed(name: &OsString) -> bool {
+    let program = program(name);
+    env::var_os("PATH").is_some_and(|path| {
+        env::split_paths(&path).any(|dir| {
+            dir.join(&program)
+                .metadata()
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        })
+    })
+}
+
+// Where the plugin name is in the command line, when its first word past the
+// global options names an installed `pkglist-<cmd>` and no subcommand of our
+// own. The options before it configure the data the plugin gets, like for
+// the listing; anything else is left to the parser, so an unknown word
+// filters the listing by name.
+pub fn requested(args: &[OsString]) -> Option<usize> {
+    for (at, arg) in args.iter().enumerate().skip(1) {
+        let word = arg.to_string_lossy();
+        if word == "--" {
+            return None;
+        }
+        // Option values look like words too, but leave the options before
+        // them incomplete.
+        if word.starts_with('-') || Cli::command().try_get_matches_from(&args[..at]).is_err() {
+            continue;
+        }
+        let known = Cli::command().find_subcommand(word.as_ref()).is_some();
+        return (!known && installed(arg)).then_some(at);
+    }
+    None
+}
+
+// Plugins are dispatched git-style to `pkglist-<cmd>` on PATH,
+// which receives the date-ordered package records as a `PackageList` on stdin.
+pub fn run(args: &[OsString], data: &PackageData) -> io::Result<i32> {
+    let (name, rest) = args
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "missing subcommand"))?;
+
+    let program = program(name);
+
+    let mut child = match Command::new(&program)
+        .args(rest)
+        .env("PKGLIST_VERSION", env!("CARGO_PKG_VERSION"))
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "unknown subcommand '{}' (no {} found in PATH)",
+                    name.to_string_lossy(),
+                    program.to_string_lossy()
+                ),
+            ));
+        }
+        Err(e) => return Err(e),
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let local = LocalDb::load();
+        let records = data
+            .records_by_date()
+            .into_iter()
+            .map(|record| record.with_local(&local).into())
+            .collect();
+        let payload = serde_json::to_vec(&PackageList::new(records))?;
+        // Plugins that ignore their input may close stdin early.
+        match stdin.write_all(&payload) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+
+    Ok(child.wait()?.code().unwrap_or(1))
+}
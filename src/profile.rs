@@ -0,0 +1,187 @@
+use std::io;
+
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use pkglist_core::pacman::InstallScope;
+
+use crate::cli::{self, Cli};
+use crate::config::{Config, Profile};
+
+// Whether the user set the option `id` themselves, on the command line or
+// through its environment variable, rather than leaving it at its default.
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+fn fill<T>(matches: &ArgMatches, id: &str, field: &mut T, value: Option<T>) {
+    if let Some(value) = value.filter(|_| !given(matches, id)) {
+        *field = value;
+    }
+}
+
+fn date_bound(name: &str, key: &str, value: &Option<String>) -> io::Result<Option<String>> {
+    value
+        .as_deref()
+        .map(cli::parse_date_bound)
+        .transpose()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{name}: {key}: {e}")))
+}
+
+fn find<'a>(config: &'a Config, name: &str) -> io::Result<&'a Profile> {
+    config.profiles.get(name).ok_or_else(|| {
+        let mut known: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+        known.sort_unstable();
+        let known = if known.is_empty() {
+            "none configured".to_string()
+        } else {
+            format!("known: {}", known.join(", "))
+        };
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no profile `{name}` in the config ({known})"),
+        )
+    })
+}
+
+// Fills the options the command line left unset from one layer of the
+// config; `name` says which in errors.
+fn fill_options(
+    cli: &mut Cli,
+    matches: &ArgMatches,
+    name: &str,
+    profile: &Profile,
+) -> io::Result<()> {
+    let profile = profile.clone();
+    fill(
+        matches,
+        "pattern",
+        &mut cli.pattern,
+        profile.pattern.map(Some),
+    );
+    fill(
+        matches,
+        "since",
+        &mut cli.since,
+        date_bound(name, "since", &profile.since)?.map(Some),
+    );
+    fill(
+        matches,
+        "until",
+        &mut cli.until,
+        date_bound(name, "until", &profile.until)?.map(Some),
+    );
+    fill(matches, "sort", &mut cli.sort, profile.sort);
+    fill(matches, "reverse", &mut cli.reverse, profile.reverse);
+    fill(matches, "foreign", &mut cli.foreign, profile.foreign);
+    fill(
+        matches,
+        "group_by",
+        &mut cli.group_by,
+        profile.group_by.map(Some),
+    );
+    let scope_given = ["all", "deps_only", "explicit"]
+        .iter()
+        .any(|id| given(matches, id));
+    if let Some(scope) = profile.packages.filter(|_| !scope_given) {
+        cli.all = scope == InstallScope::All;
+        cli.deps_only = scope == InstallScope::Dependencies;
+        cli.explicit = scope == InstallScope::Explicit;
+    }
+    fill(matches, "by_day", &mut cli.by_day, profile.by_day);
+    fill(matches, "summary", &mut cli.summary, profile.summary);
+    fill(matches, "reboots", &mut cli.reboots, profile.reboots);
+    fill(
+        matches,
+        "accessible",
+        &mut cli.accessible,
+        profile.accessible,
+    );
+    fill(
+        matches,
+        "ignore_case",
+        &mut cli.ignore_case,
+        profile.ignore_case,
+    );
+    fill(
+        matches,
+        "loose_match",
+        &mut cli.loose_match,
+        profile.loose_match,
+    );
+    fill(
+        matches,
+        "hyperlinks",
+        &mut cli.hyperlinks,
+        profile.hyperlinks,
+    );
+    fill(matches, "theme", &mut cli.theme, profile.theme);
+    fill(matches, "color", &mut cli.color, profile.color);
+    fill(matches, "output", &mut cli.output, profile.output.map(Some));
+    fill(matches, "format", &mut cli.format, profile.format.map(Some));
+    fill(matches, "backend", &mut cli.backend, profile.backend);
+    fill(matches, "log", &mut cli.log, profile.log.map(Some));
+    if !cli.no_cache {
+        fill(
+            matches,
+            "cache_path",
+            &mut cli.cache_path,
+            profile.cache_path.map(Some),
+        );
+    }
+    if !profile.status.is_empty() {
+        fill(matches, "status", &mut cli.status, Some(profile.status));
+    }
+    if !profile.jump.is_empty() {
+        fill(matches, "jump", &mut cli.jump, Some(profile.jump));
+    }
+    if !profile.include.is_empty() {
+        fill(matches, "include", &mut cli.include, Some(profile.include));
+    }
+    if !profile.repo.is_empty() {
+        fill(matches, "repo", &mut cli.repo, Some(profile.repo));
+    }
+    if !profile.columns.is_empty() {
+        fill(matches, "columns", &mut cli.columns, Some(profile.columns));
+    }
+    Ok(())
+}
+
+// Fills the options the command line left unset from `[defaults]`, then
+// from the selected profile.
+pub fn apply_options(cli: &mut Cli, matches: &ArgMatches, config: &Config) -> io::Result<()> {
+    fill_options(cli, matches, "defaults", &config.defaults)?;
+    let Some(name) = cli.profile.clone() else {
+        return Ok(());
+    };
+    fill_options(
+        cli,
+        matches,
+        &format!("profile {name}"),
+        find(config, &name)?,
+    )
+}
+
+// `apply_options`, plus the labels and age bands of `[defaults]` and the
+// profile replacing the config's.
+pub fn apply(cli: &mut Cli, matches: &ArgMatches, config: &mut Config) -> io::Result<()> {
+    apply_options(cli, matches, config)?;
+    let profile = cli
+        .profile
+        .as_deref()
+        .and_then(|name| config.profiles.get(name));
+    let (mut labels, mut age_bands) = (None, None);
+    for layer in [Some(&config.defaults), profile].into_iter().flatten() {
+        labels = layer.labels.clone().or(labels);
+        age_bands = layer.age_bands.or(age_bands);
+    }
+    if let Some(labels) = labels {
+        config.labels = labels;
+    }
+    if age_bands.is_some() {
+        config.age_bands = age_bands;
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::history;
+use pkglist_core::packages::PackageData;
+use rusqlite::Connection;
+use rusqlite::types::ValueRef;
+
+use crate::output::Palette;
+
+const SCHEMA: &str = "
+CREATE TABLE events (
+    name TEXT NOT NULL,
+    date TEXT NOT NULL,
+    status TEXT NOT NULL,
+    versions TEXT NOT NULL
+);
+CREATE TABLE packages (
+    name TEXT PRIMARY KEY,
+    date TEXT,
+    status TEXT NOT NULL,
+    explicit INTEGER NOT NULL
+);
+";
+
+fn sql_error(err: rusqlite::Error) -> io::Error {
+    io::Error::other(format!("query: {err}"))
+}
+
+// Loads the log into an in-memory database: `events` holds every logged
+// install, upgrade and removal, `packages` the latest state of each name and
+// whether it is explicitly installed now.
+fn open(data: &PackageData) -> rusqlite::Result<Connection> {
+    let mut db = Connection::open_in_memory()?;
+    db.execute_batch(SCHEMA)?;
+    let log = history::read_log_file().unwrap_or_default();
+
+    let tx = db.transaction()?;
+    {
+        let mut insert = tx.prepare("INSERT INTO events VALUES (?1, ?2, ?3, ?4)")?;
+        for (name, events) in history::parse_events(&log) {
+            for event in events {
+                insert.execute((&name, &event.date, event.status.as_str(), &event.versions))?;
+            }
+        }
+        let mut insert = tx.prepare("INSERT INTO packages VALUES (?1, ?2, ?3, ?4)")?;
+        data.for_each_by_name(|record| {
+            let explicit = data
+                .installed
+                .binary_search_by(|name| name.as_str().cmp(record.name))
+                .is_ok();
+            insert
+                .execute((
+                    record.name,
+                    record.date.map(|date| history::format_log_date(&date)),
+                    record.status.as_str(),
+                    explicit,
+                ))
+                .map(drop)
+        })?;
+    }
+    tx.commit()?;
+    Ok(db)
+}
+
+fn cell(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(number) => number.to_string(),
+        ValueRef::Real(number) => number.to_string(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+        ValueRef::Blob(blob) => format!("<{} bytes>", blob.len()),
+    }
+}
+
+// Runs one SQL statement against the log and prints the column names and
+// then every row, `::`-separated like the listing.
+pub fn run(data: &PackageData, palette: &Palette, sql: &str) -> io::Result<()> {
+    let db = open(data).map_err(sql_error)?;
+    let mut statement = db.prepare(sql).map_err(sql_error)?;
+    let columns = statement.column_count();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if columns > 0 {
+        writeln!(
+            out,
+            "{}",
+            palette
+                .date
+                .bold()
+                .paint(statement.column_names().join(" :: "))
+        )?;
+    }
+    let mut rows = statement.query([]).map_err(sql_error)?;
+    while let Some(row) = rows.next().map_err(sql_error)? {
+        let cells = (0..columns)
+            .map(|i| row.get_ref(i).map(cell))
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(sql_error)?;
+        writeln!(out, "{}", cells.join(" :: "))?;
+    }
+    out.flush()
+}
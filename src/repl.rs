@@ -0,0 +1,127 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use clap::{CommandFactory, FromArgMatches};
+use pkglist_core::packages::PackageData;
+
+use crate::cli::Cli;
+use crate::config::Config;
+use crate::listing::Listing;
+use crate::matching;
+use crate::output::Palette;
+use crate::profile;
+
+const PROMPT: &str = "pkglist> ";
+const HELP: &str =
+    "Type listing options as on the command line, e.g. `--status upg --since 2024-07 python`.
+An empty line shows the previous listing again; `quit` or Ctrl-D leaves.";
+
+// Splits a line into words like a shell would, minus expansion: quotes
+// group words and a backslash escapes the next character.
+fn words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (Some('\''), c) => word.get_or_insert_default().push(c),
+            (_, '\\') => {
+                let escaped = chars.next().ok_or("trailing backslash")?;
+                word.get_or_insert_default().push(escaped);
+            }
+            (_, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quote".to_string());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+struct Session<'a> {
+    config: &'a Config,
+    palette: &'a Palette,
+    data: &'a PackageData,
+    listing: Listing<'a>,
+    // The profile pkglist was started with, for lines that name none.
+    profile: Option<&'a str>,
+}
+
+fn render(session: &Session, line: &str) -> io::Result<()> {
+    let words = words(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let parsed = Cli::command()
+        .try_get_matches_from(std::iter::once("pkglist".to_string()).chain(words))
+        .and_then(|matches| Ok((Cli::from_arg_matches(&matches)?, matches)));
+    let (mut cli, matches) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => return e.print(),
+    };
+    if cli.command.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only listing options work here; subcommands run outside the repl",
+        ));
+    }
+    if cli.profile.is_none() {
+        cli.profile = session.profile.map(str::to_string);
+    }
+    profile::apply_options(&mut cli, &matches, session.config)?;
+    matching::configure(cli.ignore_case, cli.loose_match);
+    session.listing.print(&cli, session.palette, session.data)
+}
+
+// Reads listing options line by line and renders each against the data
+// parsed once at startup, so trying filters stays quick even over a slow
+// connection. The last-viewed marker is left alone.
+pub fn run(
+    config: &Config,
+    palette: &Palette,
+    data: &PackageData,
+    profile: Option<&str>,
+) -> io::Result<()> {
+    let session = Session {
+        config,
+        palette,
+        data,
+        listing: Listing::new(config, data, false)?,
+        profile,
+    };
+    let prompt = io::stdin().is_terminal();
+    let mut previous = String::new();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if prompt {
+            print!("{PROMPT}");
+            io::stdout().flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            if prompt {
+                println!();
+            }
+            break;
+        };
+        let line = match line.trim() {
+            "quit" | "exit" => break,
+            "help" => {
+                println!("{HELP}");
+                continue;
+            }
+            "" => previous.clone(),
+            line => line.to_string(),
+        };
+        previous.clone_from(&line);
+        match render(&session, &line) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Err(e),
+            Err(e) => eprintln!("pkglist: {e}"),
+            Ok(()) => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, FixedOffset};
+use pkglist_core::history::Status;
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::{self, InfoBlock, LocalDb, strip_constraint};
+
+use crate::transactions::{Action, Transaction};
+
+// A name a package went by before a rename or a replacement by another
+// package, as far as this system's log saw it.
+pub struct Identity {
+    pub name: String,
+    // The last logged event of the old name, normally its removal.
+    pub until: Option<DateTime<FixedOffset>>,
+    // The transaction that swapped the old name for the newer one, when both
+    // happened together as in pacman's "Replace foo with extra/foo-ng?".
+    pub transaction: Option<usize>,
+}
+
+// Names a package claims to stand in for: `Replaces` first, then
+// `Provides` and `Conflicts With`, which renamed packages keep for a while.
+fn claimed(block: &InfoBlock) -> Vec<String> {
+    ["Replaces", "Provides", "Conflicts With"]
+        .iter()
+        .flat_map(|key| pacman::field_list(block, key))
+        .map(|name| strip_constraint(&name).to_string())
+        .collect()
+}
+
+pub struct Replacements {
+    // newer name → names it claims, from the local and sync databases
+    claims: HashMap<String, Vec<String>>,
+}
+
+impl Replacements {
+    pub fn load(local: &LocalDb) -> Self {
+        let mut claims: HashMap<String, Vec<String>> = HashMap::new();
+        let sync = pacman::sync_info(&[]);
+        let blocks = local
+            .names()
+            .filter_map(|name| local.block(name))
+            .chain(&sync);
+        for block in blocks {
+            let Some(name) = block.get("Name") else {
+                continue;
+            };
+            let entry = claims.entry(name.clone()).or_default();
+            for old in claimed(block) {
+                if old != *name && !entry.contains(&old) {
+                    entry.push(old);
+                }
+            }
+        }
+        Replacements { claims }
+    }
+
+    // Follows the chain backwards from `name`: every claimed name that the
+    // log shows on this system and that is gone now, then what that one
+    // claimed, newest identity first.
+    pub fn previous_identities(
+        &self,
+        data: &PackageData,
+        transactions: &[Transaction],
+        name: &str,
+    ) -> Vec<Identity> {
+        let mut identities = Vec::new();
+        let mut seen = HashSet::from([name.to_string()]);
+        let mut current = name.to_string();
+
+        while let Some(old) = self.claims.get(&current).and_then(|olds| {
+            olds.iter().find(|old| {
+                !seen.contains(*old)
+                    && data
+                        .history
+                        .get(*old)
+                        .is_some_and(|info| info.status == Status::Removed)
+            })
+        }) {
+            let swapped = transactions.iter().rev().find(|tx| {
+                let touched = |name: &str, action: Action| {
+                    tx.changes
+                        .iter()
+                        .any(|change| change.name == name && change.action == action)
+                };
+                touched(old, Action::Removed) && touched(&current, Action::Installed)
+            });
+            identities.push(Identity {
+                name: old.clone(),
+                until: data.history[old].date,
+                transaction: swapped.map(|tx| tx.id),
+            });
+            seen.insert(old.clone());
+            current = old.clone();
+        }
+        identities
+    }
+}
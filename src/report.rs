@@ -0,0 +1,496 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::{Datelike, Local, NaiveDate};
+use pkglist_core::exec;
+use pkglist_core::pacman::LocalDb;
+use pkglist_core::size::format_size;
+
+use crate::boots::format_duration;
+use crate::export::html_escape;
+use crate::i18n::tr;
+use crate::output::Palette;
+use crate::transactions::{self, Action, Change, Outcome, Transaction};
+
+const BIG_UPGRADES: usize = 5;
+const LARGEST_NEW: usize = 10;
+const BAR_WIDTH: usize = 40;
+const CHART_HEIGHT: usize = 120;
+
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; color: #222; }
+h1 { margin-bottom: 0.2rem; }
+p.generated { color: #777; margin-top: 0; }
+ul.summary { display: flex; flex-wrap: wrap; gap: 0.8rem; list-style: none; padding: 0; }
+ul.summary li { border: 1px solid #ddd; border-radius: 6px; padding: 0.5rem 0.9rem; }
+ul.summary b { display: block; font-size: 1.4rem; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1.5rem; }
+th, td { text-align: left; padding: 0.25rem 0.6rem; border-bottom: 1px solid #eee; }
+td.num { text-align: right; }
+.failed { color: #b42318; }
+svg rect { fill: #4a7bd0; }
+svg text { font-size: 9px; fill: #555; }";
+
+// Everything the log says about one month.
+struct Report<'a> {
+    month: &'a str,
+    days: Vec<NaiveDate>,
+    transactions: Vec<&'a Transaction>,
+    changes: Vec<&'a Change>,
+    // New packages still installed at the version they came in with, and
+    // their installed size now; the log itself doesn't record sizes.
+    sizes: Vec<(&'a Change, u64)>,
+}
+
+impl<'a> Report<'a> {
+    fn new(month: &'a str, all: &'a [Transaction], local: &LocalDb) -> Self {
+        let first = NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d")
+            .expect("the month is validated by the command line");
+        let days = first
+            .iter_days()
+            .take_while(|day| day.month() == first.month())
+            .collect();
+        let transactions: Vec<&Transaction> = all
+            .iter()
+            .filter(|tx| tx.started.starts_with(month))
+            .collect();
+        let changes: Vec<&Change> = transactions
+            .iter()
+            .flat_map(|tx| &tx.changes)
+            .filter(|change| change.date.starts_with(month))
+            .collect();
+        let mut sizes: Vec<(&Change, u64)> = changes
+            .iter()
+            .filter(|change| change.action == Action::Installed)
+            .filter(|change| local.field(&change.name, "Version") == change.new_version.as_deref())
+            .filter_map(|change| Some((*change, local.installed_size(&change.name)?)))
+            .collect();
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name)));
+        Report {
+            month,
+            days,
+            transactions,
+            changes,
+            sizes,
+        }
+    }
+
+    fn of(&self, action: Action) -> Vec<&'a Change> {
+        self.changes
+            .iter()
+            .copied()
+            .filter(|change| change.action == action)
+            .collect()
+    }
+
+    fn failed(&self) -> usize {
+        self.transactions
+            .iter()
+            .filter(|tx| tx.outcome != Outcome::Completed)
+            .count()
+    }
+
+    // The upgrade transactions touching the most packages, largest first.
+    fn big_upgrades(&self) -> Vec<&'a Transaction> {
+        let mut upgrades: Vec<&Transaction> = self
+            .transactions
+            .iter()
+            .copied()
+            .filter(|tx| tx.is_upgrade())
+            .collect();
+        upgrades.sort_by_key(|tx| std::cmp::Reverse(tx.changes.len()));
+        upgrades.truncate(BIG_UPGRADES);
+        upgrades
+    }
+
+    // Package changes on each day of the month, empty days included.
+    fn per_day(&self) -> Vec<(NaiveDate, usize)> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for change in &self.changes {
+            *counts
+                .entry(change.date.get(..10).unwrap_or(""))
+                .or_default() += 1;
+        }
+        self.days
+            .iter()
+            .map(|day| {
+                let key = day.format("%Y-%m-%d").to_string();
+                (*day, counts.get(key.as_str()).copied().unwrap_or(0))
+            })
+            .collect()
+    }
+
+    fn added_size(&self) -> u64 {
+        self.sizes.iter().map(|(_, size)| size).sum()
+    }
+
+    fn summary(&self) -> Vec<(String, String)> {
+        vec![
+            (
+                tr!("report-transactions"),
+                self.transactions.len().to_string(),
+            ),
+            (tr!("report-failed"), self.failed().to_string()),
+            (
+                tr!("report-installed"),
+                self.of(Action::Installed).len().to_string(),
+            ),
+            (
+                tr!("report-upgraded"),
+                self.of(Action::Upgraded).len().to_string(),
+            ),
+            (
+                tr!("report-downgraded"),
+                self.of(Action::Downgraded).len().to_string(),
+            ),
+            (
+                tr!("report-removed"),
+                self.of(Action::Removed).len().to_string(),
+            ),
+            (tr!("report-size-added"), format_size(self.added_size())),
+        ]
+    }
+
+    fn size_summary(&self) -> String {
+        tr!(
+            "report-size-summary",
+            size = format_size(self.added_size()),
+            count = self.sizes.len()
+        )
+    }
+}
+
+fn duration(tx: &Transaction) -> String {
+    tx.duration().map(format_duration).unwrap_or_default()
+}
+
+// The page's headings are the terminal's, capitalised.
+fn html_heading(title: &str) -> String {
+    let mut chars = title.chars();
+    let title: String = chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default();
+    format!("<h2>{}</h2>", html_escape(&title))
+}
+
+fn heading(out: &mut impl Write, palette: &Palette, title: &str) -> io::Result<()> {
+    let edge = palette.glyph("──", "");
+    let text = format!("{edge} {title} {edge}");
+    writeln!(out, "\n{}", palette.date.bold().paint(text.trim()))
+}
+
+fn write_text(out: &mut impl Write, palette: &Palette, report: &Report) -> io::Result<()> {
+    writeln!(
+        out,
+        "{}",
+        palette
+            .date
+            .bold()
+            .paint(tr!("report-title", month = report.month))
+    )?;
+    let summary: Vec<String> = report
+        .summary()
+        .iter()
+        .map(|(label, value)| format!("{value} {label}"))
+        .collect();
+    writeln!(out, "{}", summary.join(" :: "))?;
+    if report.transactions.is_empty() {
+        return Ok(());
+    }
+
+    heading(out, palette, &tr!("report-changes-per-day"))?;
+    let per_day = report.per_day();
+    let busiest = per_day.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    let bar = palette.glyph("█", "#");
+    // Quiet days only matter to the HTML chart's axis.
+    for (day, count) in per_day.iter().filter(|(_, count)| *count > 0) {
+        let width = (count * BAR_WIDTH).div_ceil(busiest.max(1));
+        writeln!(
+            out,
+            "{} {:>4} {}",
+            palette.date.paint(day.format("%m-%d").to_string()),
+            count,
+            palette.upg.paint(bar.repeat(width))
+        )?;
+    }
+
+    heading(out, palette, &tr!("report-transactions"))?;
+    for tx in &report.transactions {
+        let outcome = match tx.outcome {
+            Outcome::Completed => palette.ins.paint(tx.outcome.label()),
+            _ => palette.rem.paint(tx.outcome.label()),
+        };
+        writeln!(
+            out,
+            "#{} :: {} :: {} :: {} :: {} :: {}",
+            tx.id,
+            palette.date.paint(&tx.started),
+            outcome,
+            tr!("report-packages", count = tx.changes.len()),
+            duration(tx),
+            tx.command.as_deref().unwrap_or("-")
+        )?;
+    }
+
+    let big = report.big_upgrades();
+    if !big.is_empty() {
+        heading(out, palette, &tr!("report-big-upgrades"))?;
+        for tx in big {
+            writeln!(
+                out,
+                "#{} :: {} :: {} :: {}",
+                tx.id,
+                palette.date.paint(&tx.started),
+                tr!("report-packages", count = tx.changes.len()),
+                duration(tx)
+            )?;
+        }
+    }
+
+    for (title, action) in [
+        (tr!("report-new-packages"), Action::Installed),
+        (tr!("report-removals"), Action::Removed),
+    ] {
+        let changes = report.of(action);
+        if changes.is_empty() {
+            continue;
+        }
+        heading(out, palette, &title)?;
+        for change in changes {
+            writeln!(
+                out,
+                "{} :: {} :: {}",
+                palette.date.paint(&change.date),
+                action.colour(palette).paint(&change.name),
+                change.versions()
+            )?;
+        }
+    }
+
+    if !report.sizes.is_empty() {
+        heading(out, palette, &tr!("report-size"))?;
+        writeln!(out, "{}", report.size_summary())?;
+        for (change, size) in report.sizes.iter().take(LARGEST_NEW) {
+            writeln!(
+                out,
+                "{:>10} :: {}",
+                format_size(*size),
+                palette.pkg.paint(&change.name)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Bars for the changes per day, labelled every few days so the axis stays
+// readable in a 31-day month.
+fn write_chart(out: &mut impl Write, per_day: &[(NaiveDate, usize)]) -> io::Result<()> {
+    let busiest = per_day.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    let (bar, gap, top) = (16, 4, 12);
+    let width = per_day.len() * (bar + gap);
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{}\" \
+         role=\"img\" aria-label=\"{}\">",
+        CHART_HEIGHT + top + 14,
+        html_escape(&tr!("report-changes-per-day"))
+    )?;
+    for (index, (day, count)) in per_day.iter().enumerate() {
+        let x = index * (bar + gap);
+        let height = (count * CHART_HEIGHT).div_ceil(busiest.max(1));
+        let y = top + CHART_HEIGHT - height;
+        writeln!(
+            out,
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{bar}\" height=\"{height}\">\
+             <title>{}: {count}</title></rect>",
+            day.format("%Y-%m-%d")
+        )?;
+        if *count > 0 {
+            writeln!(out, "<text x=\"{x}\" y=\"{}\">{count}</text>", y - 2)?;
+        }
+        if day.day() == 1 || day.day() % 5 == 0 {
+            writeln!(
+                out,
+                "<text x=\"{x}\" y=\"{}\">{}</text>",
+                top + CHART_HEIGHT + 12,
+                day.day()
+            )?;
+        }
+    }
+    writeln!(out, "</svg>")
+}
+
+fn write_table(out: &mut impl Write, header: &[&str], rows: &[Vec<String>]) -> io::Result<()> {
+    writeln!(out, "<table>")?;
+    let header: String = header
+        .iter()
+        .map(|cell| format!("<th>{}</th>", html_escape(cell)))
+        .collect();
+    writeln!(out, "<thead><tr>{header}</tr></thead>\n<tbody>")?;
+    for row in rows {
+        let cells: String = row
+            .iter()
+            .map(|cell| format!("<td>{}</td>", html_escape(cell)))
+            .collect();
+        writeln!(out, "<tr>{cells}</tr>")?;
+    }
+    writeln!(out, "</tbody>\n</table>")
+}
+
+fn change_rows(changes: &[&Change]) -> Vec<Vec<String>> {
+    changes
+        .iter()
+        .map(|change| vec![change.date.clone(), change.name.clone(), change.versions()])
+        .collect()
+}
+
+fn write_html(out: &mut impl Write, report: &Report) -> io::Result<()> {
+    let title = tr!("report-title", month = report.month);
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(
+        out,
+        "<html><head><meta charset=\"utf-8\"><title>{}</title>",
+        html_escape(&title)
+    )?;
+    writeln!(out, "<style>\n{STYLE}\n</style>\n</head><body>")?;
+    writeln!(out, "<h1>{}</h1>", html_escape(&title))?;
+    writeln!(
+        out,
+        "<p class=\"generated\">{}</p>",
+        html_escape(&tr!(
+            "report-generated",
+            date = Local::now().format("%Y-%m-%d %H:%M")
+        ))
+    )?;
+    writeln!(out, "<ul class=\"summary\">")?;
+    for (label, value) in report.summary() {
+        writeln!(
+            out,
+            "<li><b>{}</b>{}</li>",
+            html_escape(&value),
+            html_escape(&label)
+        )?;
+    }
+    writeln!(out, "</ul>")?;
+
+    if !report.transactions.is_empty() {
+        writeln!(out, "{}", html_heading(&tr!("report-changes-per-day")))?;
+        write_chart(out, &report.per_day())?;
+
+        writeln!(out, "{}", html_heading(&tr!("report-transactions")))?;
+        writeln!(out, "<table>")?;
+        let header: String = [
+            "#".to_string(),
+            tr!("report-started"),
+            tr!("report-outcome"),
+            tr!("report-package-count"),
+            tr!("report-duration"),
+            tr!("report-command"),
+        ]
+        .iter()
+        .map(|cell| format!("<th>{}</th>", html_escape(cell)))
+        .collect();
+        writeln!(out, "<thead><tr>{header}</tr></thead>\n<tbody>")?;
+        for tx in &report.transactions {
+            let class = if tx.outcome == Outcome::Completed {
+                ""
+            } else {
+                " class=\"failed\""
+            };
+            writeln!(
+                out,
+                "<tr><td class=\"num\">{}</td><td>{}</td><td{class}>{}</td>\
+                 <td class=\"num\">{}</td><td>{}</td><td><code>{}</code></td></tr>",
+                tx.id,
+                html_escape(&tx.started),
+                html_escape(&tx.outcome.label()),
+                tx.changes.len(),
+                html_escape(&duration(tx)),
+                html_escape(tx.command.as_deref().unwrap_or(""))
+            )?;
+        }
+        writeln!(out, "</tbody>\n</table>")?;
+    }
+
+    let big = report.big_upgrades();
+    if !big.is_empty() {
+        writeln!(out, "{}", html_heading(&tr!("report-big-upgrades")))?;
+        let rows: Vec<Vec<String>> = big
+            .iter()
+            .map(|tx| {
+                vec![
+                    format!("#{}", tx.id),
+                    tx.started.clone(),
+                    tx.changes.len().to_string(),
+                    duration(tx),
+                ]
+            })
+            .collect();
+        write_table(
+            out,
+            &[
+                "#",
+                &tr!("report-started"),
+                &tr!("report-package-count"),
+                &tr!("report-duration"),
+            ],
+            &rows,
+        )?;
+    }
+
+    for (title, action) in [
+        (tr!("report-new-packages"), Action::Installed),
+        (tr!("report-removals"), Action::Removed),
+    ] {
+        let changes = report.of(action);
+        if !changes.is_empty() {
+            writeln!(out, "{}", html_heading(&title))?;
+            write_table(
+                out,
+                &[
+                    &tr!("report-date"),
+                    &tr!("report-package"),
+                    &tr!("report-version"),
+                ],
+                &change_rows(&changes),
+            )?;
+        }
+    }
+
+    if !report.sizes.is_empty() {
+        writeln!(out, "{}", html_heading(&tr!("report-size")))?;
+        writeln!(out, "<p>{}.</p>", html_escape(&report.size_summary()))?;
+        let rows: Vec<Vec<String>> = report
+            .sizes
+            .iter()
+            .take(LARGEST_NEW)
+            .map(|(change, size)| vec![change.name.clone(), format_size(*size)])
+            .collect();
+        write_table(
+            out,
+            &[&tr!("report-package"), &tr!("report-installed-size")],
+            &rows,
+        )?;
+    }
+    writeln!(out, "</body></html>")
+}
+
+// The report for `month` (YYYY-MM), as text or a standalone HTML page, on
+// stdout or written to `out`.
+pub fn run(palette: &Palette, month: &str, html: bool, out: Option<&Path>) -> io::Result<()> {
+    let transactions = transactions::load()?;
+    let local = LocalDb::load();
+    let report = Report::new(month, &transactions, &local);
+    let mut bytes = Vec::new();
+    match (html, out) {
+        (true, _) => write_html(&mut bytes, &report)?,
+        (false, Some(_)) => write_text(&mut bytes, &palette.uncoloured(), &report)?,
+        (false, None) => write_text(&mut bytes, palette, &report)?,
+    }
+    match out {
+        Some(path) => exec::write_file(path, &bytes, None),
+        None => io::stdout().lock().write_all(&bytes),
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use pkglist_core::packages::PackageRecord;
+use pkglist_core::pacman;
+
+use crate::output::{self, Highlight, Palette};
+
+// The repository of packages no sync repository has, such as AUR packages
+// or ones built by hand.
+pub const FOREIGN: &str = "foreign";
+
+// The repository each package comes from, as the sync databases say.
+pub struct Repos {
+    by_package: HashMap<String, String>,
+    // Repositories in pacman.conf order, for the groups.
+    order: Vec<String>,
+}
+
+impl Repos {
+    pub fn load() -> Self {
+        let mut by_package = HashMap::new();
+        let mut order: Vec<String> = Vec::new();
+        for (repo, name) in pacman::sync_packages() {
+            if order.last() != Some(&repo) {
+                order.push(repo.clone());
+            }
+            // The first repository wins, as it does for pacman.
+            by_package.entry(name).or_insert(repo);
+        }
+        Repos { by_package, order }
+    }
+
+    pub fn repo_of(&self, name: &str) -> &str {
+        self.by_package.get(name).map_or(FOREIGN, String::as_str)
+    }
+
+    fn rank(&self, repo: &str) -> usize {
+        self.order
+            .iter()
+            .position(|known| known == repo)
+            .unwrap_or(self.order.len())
+    }
+}
+
+// The listing under a heading per repository with its count, repositories
+// in pacman.conf order and foreign packages last. Rows are collected until
+// the end since a repository's packages are spread over the whole listing.
+pub struct RepoGroups<'a> {
+    repos: Option<&'a Repos>,
+    groups: BTreeMap<(usize, String), (usize, Vec<u8>)>,
+}
+
+impl<'a> RepoGroups<'a> {
+    pub fn new(repos: Option<&'a Repos>) -> Self {
+        RepoGroups {
+            repos,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.repos.is_some()
+    }
+
+    pub fn write(
+        &mut self,
+        palette: &Palette,
+        record: &PackageRecord<'_>,
+        extra: &[String],
+        highlight: Highlight,
+    ) -> io::Result<()> {
+        let Some(repos) = self.repos else {
+            return Ok(());
+        };
+        let repo = repos.repo_of(record.name);
+        let (count, rows) = self
+            .groups
+            .entry((repos.rank(repo), repo.to_string()))
+            .or_default();
+        *count += 1;
+        output::write_record(rows, palette, record, extra, highlight)
+    }
+
+    pub fn finish(&mut self, out: &mut impl Write, palette: &Palette) -> io::Result<()> {
+        let edge = palette.glyph("──", "");
+        for ((_, repo), (count, rows)) in std::mem::take(&mut self.groups) {
+            let text = format!("{edge} {repo} ({count}) {edge}");
+            writeln!(out, "{}", palette.pkg.bold().paint(text.trim()))?;
+            out.write_all(&rows)?;
+        }
+        Ok(())
+    }
+}
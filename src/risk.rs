@@ -0,0 +1,99 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use pkglist_core::backend;
+use pkglist_core::history::{self, Status};
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman;
+
+use crate::i18n::tr;
+
+// Packages that ship matching halves of one ABI; upgrading one without the
+// other is the most common way a partial upgrade breaks a system.
+const COUPLED_PACKAGES: &[(&str, &str)] = &[
+    ("linux", "linux-headers"),
+    ("linux-lts", "linux-lts-headers"),
+    ("linux-zen", "linux-zen-headers"),
+    ("systemd", "systemd-libs"),
+    ("gcc", "gcc-libs"),
+    ("glibc", "lib32-glibc"),
+    ("mesa", "lib32-mesa"),
+    ("nvidia-utils", "lib32-nvidia-utils"),
+];
+
+const COUPLING_SLACK_SECS: i64 = 60 * 60;
+
+fn timestamp(date: &str) -> Option<i64> {
+    history::parse_log_date(date).map(|date| date.timestamp())
+}
+
+fn last_change(data: &PackageData, name: &str) -> Option<DateTime<FixedOffset>> {
+    let info = data.history.get(name)?;
+    if info.status == Status::Removed {
+        return None;
+    }
+    info.date
+}
+
+// What makes a partial upgrade likely, each on its own; the listing prefixes
+// them as partial upgrade risks, `doctor` files them under its check.
+pub fn partial_upgrade_warnings(data: &PackageData) -> Vec<String> {
+    let mut warnings = Vec::new();
+    // Syncs and full upgrades are only logged, and partial upgrades only
+    // unsupported, on pacman.
+    if backend::current().name() != "pacman" {
+        return warnings;
+    }
+
+    let logged_sync = data.markers.last_sync.as_deref().and_then(timestamp);
+    let db_sync = pacman::sync_db_mtime().map(|time| DateTime::<Utc>::from(time).timestamp());
+    let last_full = data
+        .markers
+        .last_full_upgrade
+        .as_deref()
+        .and_then(timestamp)
+        .unwrap_or(i64::MIN);
+
+    if let Some(synced) = logged_sync
+        .max(db_sync)
+        .filter(|&synced| synced > last_full)
+    {
+        let changed_since = data
+            .history
+            .values()
+            .filter(|info| info.status != Status::Removed)
+            .filter(|info| info.date.is_some_and(|date| date.timestamp() > synced))
+            .count();
+
+        if changed_since > 0 {
+            let synced = DateTime::from_timestamp(synced, 0)
+                .map(|date| date.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_default();
+            let last = match data.markers.last_full_upgrade.as_deref() {
+                Some(date) => date.to_string(),
+                None => tr!("risk-never"),
+            };
+            warnings.push(tr!(
+                "risk-unsynced",
+                count = changed_since,
+                synced = synced,
+                last = last
+            ));
+        }
+    }
+
+    for (a, b) in COUPLED_PACKAGES {
+        let (Some(date_a), Some(date_b)) = (last_change(data, a), last_change(data, b)) else {
+            continue;
+        };
+        if (date_a - date_b).num_seconds().abs() > COUPLING_SLACK_SECS {
+            warnings.push(tr!(
+                "risk-coupled",
+                a = a,
+                date_a = history::format_log_date(&date_a),
+                b = b,
+                date_b = history::format_log_date(&date_b)
+            ));
+        }
+    }
+
+    warnings
+}
@@ -0,0 +1,96 @@
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::exec;
+use pkglist_core::pacman::{self, LocalDb};
+
+use crate::matching;
+use crate::output::Palette;
+use crate::transactions;
+
+const ARCHIVE_URL: &str = "https://archive.archlinux.org/packages";
+
+// Versions the package has had according to the log, oldest first, each with
+// the date it was first seen.
+fn known_versions(package: &str) -> io::Result<Vec<(String, String)>> {
+    let mut versions: Vec<(String, String)> = Vec::new();
+    for tx in transactions::load()? {
+        for change in tx.changes.iter().filter(|change| change.name == package) {
+            for version in [&change.old_version, &change.new_version]
+                .into_iter()
+                .flatten()
+            {
+                if !versions.iter().any(|(known, _)| known == version) {
+                    versions.push((version.clone(), change.date.clone()));
+                }
+            }
+        }
+    }
+    Ok(versions)
+}
+
+// The Arch Linux Archive keeps every package under its first letter.
+fn archive_url(package: &str, version: &str, arch: &str) -> String {
+    let letter = package.chars().next().unwrap_or('_');
+    format!("{ARCHIVE_URL}/{letter}/{package}/{package}-{version}-{arch}.pkg.tar.zst")
+}
+
+fn locate(local: &LocalDb, package: &str, version: &str) -> String {
+    let cached = pacman::cached_packages()
+        .into_iter()
+        .find(|(name, cached_version, _)| name == package && cached_version == version);
+    match cached {
+        Some((_, _, path)) => path.display().to_string(),
+        None => {
+            let arch = local.field(package, "Architecture").unwrap_or("x86_64");
+            archive_url(package, version, arch)
+        }
+    }
+}
+
+pub fn run(palette: &Palette, package: &str, to: Option<&str>, execute: bool) -> io::Result<()> {
+    let local = LocalDb::load();
+    let package = matching::find(local.names(), package).unwrap_or(package);
+    let versions = known_versions(package)?;
+    let installed = local.field(package, "Version");
+
+    let Some(version) = to else {
+        let mut out = BufWriter::new(io::stdout().lock());
+        if versions.is_empty() {
+            writeln!(out, "no versions of {package} recorded in the log")?;
+        }
+        for (version, date) in &versions {
+            let marker = if Some(version.as_str()) == installed {
+                " (installed)"
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "{} :: {} :: {}{marker}",
+                palette.date.paint(date),
+                palette.pkg.paint(package),
+                palette.upg.paint(version)
+            )?;
+        }
+        return out.flush();
+    };
+
+    if Some(version) == installed {
+        return Err(io::Error::other(format!(
+            "{package} {version} is already installed"
+        )));
+    }
+    if !versions.iter().any(|(known, _)| known == version) {
+        eprintln!("pkglist: {package} {version} does not appear in the log; trying anyway");
+    }
+
+    let source = locate(&local, package, version);
+    let mut command = exec::privileged("pacman");
+    command.args(["-U", &source]);
+    println!("{}", exec::command_line(&command));
+
+    if execute && exec::confirm(&format!("Downgrade {package} to {version}?"))? {
+        exec::run_checked(&mut command)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use pkglist_core::packages::PackageRecord;
+use schemars::{JsonSchema, schema_for};
+use serde::Serialize;
+
+// Bump whenever a field is removed, renamed or changes meaning; adding
+// optional fields is backwards compatible and keeps the version.
+pub const SCHEMA_VERSION: u32 = 2;
+
+// A record as the listing exports it: the fields every record has, and the
+// optional columns that were selected, so JSON carries what CSV does.
+#[derive(Serialize, JsonSchema)]
+pub struct ListedPackage<'a> {
+    #[serde(flatten)]
+    pub record: PackageRecord<'a>,
+    /// Other selected columns by name, as the listing shows them: `--columns`, script columns and markers like `ignored`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub columns: BTreeMap<String, String>,
+}
+
+impl<'a> From<PackageRecord<'a>> for ListedPackage<'a> {
+    fn from(record: PackageRecord<'a>) -> Self {
+        ListedPackage {
+            record,
+            columns: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PackageList<'a> {
+    pub schema_version: u32,
+    pub packages: Vec<ListedPackage<'a>>,
+}
+
+impl<'a> PackageList<'a> {
+    pub fn new(packages: Vec<ListedPackage<'a>>) -> Self {
+        PackageList {
+            schema_version: SCHEMA_VERSION,
+            packages,
+        }
+    }
+}
+
+pub fn print_schema() -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut out, &schema_for!(PackageList<'static>))?;
+    writeln!(out)
+}
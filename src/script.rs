@@ -0,0 +1,116 @@
+use std::io;
+
+use pkglist_core::packages::PackageRecord;
+
+use crate::config::ScriptConfig;
+
+// Rhai expressions from `[script]` see the record as the variables `name`,
+// `date` and `status`; the filter must yield a bool, columns any value.
+#[cfg(feature = "scripting")]
+pub struct ScriptHooks {
+    engine: rhai::Engine,
+    filter: Option<rhai::AST>,
+    columns: Vec<rhai::AST>,
+}
+
+#[cfg(not(feature = "scripting"))]
+pub struct ScriptHooks;
+
+#[cfg(feature = "scripting")]
+fn script_error(what: &str, err: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("script {what}: {err}"))
+}
+
+#[cfg(feature = "scripting")]
+impl ScriptHooks {
+    pub fn new(config: &ScriptConfig) -> io::Result<Self> {
+        let engine = rhai::Engine::new();
+        let filter = config
+            .filter
+            .as_deref()
+            .map(|expr| engine.compile_expression(expr))
+            .transpose()
+            .map_err(|e| script_error("filter", e))?;
+        let columns = config
+            .columns
+            .iter()
+            .map(|column| {
+                engine
+                    .compile_expression(&column.expr)
+                    .map_err(|e| script_error(&format!("column '{}'", column.name), e))
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(ScriptHooks {
+            engine,
+            filter,
+            columns,
+        })
+    }
+
+    fn scope(record: &PackageRecord<'_>) -> rhai::Scope<'static> {
+        let mut scope = rhai::Scope::new();
+        scope.push_constant("name", record.name.to_string());
+        // Unknown dates are empty, so they compare as older than any other.
+        let date = record
+            .date
+            .map(|date| pkglist_core::history::format_log_date(&date));
+        scope.push_constant("date", date.unwrap_or_default());
+        scope.push_constant("status", record.status.as_str());
+        scope
+    }
+
+    pub fn accepts(&self, record: &PackageRecord<'_>) -> io::Result<bool> {
+        match &self.filter {
+            Some(ast) => self
+                .engine
+                .eval_ast_with_scope::<bool>(&mut Self::scope(record), ast)
+                .map_err(|e| script_error("filter", e)),
+            None => Ok(true),
+        }
+    }
+
+    pub fn columns(&self, record: &PackageRecord<'_>) -> io::Result<Vec<String>> {
+        if self.columns.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut scope = Self::scope(record);
+        self.columns
+            .iter()
+            .map(|ast| {
+                self.engine
+                    .eval_ast_with_scope::<rhai::Dynamic>(&mut scope, ast)
+                    .map(|value| value.to_string())
+                    .map_err(|e| script_error("column", e))
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+impl ScriptHooks {
+    pub fn new(config: &ScriptConfig) -> io::Result<Self> {
+        let mut ignored: Vec<_> = config.filter.iter().map(|_| "filter".to_string()).collect();
+        ignored.extend(
+            config
+                .columns
+                .iter()
+                .map(|column| format!("column '{}'", column.name)),
+        );
+        if !ignored.is_empty() {
+            eprintln!(
+                "pkglist: built without the `scripting` feature, ignoring [script] {}",
+                ignored.join(", ")
+            );
+        }
+        Ok(ScriptHooks)
+    }
+
+    pub fn accepts(&self, _record: &PackageRecord<'_>) -> io::Result<bool> {
+        Ok(true)
+    }
+
+    pub fn columns(&self, _record: &PackageRecord<'_>) -> io::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+}
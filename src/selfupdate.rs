@@ -0,0 +1,166 @@
+use std::env;
+use std::io::{self, Read};
+use std::process::Command;
+
+use pkglist_core::exec;
+use pkglist_core::progress;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+const RELEASES_URL: &str = "https://api.github.com/repos/johsve-source/pkglist/releases/latest";
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+const MAX_DOWNLOAD: u64 = 64 * 1024 * 1024;
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+fn http_error(e: ureq::Error) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+// Failures worth another attempt: the network, not the server's answer.
+fn is_transient(e: &ureq::Error) -> bool {
+    matches!(
+        e,
+        ureq::Error::Io(_)
+            | ureq::Error::Timeout(_)
+            | ureq::Error::HostNotFound
+            | ureq::Error::ConnectionFailed
+    )
+}
+
+// Connecting and waiting for the response are bounded by the configured
+// timeout and retried like other queries; the body of a large asset may
+// take as long as it takes.
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    let limits = exec::limits();
+    let agent = ureq::Agent::config_builder()
+        .timeout_connect(limits.timeout)
+        .timeout_recv_response(limits.timeout)
+        .build()
+        .new_agent();
+    let mut response = exec::retry(
+        limits,
+        || {
+            agent
+                .get(url)
+                .header("User-Agent", concat!("pkglist/", env!("CARGO_PKG_VERSION")))
+                .call()
+        },
+        |result| result.as_ref().is_err_and(is_transient),
+    )
+    .map_err(http_error)?;
+    let mut task = progress::Task::start("download", "bytes", response.body().content_length());
+    let mut reader = response
+        .body_mut()
+        .with_config()
+        .limit(MAX_DOWNLOAD)
+        .reader();
+    let mut body = Vec::new();
+    let mut chunk = [0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(body);
+        }
+        body.extend_from_slice(&chunk[..read]);
+        task.set(body.len() as u64);
+    }
+}
+
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn find_asset<'a>(release: &'a Release, name: &str) -> io::Result<&'a Asset> {
+    release
+        .assets
+        .iter()
+        .find(|asset| asset.name == name)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("release {} has no asset {name}", release.tag_name),
+            )
+        })
+}
+
+// Refuse to overwrite a binary that pacman manages; that copy belongs to the
+// distro package and should be updated through it.
+fn owned_by_pacman(path: &std::path::Path) -> bool {
+    exec::query(Command::new("pacman").arg("-Qoq").arg(path), |_| false)
+        .is_ok_and(|output| output.status.success())
+}
+
+pub fn run(check_only: bool) -> io::Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+
+    let body = download(RELEASES_URL)?;
+    let release: Release = serde_json::from_slice(&body)?;
+
+    if parse_version(&release.tag_name) <= parse_version(current) {
+        println!("pkglist {current} is up to date");
+        return Ok(());
+    }
+    println!("pkglist {current} -> {}", release.tag_name);
+    if check_only {
+        return Ok(());
+    }
+
+    let exe = env::current_exe()?.canonicalize()?;
+    if owned_by_pacman(&exe) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is managed by pacman, update it there", exe.display()),
+        ));
+    }
+
+    let asset_name = format!("pkglist-{}-linux", env::consts::ARCH);
+    let binary = download(&find_asset(&release, &asset_name)?.browser_download_url)?;
+    let sums = download(&find_asset(&release, CHECKSUMS_ASSET)?.browser_download_url)?;
+
+    // The checksums come from the same release: they catch a corrupted or
+    // truncated download, not a release someone else published.
+    let expected = String::from_utf8_lossy(&sums)
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim().trim_start_matches('*') == asset_name)
+        .map(|(sum, _)| sum.to_ascii_lowercase())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{CHECKSUMS_ASSET} has no entry for {asset_name}"),
+            )
+        })?;
+    let actual = hex(&Sha256::digest(&binary));
+    if actual != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("checksum mismatch for {asset_name}: expected {expected}, got {actual}"),
+        ));
+    }
+
+    exec::write_file(&exe, &binary, Some(0o755))?;
+    if exec::is_dry_run() {
+        return Ok(());
+    }
+    println!("updated {} to {}", exe.display(), release.tag_name);
+    Ok(())
+}
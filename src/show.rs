@@ -0,0 +1,83 @@
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::history;
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::LocalDb;
+use pkglist_core::size::format_size;
+
+use crate::lookup;
+use crate::notes::Notes;
+use crate::output::{self, Highlight, Palette};
+use crate::replacements::Replacements;
+use crate::transactions;
+
+// `-Qi` fields shown under the log record, with the label they get.
+const FIELDS: &[(&str, &str)] = &[
+    ("Version", "version"),
+    ("Description", "description"),
+    ("URL", "url"),
+    ("Provides", "provides"),
+    ("Conflicts With", "conflicts"),
+    ("Replaces", "replaces"),
+];
+
+pub fn run(data: &PackageData, palette: &Palette, package: &str) -> io::Result<()> {
+    let local = LocalDb::load();
+    let found = lookup::find(&local, package)?;
+    let name = found.name();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if let Some(note) = found.note(package) {
+        writeln!(out, "{note}")?;
+    }
+    output::write_record(
+        &mut out,
+        palette,
+        &data.record(name),
+        &[],
+        Highlight::default(),
+    )?;
+
+    for (key, label) in FIELDS {
+        if let Some(value) = local.field(name, key) {
+            writeln!(out, "  {label} :: {value}")?;
+        }
+    }
+    let transactions = transactions::load().unwrap_or_default();
+    let identities = Replacements::load(&local).previous_identities(data, &transactions, name);
+    if !identities.is_empty() {
+        let chain: Vec<String> = identities
+            .iter()
+            .map(|identity| match identity.transaction {
+                Some(id) => format!(
+                    "{} (until {}, replaced in #{id})",
+                    identity.name,
+                    history::date_or_unknown(identity.until)
+                ),
+                None => format!(
+                    "{} (until {})",
+                    identity.name,
+                    history::date_or_unknown(identity.until)
+                ),
+            })
+            .collect();
+        writeln!(
+            out,
+            "  previously :: {}",
+            chain.join(palette.glyph(" ← ", ", before that "))
+        )?;
+    }
+    let reason = if local.is_explicit(name) {
+        "explicit"
+    } else {
+        "dependency"
+    };
+    writeln!(out, "  reason :: {reason}")?;
+    if let Some(size) = local.installed_size(name) {
+        writeln!(out, "  size :: {}", format_size(size))?;
+    }
+    if let Some(note) = Notes::load().get(name) {
+        writeln!(out, "  note :: {note}")?;
+    }
+    out.flush()
+}
@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use pkglist_core::backend;
+use pkglist_core::exec;
+use pkglist_core::growth;
+use pkglist_core::history;
+use pkglist_core::pacman::{self, InstallScope};
+use serde::{Deserialize, Serialize};
+
+use crate::diff;
+use crate::output::Palette;
+
+const FORMAT: u32 = 1;
+const BUNDLE_MANIFEST: &str = "snapshot.json";
+
+// The installed packages as a file meant for version control: pretty-printed
+// and sorted by name, so a commit shows exactly what changed.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Snapshot {
+    format: u32,
+    taken: String,
+    packages: BTreeMap<String, Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    version: String,
+    explicit: bool,
+    // In no sync repository: built from the AUR or installed from a file.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    foreign: bool,
+}
+
+impl Snapshot {
+    fn current() -> io::Result<Self> {
+        let versions = backend::current().installed_versions();
+        if versions.is_empty() {
+            return Err(io::Error::other("pacman -Q listed no packages"));
+        }
+        let explicit: HashSet<String> = backend::current()
+            .read_installed(InstallScope::Explicit)
+            .into_iter()
+            .collect();
+        let foreign: HashSet<String> = backend::current().foreign_packages().into_iter().collect();
+        let packages = versions
+            .into_iter()
+            .map(|(name, version)| {
+                let entry = Entry {
+                    version,
+                    explicit: explicit.contains(&name),
+                    foreign: foreign.contains(&name),
+                };
+                (name, entry)
+            })
+            .collect();
+        Ok(Snapshot {
+            format: FORMAT,
+            taken: history::format_log_date(&Local::now().fixed_offset()),
+            packages,
+        })
+    }
+
+    fn load(file: &Path) -> io::Result<Self> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", file.display()),
+            )
+        };
+        let bytes = fs::read(file)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", file.display())))?;
+        let snapshot: Snapshot = serde_json::from_slice(&bytes).map_err(|e| invalid(&e))?;
+        if snapshot.format > FORMAT {
+            return Err(invalid(&format!(
+                "snapshot format {} is newer than this pkglist understands",
+                snapshot.format
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    fn versions(&self) -> BTreeMap<String, String> {
+        self.packages
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.version.clone()))
+            .collect()
+    }
+
+    fn names(&self, keep: impl Fn(&Entry) -> bool) -> Vec<&str> {
+        self.packages
+            .iter()
+            .filter(|(_, entry)| keep(entry))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+pub fn save(file: &Path) -> io::Result<()> {
+    let snapshot = Snapshot::current()?;
+    let mut json = serde_json::to_vec_pretty(&snapshot)?;
+    json.push(b'\n');
+    exec::write_file(file, &json, None)?;
+    growth::refresh();
+    println!(
+        "{} packages saved to {}",
+        snapshot.packages.len(),
+        file.display()
+    );
+    Ok(())
+}
+
+// A directory to restore the installed packages from without a network: the
+// snapshot as its manifest, next to the package file of every package. The
+// installed versions come from the package cache; `pacman -Sw` downloads the
+// rest, in the version the repositories have now. Foreign packages have no
+// repository to download from and only appear in the manifest.
+pub fn bundle(dir: &Path) -> io::Result<()> {
+    let snapshot = Snapshot::current()?;
+    exec::create_dir_all(dir)?;
+    let cached: BTreeMap<(String, String), PathBuf> = pacman::cached_packages()
+        .into_iter()
+        .map(|(name, version, path)| ((name, version), path))
+        .collect();
+    let mut copied = 0;
+    let mut missing = Vec::new();
+    for (name, entry) in snapshot.packages.iter().filter(|(_, entry)| !entry.foreign) {
+        let Some(path) = cached.get(&(name.clone(), entry.version.clone())) else {
+            missing.push(name.as_str());
+            continue;
+        };
+        let sig = PathBuf::from(format!("{}.sig", path.display()));
+        for file in [path, &sig].into_iter().filter(|file| file.exists()) {
+            if let Some(file_name) = file.file_name() {
+                exec::copy_file(file, &dir.join(file_name))?;
+            }
+        }
+        copied += 1;
+    }
+    if !missing.is_empty() {
+        let mut command = exec::privileged("pacman");
+        command
+            .args(["-Sw", "--noconfirm", "--cachedir"])
+            .arg(std::path::absolute(dir)?)
+            .args(&missing);
+        exec::run_checked(&mut command)?;
+    }
+    let mut json = serde_json::to_vec_pretty(&snapshot)?;
+    json.push(b'\n');
+    exec::write_file(&dir.join(BUNDLE_MANIFEST), &json, None)?;
+    let foreign = snapshot.names(|entry| entry.foreign).len();
+    println!(
+        "{copied} packages copied from the cache and {} downloaded to {}",
+        missing.len(),
+        dir.display()
+    );
+    if foreign > 0 {
+        println!("{foreign} foreign packages are listed in {BUNDLE_MANIFEST} but not bundled");
+    }
+    Ok(())
+}
+
+// Changes from snapshot `a` to snapshot `b`, or to the installed packages
+// without `b`, in the format of `pkglist diff`; true when there are any.
+pub fn diff(palette: &Palette, a: &Path, b: Option<&Path>, quiet: bool) -> io::Result<bool> {
+    let before = Snapshot::load(a)?;
+    let after = match b {
+        Some(b) => Snapshot::load(b)?,
+        None => Snapshot::current()?,
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    let found = diff::print_changes(
+        &mut out,
+        palette,
+        &before.versions(),
+        &after.versions(),
+        &before.taken,
+        quiet,
+    )?;
+    out.flush()?;
+    Ok(found)
+}
+
+fn write_install(
+    out: &mut impl Write,
+    pacman: &str,
+    flags: &str,
+    names: &[&str],
+) -> io::Result<()> {
+    if names.is_empty() {
+        return Ok(());
+    }
+    write!(out, "{pacman} -S --needed{flags}")?;
+    // One package per line, so the script diffs as cleanly as the snapshot.
+    for name in names {
+        write!(out, " \\\n    {name}")?;
+    }
+    writeln!(out)
+}
+
+// A shell script installing the snapshot's packages from the repositories:
+// explicit ones first, then the dependencies nothing pulled in, kept as
+// dependencies. Foreign packages can't come from pacman and are listed in
+// comments.
+pub fn restore_script(file: &Path) -> io::Result<()> {
+    let snapshot = Snapshot::load(file)?;
+    let pacman = exec::command_line(&exec::privileged("pacman"));
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(out, "#!/bin/sh")?;
+    writeln!(
+        out,
+        "# Restores the packages of {}, taken {}.",
+        file.display(),
+        snapshot.taken
+    )?;
+    writeln!(
+        out,
+        "# pacman installs the versions the repositories have now, not the recorded ones."
+    )?;
+    writeln!(out, "set -e")?;
+    write_install(
+        &mut out,
+        &pacman,
+        "",
+        &snapshot.names(|entry| entry.explicit && !entry.foreign),
+    )?;
+    write_install(
+        &mut out,
+        &pacman,
+        " --asdeps",
+        &snapshot.names(|entry| !entry.explicit && !entry.foreign),
+    )?;
+    let foreign = snapshot.names(|entry| entry.foreign);
+    if !foreign.is_empty() {
+        writeln!(
+            out,
+            "# Foreign packages, to install from the AUR or their source:"
+        )?;
+        for name in foreign {
+            writeln!(out, "#   {name} {}", snapshot.packages[name].version)?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use chrono::Datelike;
+use pkglist_core::history;
+
+use crate::transactions;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const MAX_QUARTERS: i32 = 12;
+
+fn quarter(date: &str) -> Option<i32> {
+    let date = history::parse_log_date(date)?;
+    Some(date.year() * 4 + date.month0() as i32 / 3)
+}
+
+// Log events per package per calendar quarter, over the most recent
+// quarters of the log.
+#[derive(Default)]
+pub struct Activity {
+    counts: HashMap<String, Vec<u32>>,
+    quarters: usize,
+}
+
+impl Activity {
+    pub fn load() -> Self {
+        let transactions = transactions::load().unwrap_or_default();
+        let events: Vec<(&str, i32)> = transactions
+            .iter()
+            .flat_map(|tx| &tx.changes)
+            .filter_map(|change| Some((change.name.as_str(), quarter(&change.date)?)))
+            .collect();
+        let Some(last) = events.iter().map(|&(_, quarter)| quarter).max() else {
+            return Activity::default();
+        };
+        let first = events
+            .iter()
+            .map(|&(_, quarter)| quarter)
+            .min()
+            .unwrap_or(last)
+            .max(last - MAX_QUARTERS + 1);
+        let quarters = (last - first + 1) as usize;
+
+        let mut counts: HashMap<String, Vec<u32>> = HashMap::new();
+        for (name, quarter) in events {
+            if quarter < first {
+                continue;
+            }
+            counts
+                .entry(name.to_string())
+                .or_insert_with(|| vec![0; quarters])[(quarter - first) as usize] += 1;
+        }
+        Activity { counts, quarters }
+    }
+
+    // Plain counts per quarter, oldest first, for accessible output.
+    pub fn counts(&self, name: &str) -> String {
+        let counts = self
+            .counts
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| vec![0; self.quarters]);
+        let counts: Vec<String> = counts.iter().map(u32::to_string).collect();
+        format!("events per quarter {}", counts.join(" "))
+    }
+
+    // Scaled to the package's own busiest quarter; quiet quarters stay blank
+    // so single events remain visible.
+    pub fn sparkline(&self, name: &str) -> String {
+        let Some(counts) = self.counts.get(name) else {
+            return " ".repeat(self.quarters);
+        };
+        let max = counts.iter().copied().max().unwrap_or(0).max(1) as usize;
+        counts
+            .iter()
+            .map(|&count| match count as usize {
+                0 => ' ',
+                count => BARS[(count * BARS.len()).div_ceil(max) - 1],
+            })
+            .collect()
+    }
+}
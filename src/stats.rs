@@ -0,0 +1,507 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufWriter, Write};
+
+use chrono::{DateTime, FixedOffset, Local, TimeDelta};
+use pkglist_core::backend;
+use pkglist_core::growth::{self, Sizes};
+use pkglist_core::history::{self, Event, Status};
+use pkglist_core::jump::{self, Jump};
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::{self, InstallScope, LocalDb};
+use pkglist_core::size::format_size;
+
+use crate::boots::format_duration;
+use crate::i18n::tr;
+use crate::output::{self, Highlight, Palette};
+use crate::summary::Summary;
+use crate::transactions;
+
+const TOP_UPGRADED: usize = 5;
+const TOP_GROWTH: usize = 10;
+
+pub struct StatsOptions<'a> {
+    pub licenses: bool,
+    pub license: Option<&'a str>,
+    pub growth: bool,
+    pub jumps: bool,
+    // Where --growth starts counting and the period --jumps covers, date
+    // prefixes like `2024-07`.
+    pub since: Option<&'a str>,
+    pub until: Option<&'a str>,
+}
+
+// Folds SPDX identifiers and Arch's legacy names ("GPL2", "custom:foo")
+// into the families an audit cares about.
+pub fn license_family(license: &str) -> &'static str {
+    let id = license
+        .trim_matches(|c| c == '(' || c == ')')
+        .to_ascii_lowercase();
+    let id = id.as_str();
+    match id {
+        "" => "unknown",
+        _ if id.starts_with("agpl") => "AGPL",
+        _ if id.starts_with("lgpl") => "LGPL",
+        _ if id.starts_with("gpl") => "GPL",
+        _ if id.starts_with("mit") => "MIT",
+        _ if id.starts_with("bsd") || id == "0bsd" => "BSD",
+        _ if id.starts_with("apache") => "Apache",
+        _ if id.starts_with("mpl") => "MPL",
+        _ if id == "isc" => "ISC",
+        _ if id == "zlib" => "Zlib",
+        _ if id.starts_with("cc0") || id == "unlicense" || id.contains("public") => "public domain",
+        _ if id.contains("proprietary") || id.contains("nonfree") || id == "unfree" => {
+            "proprietary"
+        }
+        _ if id.starts_with("custom") || id.starts_with("licenseref") => "custom",
+        _ => "other",
+    }
+}
+
+fn license_ids(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split_whitespace()
+        .filter(|token| !matches!(*token, "AND" | "OR" | "WITH"))
+}
+
+fn package_families(value: &str) -> Vec<&'static str> {
+    let mut families: Vec<_> = license_ids(value).map(license_family).collect();
+    if families.is_empty() {
+        families.push("unknown");
+    }
+    families.sort_unstable();
+    families.dedup();
+    families
+}
+
+fn print_overview(out: &mut impl Write, palette: &Palette, data: &PackageData) -> io::Result<()> {
+    let mut summary = Summary::default();
+    for record in data.records_by_date() {
+        summary.record(record.status);
+    }
+    summary.write(out, palette)?;
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "stats-explicit",
+            count = backend::current()
+                .read_installed(InstallScope::Explicit)
+                .len()
+        )
+    )?;
+    print_upgrade_durations(out, palette)?;
+    let events = history::parse_events(&history::read_log_file().unwrap_or_default());
+    print_average_age(out, palette, data, &events)?;
+    print_activity(out, palette, &events)
+}
+
+// When a package was first installed: its earliest logged install, or the
+// local database's estimate for packages that predate the log.
+fn first_installed(
+    data: &PackageData,
+    events: &BTreeMap<String, Vec<Event>>,
+    name: &str,
+) -> Option<DateTime<FixedOffset>> {
+    events
+        .get(name)
+        .and_then(|events| {
+            events
+                .iter()
+                .find(|event| event.status == Status::Installed)
+        })
+        .and_then(|event| history::parse_log_date(&event.date))
+        .or_else(|| data.estimated.get(name).copied())
+}
+
+fn print_average_age(
+    out: &mut impl Write,
+    palette: &Palette,
+    data: &PackageData,
+    events: &BTreeMap<String, Vec<Event>>,
+) -> io::Result<()> {
+    let now = Local::now().fixed_offset();
+    let ages: Vec<TimeDelta> = data
+        .installed
+        .iter()
+        .filter_map(|name| Some(now - first_installed(data, events, name)?))
+        .collect();
+    if ages.is_empty() {
+        return Ok(());
+    }
+    let average = ages.iter().sum::<TimeDelta>() / ages.len() as i32;
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "stats-average-age",
+            age = palette.upg.paint(format_duration(average)),
+            count = ages.len()
+        )
+    )
+}
+
+// Aggregates every logged event, where the listing keeps only each
+// package's latest: the busiest day, the most upgraded packages and the
+// events per month.
+fn print_activity(
+    out: &mut impl Write,
+    palette: &Palette,
+    events: &BTreeMap<String, Vec<Event>>,
+) -> io::Result<()> {
+    let mut days: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut months: BTreeMap<&str, BTreeMap<Status, usize>> = BTreeMap::new();
+    let mut upgraded: Vec<(&str, usize)> = Vec::new();
+    for (name, events) in events {
+        for event in events {
+            let (Some(day), Some(month)) = (event.date.get(..10), event.date.get(..7)) else {
+                continue;
+            };
+            *days.entry(day).or_default() += 1;
+            *months
+                .entry(month)
+                .or_default()
+                .entry(event.status)
+                .or_default() += 1;
+        }
+        let upgrades = events
+            .iter()
+            .filter(|event| event.status == Status::Upgraded)
+            .count();
+        if upgrades > 0 {
+            upgraded.push((name, upgrades));
+        }
+    }
+
+    // The earliest of equally busy days.
+    if let Some((day, count)) = days.iter().rev().max_by_key(|(_, count)| **count) {
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "stats-busiest-day",
+                date = palette.date.paint(*day),
+                count = count
+            )
+        )?;
+    }
+    upgraded.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    if !upgraded.is_empty() {
+        let top: Vec<String> = upgraded
+            .iter()
+            .take(TOP_UPGRADED)
+            .map(|(name, count)| format!("{} ({count})", palette.pkg.paint(*name)))
+            .collect();
+        writeln!(
+            out,
+            "{}",
+            tr!("stats-most-upgraded", packages = top.join(", "))
+        )?;
+    }
+    if months.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "{}", tr!("stats-per-month"))?;
+    for (month, counts) in &months {
+        write!(out, "{}", palette.date.paint(*month))?;
+        // Downgrades and reinstalls are rare, so they only show when there
+        // were any.
+        for status in Status::ALL {
+            let count = counts.get(&status).copied().unwrap_or(0);
+            if count == 0 && matches!(status, Status::Downgraded | Status::Reinstalled) {
+                continue;
+            }
+            write!(
+                out,
+                " :: {count} {}",
+                palette.status(status).paint(palette.label(status))
+            )?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn print_upgrade_durations(out: &mut impl Write, palette: &Palette) -> io::Result<()> {
+    let transactions = transactions::load().unwrap_or_default();
+    let durations: Vec<(usize, TimeDelta)> = transactions
+        .iter()
+        .filter(|tx| tx.is_upgrade())
+        .filter_map(|tx| Some((tx.id, tx.duration()?)))
+        .collect();
+    let Some(&(longest_id, longest)) = durations.iter().max_by_key(|(_, duration)| *duration)
+    else {
+        return Ok(());
+    };
+
+    let total: TimeDelta = durations.iter().map(|(_, duration)| *duration).sum();
+    let average = total / durations.len() as i32;
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "stats-upgrades",
+            count = durations.len(),
+            average = palette.upg.paint(format_duration(average)),
+            longest = palette.rem.paint(format_duration(longest)),
+            id = longest_id
+        )
+    )
+}
+
+fn total(sizes: &Sizes) -> i64 {
+    sizes.values().sum::<u64>() as i64
+}
+
+fn signed_size(palette: &Palette, bytes: i64) -> String {
+    let text = format_size(bytes.unsigned_abs());
+    if bytes < 0 {
+        palette.ins.paint(format!("-{text}")).to_string()
+    } else {
+        palette.rem.paint(format!("+{text}")).to_string()
+    }
+}
+
+// The recorded sizes, plus the installed ones now when they differ from the
+// latest record: the total then and now, the total at the end of each month
+// and the packages that grew or shrank the most.
+fn print_growth(out: &mut impl Write, palette: &Palette, since: Option<&str>) -> io::Result<()> {
+    let records = match growth::path() {
+        Some(path) => growth::read(path)?,
+        None => Vec::new(),
+    };
+    let mut states = growth::replay(&records);
+    let now = history::format_log_date(&Local::now().fixed_offset());
+    let current = growth::installed(&LocalDb::load());
+    if !current.is_empty() && states.last().map(|(_, sizes)| sizes) != Some(&current) {
+        states.push((&now, current));
+    }
+    if states.len() < 2 {
+        return writeln!(out, "{}", tr!("stats-growth-none"));
+    }
+
+    // The state as of `since`: the last one recorded before it.
+    let start = since
+        .and_then(|since| states.iter().rposition(|(date, _)| *date < since))
+        .unwrap_or(0);
+    let states = &states[start..];
+    let (from_date, from) = &states[0];
+    let (_, to) = &states[states.len() - 1];
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "stats-growth-total",
+            from = format_size(total(from) as u64),
+            date = palette.date.paint(*from_date),
+            to = format_size(total(to) as u64),
+            change = signed_size(palette, total(to) - total(from))
+        )
+    )?;
+
+    let mut months: BTreeMap<&str, &Sizes> = BTreeMap::new();
+    for (date, sizes) in states {
+        months.insert(date.get(..7).unwrap_or(date), sizes);
+    }
+    writeln!(out, "{}", tr!("stats-per-month"))?;
+    let mut previous = total(from);
+    for (month, sizes) in months {
+        writeln!(
+            out,
+            "{} :: {} :: {}",
+            palette.date.paint(month),
+            format_size(total(sizes) as u64),
+            signed_size(palette, total(sizes) - previous)
+        )?;
+        previous = total(sizes);
+    }
+
+    let change = |name: &str| {
+        to.get(name).copied().unwrap_or(0) as i64 - from.get(name).copied().unwrap_or(0) as i64
+    };
+    let mut changes: Vec<(&str, i64)> = to
+        .keys()
+        .chain(from.keys().filter(|name| !to.contains_key(*name)))
+        .map(|name| (name.as_str(), change(name)))
+        .filter(|(_, change)| *change != 0)
+        .collect();
+    changes.sort_by_key(|(name, change)| (std::cmp::Reverse(change.unsigned_abs()), *name));
+    if changes.is_empty() {
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "stats-growth-packages",
+            date = palette.date.paint(*from_date)
+        )
+    )?;
+    let size = |sizes: &Sizes, name: &str| {
+        sizes
+            .get(name)
+            .map(|bytes| format_size(*bytes))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    for (name, change) in changes.into_iter().take(TOP_GROWTH) {
+        writeln!(
+            out,
+            "{} :: {} :: {} -> {}",
+            signed_size(palette, change),
+            palette.pkg.paint(name),
+            size(from, name),
+            size(to, name)
+        )?;
+    }
+    Ok(())
+}
+
+fn write_jump_counts(
+    out: &mut impl Write,
+    palette: &Palette,
+    counts: &BTreeMap<Jump, usize>,
+) -> io::Result<()> {
+    for jump in Jump::ALL {
+        let count = counts.get(&jump).copied().unwrap_or(0);
+        let label = jump.as_str();
+        let label = match jump {
+            Jump::Major => palette.rem.paint(label),
+            Jump::Minor => palette.upg.paint(label),
+            Jump::Patch | Jump::Rebuild => palette.ins.paint(label),
+        };
+        write!(out, " :: {count} {label}")?;
+    }
+    writeln!(out)
+}
+
+// How far the upgrades in the period jumped: the counts per kind, then per
+// month, then the major upgrades themselves, which are the ones worth a
+// reboot or a test before trusting the system again.
+fn print_jumps(
+    out: &mut impl Write,
+    palette: &Palette,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> io::Result<()> {
+    let events = history::parse_events(&history::read_log_file().unwrap_or_default());
+    let in_period = |date: &str| {
+        since.is_none_or(|since| date >= since)
+            && until.is_none_or(|until| date.get(..until.len()).unwrap_or(date) <= until)
+    };
+    let mut upgrades: Vec<(&str, &str, &str, Jump)> = events
+        .iter()
+        .flat_map(|(name, events)| events.iter().map(move |event| (name.as_str(), event)))
+        .filter(|(_, event)| event.status == Status::Upgraded && in_period(&event.date))
+        .filter_map(|(name, event)| {
+            let jump = jump::classify(&event.versions)?;
+            Some((event.date.as_str(), name, event.versions.as_str(), jump))
+        })
+        .collect();
+    if upgrades.is_empty() {
+        return writeln!(out, "{}", tr!("stats-jumps-none"));
+    }
+    upgrades.sort();
+
+    let mut totals: BTreeMap<Jump, usize> = BTreeMap::new();
+    let mut months: BTreeMap<&str, BTreeMap<Jump, usize>> = BTreeMap::new();
+    for (date, _, _, jump) in &upgrades {
+        *totals.entry(*jump).or_default() += 1;
+        let month = date.get(..7).unwrap_or(date);
+        *months.entry(month).or_default().entry(*jump).or_default() += 1;
+    }
+    write!(out, "{}", tr!("stats-jumps", count = upgrades.len()))?;
+    write_jump_counts(out, palette, &totals)?;
+    writeln!(out, "{}", tr!("stats-per-month"))?;
+    for (month, counts) in &months {
+        write!(out, "{}", palette.date.paint(*month))?;
+        write_jump_counts(out, palette, counts)?;
+    }
+
+    let majors: Vec<_> = upgrades
+        .iter()
+        .filter(|(_, _, _, jump)| *jump == Jump::Major)
+        .collect();
+    if majors.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "{}", tr!("stats-jumps-major"))?;
+    for (date, name, versions, _) in majors {
+        writeln!(
+            out,
+            "{} :: {} :: {}",
+            palette.date.paint(date.get(..10).unwrap_or(date)),
+            palette.pkg.paint(*name),
+            versions.replace(" -> ", &format!(" {} ", palette.glyph("→", "to")))
+        )?;
+    }
+    Ok(())
+}
+
+fn print_licenses(
+    out: &mut impl Write,
+    palette: &Palette,
+    data: &PackageData,
+    filter: Option<&str>,
+) -> io::Result<()> {
+    let blocks = pacman::local_info(&data.installed, false);
+    let mut by_family: BTreeMap<&'static str, Vec<(&str, &str)>> = BTreeMap::new();
+
+    for block in &blocks {
+        let (Some(name), licenses) = (
+            block.get("Name"),
+            block.get("Licenses").map(String::as_str).unwrap_or(""),
+        ) else {
+            continue;
+        };
+        for family in package_families(licenses) {
+            by_family.entry(family).or_default().push((name, licenses));
+        }
+    }
+
+    let Some(filter) = filter else {
+        let width = by_family
+            .keys()
+            .map(|family| family.len())
+            .max()
+            .unwrap_or(0);
+        for (family, packages) in &by_family {
+            writeln!(
+                out,
+                "{} :: {}",
+                palette.pkg.paint(format!("{family:<width$}")),
+                packages.len()
+            )?;
+        }
+        return Ok(());
+    };
+
+    let packages = by_family
+        .iter()
+        .find(|(family, _)| family.eq_ignore_ascii_case(filter))
+        .map(|(_, packages)| packages.as_slice())
+        .unwrap_or_default();
+    for (name, licenses) in packages {
+        let record = data.record(name);
+        output::write_record(
+            out,
+            palette,
+            &record,
+            &[licenses.to_string()],
+            Highlight::default(),
+        )?;
+    }
+    Ok(())
+}
+
+pub fn run(data: &PackageData, palette: &Palette, options: &StatsOptions<'_>) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    if options.growth {
+        print_growth(&mut out, palette, options.since)?;
+    } else if options.jumps {
+        print_jumps(&mut out, palette, options.since, options.until)?;
+    } else if options.licenses || options.license.is_some() {
+        print_licenses(&mut out, palette, data, options.license)?;
+    } else {
+        print_overview(&mut out, palette, data)?;
+    }
+    out.flush()
+}
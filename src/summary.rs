@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use pkglist_core::history::Status;
+
+use crate::i18n::tr;
+use crate::output::Palette;
+
+#[derive(Default)]
+pub struct Summary {
+    counts: BTreeMap<Status, usize>,
+    total: usize,
+    pub warnings: Vec<String>,
+}
+
+impl Summary {
+    pub fn record(&mut self, status: Status) {
+        *self.counts.entry(status).or_default() += 1;
+        self.total += 1;
+    }
+
+    pub fn write(&self, out: &mut impl Write, palette: &Palette) -> io::Result<()> {
+        write!(out, "{}", tr!("summary-packages", count = self.total))?;
+        for (status, count) in &self.counts {
+            write!(out, " :: {count} {}", palette.label(*status))?;
+        }
+        writeln!(out)?;
+
+        for warning in &self.warnings {
+            writeln!(
+                out,
+                "{} {}",
+                palette
+                    .watched
+                    .bold()
+                    .paint(palette.glyph("!!", "warning:")),
+                palette.watched.paint(warning)
+            )?;
+        }
+        Ok(())
+    }
+}
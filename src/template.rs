@@ -0,0 +1,126 @@
+// A small template language for text pkglist sends elsewhere: `{name}` is
+// replaced by the value called `name`, and `{name|json}` by the value as a
+// quoted JSON string. Braces around anything else are left alone, so JSON
+// templates need no escaping of their own.
+
+const FILTERS: &[&str] = &["json"];
+
+fn apply(filter: Option<&str>, value: &str) -> String {
+    match filter {
+        Some("json") => serde_json::Value::from(value).to_string(),
+        _ => value.to_string(),
+    }
+}
+
+// Values are inserted in one pass, so a value that itself contains
+// `{name}` is never expanded.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let placeholder = rest[1..].find('}').map(|end| &rest[1..=end]);
+        let expanded = placeholder.and_then(|placeholder| {
+            let (name, filter) = match placeholder.split_once('|') {
+                Some((name, filter)) => (name, Some(filter)),
+                None => (placeholder, None),
+            };
+            if filter.is_some_and(|filter| !FILTERS.contains(&filter)) {
+                return None;
+            }
+            let (_, value) = values.iter().find(|(key, _)| *key == name)?;
+            Some((placeholder.len() + 2, apply(filter, value)))
+        });
+        match expanded {
+            Some((len, value)) => {
+                out.push_str(&value);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Backslash escapes as printf and expac know them, for templates typed on
+// the command line: `\n`, `\t`, `\e` (to start a colour sequence) and `\\`.
+// Any other backslash is kept as it is.
+pub fn unescape(template: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('e') => out.push('\x1b'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: &[(&str, &str)] = &[("name", "vim"), ("version", "9.1-1")];
+
+    #[test]
+    fn known_placeholders_expand_and_unknown_stay() {
+        assert_eq!(render("{name} {version}", VALUES), "vim 9.1-1");
+        assert_eq!(render("{name} {size}", VALUES), "vim {size}");
+        assert_eq!(
+            render(r#"{"pkg": {name|json}}"#, VALUES),
+            r#"{"pkg": "vim"}"#
+        );
+    }
+
+    #[test]
+    fn json_filter_quotes() {
+        let values = [("name", "say \"hi\"\n\\")];
+        assert_eq!(render("{name|json}", &values), r#""say \"hi\"\n\\""#);
+    }
+
+    #[test]
+    fn unknown_filter_is_left_alone() {
+        assert_eq!(render("{name|upper}", VALUES), "{name|upper}");
+        assert_eq!(render("{size|json}", VALUES), "{size|json}");
+    }
+
+    #[test]
+    fn values_are_not_expanded_again() {
+        let values = [("name", "{version}"), ("version", "1.0")];
+        assert_eq!(render("{name} {version}", &values), "{version} 1.0");
+    }
+
+    #[test]
+    fn stray_brace_is_kept() {
+        assert_eq!(render("{name} {", VALUES), "vim {");
+        assert_eq!(render("{{name}", VALUES), "{vim");
+        assert_eq!(render("{", VALUES), "{");
+    }
+
+    #[test]
+    fn unescape_known_escapes() {
+        assert_eq!(unescape(r"a\nb"), "a\nb");
+        assert_eq!(unescape(r"a\tb"), "a\tb");
+        assert_eq!(unescape(r"\e[1m"), "\x1b[1m");
+        assert_eq!(unescape(r"a\\nb"), r"a\nb");
+        assert_eq!(unescape(r"a\qb"), r"a\qb");
+        assert_eq!(unescape(r"end\"), r"end\");
+    }
+}
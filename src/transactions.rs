@@ -0,0 +1,448 @@
+use std::io::{self, BufWriter, Write};
+
+use chrono::TimeDelta;
+use pkglist_core::history;
+use pkglist_core::progress;
+
+use crate::boots::{RebootMarkers, format_duration};
+use crate::config::Labels;
+use crate::i18n::tr;
+use crate::output::{Ink, Palette};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Installed,
+    Upgraded,
+    Downgraded,
+    Reinstalled,
+    Removed,
+}
+
+impl Action {
+    fn parse(word: &str) -> Option<Self> {
+        Some(match word {
+            "installed" => Action::Installed,
+            "upgraded" => Action::Upgraded,
+            "downgraded" => Action::Downgraded,
+            "reinstalled" => Action::Reinstalled,
+            "removed" => Action::Removed,
+            _ => return None,
+        })
+    }
+
+    pub fn label(self, labels: &Labels) -> &str {
+        match self {
+            Action::Installed => &labels.installed,
+            Action::Upgraded => &labels.upgraded,
+            Action::Downgraded => &labels.downgraded,
+            Action::Reinstalled => &labels.reinstalled,
+            Action::Removed => &labels.removed,
+        }
+    }
+
+    pub fn colour(self, palette: &Palette) -> Ink {
+        match self {
+            Action::Installed => palette.ins,
+            Action::Upgraded => palette.upg,
+            Action::Downgraded => palette.dwn,
+            Action::Reinstalled => palette.rns,
+            Action::Removed => palette.rem,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub date: String,
+    pub action: Action,
+    pub name: String,
+    // The version before the transaction, absent for fresh installs.
+    pub old_version: Option<String>,
+    // The version after the transaction, absent for removals.
+    pub new_version: Option<String>,
+}
+
+impl Change {
+    // `old -> new`, or the single version for installs, removals and reinstalls.
+    pub fn versions(&self) -> String {
+        match (&self.old_version, &self.new_version) {
+            (Some(old), Some(new)) if old != new => format!("{old} -> {new}"),
+            (Some(version), _) | (None, Some(version)) => version.clone(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Completed,
+    Failed,
+    Interrupted,
+    // The log ends inside the transaction, e.g. after a crash or power loss.
+    Unfinished,
+}
+
+impl Outcome {
+    pub fn label(self) -> String {
+        match self {
+            Outcome::Completed => tr!("transactions-completed"),
+            Outcome::Failed => tr!("transactions-failed"),
+            Outcome::Interrupted => tr!("transactions-interrupted"),
+            Outcome::Unfinished => tr!("transactions-unfinished"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub id: usize,
+    pub command: Option<String>,
+    pub started: String,
+    pub finished: Option<String>,
+    pub outcome: Outcome,
+    pub changes: Vec<Change>,
+    pub hooks: Vec<String>,
+    pub failed_hooks: Vec<String>,
+    pub messages: Vec<String>,
+}
+
+impl Transaction {
+    // From `transaction started` to its end, or to the last package event if
+    // the end was never logged.
+    pub fn duration(&self) -> Option<TimeDelta> {
+        let start = history::parse_log_date(&self.started)?;
+        let end = self
+            .finished
+            .as_deref()
+            .or_else(|| self.changes.last().map(|change| change.date.as_str()))?;
+        Some(history::parse_log_date(end)? - start)
+    }
+
+    pub fn is_upgrade(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|change| change.action == Action::Upgraded)
+    }
+}
+
+// `upgraded foo (1.0-1 -> 1.1-1)`, `installed foo (1.0-1)`, ...
+fn parse_change(date: &str, message: &str) -> Option<Change> {
+    let (word, rest) = message.split_once(' ')?;
+    let action = Action::parse(word)?;
+    let (name, versions) = rest.split_once(" (")?;
+    let versions = versions.strip_suffix(')')?;
+    let (old_version, new_version) = match (action, versions.split_once(" -> ")) {
+        (_, Some((old, new))) => (Some(old), Some(new)),
+        (Action::Installed, None) => (None, Some(versions)),
+        (Action::Removed, None) => (Some(versions), None),
+        (_, None) => (Some(versions), Some(versions)),
+    };
+    Some(Change {
+        date: date.to_string(),
+        action,
+        name: name.to_string(),
+        old_version: old_version.map(str::to_string),
+        new_version: new_version.map(str::to_string),
+    })
+}
+
+// Groups the log into pacman transactions, numbered from 1 in log order.
+// The `Running '...'` command and any pre-transaction hooks are attached to
+// the transaction that follows them; post-transaction hooks to the one that
+// just finished.
+pub fn parse_transactions(log_content: &[u8]) -> Vec<Transaction> {
+    let mut transactions: Vec<Transaction> = Vec::new();
+    let mut open = false;
+    let mut command: Option<String> = None;
+    let mut pre_hooks: Vec<String> = Vec::new();
+    let mut task = progress::Task::start(
+        "parse-transactions",
+        "bytes",
+        Some(log_content.len() as u64),
+    );
+
+    for line in log_content.split(|&b| b == b'\n') {
+        task.advance(line.len() as u64 + 1);
+        let Ok(line) = std::str::from_utf8(line) else {
+            continue;
+        };
+        let Some((date, rest)) = line.strip_prefix('[').and_then(|l| l.split_once("] [")) else {
+            continue;
+        };
+        let Some((source, message)) = rest.split_once("] ") else {
+            continue;
+        };
+
+        match source {
+            "PACMAN" => {
+                if let Some(cmd) = message
+                    .strip_prefix("Running '")
+                    .and_then(|m| m.strip_suffix('\''))
+                {
+                    command = Some(cmd.to_string());
+                    pre_hooks.clear();
+                }
+            }
+            "ALPM" if message == "transaction started" => {
+                if let Some(last) = transactions.last_mut().filter(|_| open) {
+                    last.outcome = Outcome::Unfinished;
+                }
+                transactions.push(Transaction {
+                    id: transactions.len() + 1,
+                    command: command.take(),
+                    started: date.to_string(),
+                    finished: None,
+                    outcome: Outcome::Unfinished,
+                    changes: Vec::new(),
+                    hooks: std::mem::take(&mut pre_hooks),
+                    failed_hooks: Vec::new(),
+                    messages: Vec::new(),
+                });
+                open = true;
+            }
+            "ALPM" => {
+                let outcome = match message {
+                    "transaction completed" => Some(Outcome::Completed),
+                    "transaction failed" => Some(Outcome::Failed),
+                    "transaction interrupted" => Some(Outcome::Interrupted),
+                    _ => None,
+                };
+                if let Some(outcome) = outcome {
+                    if let Some(last) = transactions.last_mut().filter(|_| open) {
+                        last.outcome = outcome;
+                        last.finished = Some(date.to_string());
+                    }
+                    open = false;
+                    continue;
+                }
+
+                if let Some(hook) = message
+                    .strip_prefix("running '")
+                    .and_then(|m| m.strip_suffix("'..."))
+                {
+                    match transactions.last_mut() {
+                        Some(last) if command.is_none() => last.hooks.push(hook.to_string()),
+                        _ => pre_hooks.push(hook.to_string()),
+                    }
+                    continue;
+                }
+
+                // Post-transaction hooks report their failures after the
+                // transaction completed, so those still belong to it.
+                let Some(last) = transactions
+                    .last_mut()
+                    .filter(|_| open || command.is_none())
+                else {
+                    continue;
+                };
+                if let Some(change) = parse_change(date, message).filter(|_| open) {
+                    last.changes.push(change);
+                } else if message.starts_with("warning:") || message.starts_with("error:") {
+                    if message == "error: command failed to execute correctly"
+                        && let Some(hook) = last.hooks.last()
+                    {
+                        last.failed_hooks.push(hook.clone());
+                    }
+                    last.messages.push(message.to_string());
+                }
+            }
+            "ALPM-SCRIPTLET" => {
+                if let Some(last) = transactions.last_mut().filter(|_| open) {
+                    last.messages.push(message.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    transactions
+}
+
+pub fn load() -> io::Result<Vec<Transaction>> {
+    history::read_log_file().map(|log| parse_transactions(&log))
+}
+
+fn write_header(out: &mut impl Write, palette: &Palette, tx: &Transaction) -> io::Result<()> {
+    let outcome = match tx.outcome {
+        Outcome::Completed => palette.ins.paint(tx.outcome.label()),
+        _ => palette.rem.paint(tx.outcome.label()),
+    };
+    write!(
+        out,
+        "#{} :: {} :: {} :: {} package(s)",
+        tx.id,
+        palette.date.paint(&tx.started),
+        outcome,
+        tx.changes.len()
+    )?;
+    if let Some(duration) = tx.duration() {
+        write!(out, " :: {}", format_duration(duration))?;
+    }
+    if let Some(command) = &tx.command {
+        write!(out, " :: {command}")?;
+    }
+    writeln!(out)
+}
+
+pub fn list(palette: &Palette, reboots: bool) -> io::Result<()> {
+    let transactions = load()?;
+    let mut markers = RebootMarkers::new(reboots);
+    let mut out = BufWriter::new(io::stdout().lock());
+    for tx in &transactions {
+        markers.before(&mut out, palette, history::parse_log_date(&tx.started))?;
+        write_header(&mut out, palette, tx)?;
+    }
+    markers.finish(&mut out, palette)?;
+    out.flush()
+}
+
+pub fn show(palette: &Palette, id: usize) -> io::Result<()> {
+    let transactions = load()?;
+    let Some(tx) = id.checked_sub(1).and_then(|index| transactions.get(index)) else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            tr!(
+                "transactions-not-found",
+                id = id,
+                count = transactions.len()
+            ),
+        ));
+    };
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    write_header(&mut out, palette, tx)?;
+    if let Some(finished) = &tx.finished {
+        writeln!(
+            out,
+            "{}",
+            tr!("transactions-finished", date = palette.date.paint(finished))
+        )?;
+    }
+
+    for change in &tx.changes {
+        writeln!(
+            out,
+            "  {} :: {} :: {} :: {}",
+            palette.date.paint(&change.date),
+            change
+                .action
+                .colour(palette)
+                .paint(change.action.label(&palette.labels)),
+            palette.pkg.paint(&change.name),
+            change.versions()
+        )?;
+    }
+
+    if !tx.hooks.is_empty() {
+        writeln!(out, "{}", tr!("transactions-hooks"))?;
+        for hook in &tx.hooks {
+            if tx.failed_hooks.contains(hook) {
+                writeln!(
+                    out,
+                    "  {hook} {}",
+                    palette.rem.paint(tr!("transactions-hook-failed"))
+                )?;
+            } else {
+                writeln!(out, "  {hook}")?;
+            }
+        }
+    }
+    if !tx.messages.is_empty() {
+        writeln!(out, "{}", tr!("transactions-messages"))?;
+        for message in &tx.messages {
+            writeln!(out, "  {}", palette.upg.paint(message))?;
+        }
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An upgrade with a pre- and a failing post-transaction hook, an
+    // interrupted install, a removal the next transaction cut short, and a
+    // log that ends in the middle of a line.
+    const LOG: &str = "\
+[2024-03-01T10:00:00+0100] [PACMAN] Running 'pacman -Syu'
+[2024-03-01T10:00:01+0100] [ALPM] running '60-mkinitcpio-remove.hook'...
+[2024-03-01T10:00:01+0100] [ALPM] transaction started
+[2024-03-01T10:00:02+0100] [ALPM] upgraded linux (6.7.0-1 -> 6.7.1-1)
+[2024-03-01T10:00:03+0100] [ALPM] installed foo (1.0-1)
+[2024-03-01T10:00:03+0100] [ALPM-SCRIPTLET] foo: remember to configure it
+[2024-03-01T10:00:04+0100] [ALPM] transaction completed
+[2024-03-01T10:00:05+0100] [ALPM] running '90-mkinitcpio-install.hook'...
+[2024-03-01T10:00:06+0100] [ALPM] error: command failed to execute correctly
+[2024-03-02T09:00:00+0100] [PACMAN] Running 'pacman -S bar'
+[2024-03-02T09:00:01+0100] [ALPM] transaction started
+[2024-03-02T09:00:02+0100] [ALPM] transaction interrupted
+[2024-03-03T09:00:00+0100] [PACMAN] Running 'pacman -R foo'
+[2024-03-03T09:00:01+0100] [ALPM] transaction started
+[2024-03-03T09:00:02+0100] [ALPM] removed foo (1.0-1)
+[2024-03-04T09:00:00+0100] [PACMAN] Running 'pacman -S baz'
+[2024-03-04T09:00:01+0100] [ALPM] transaction started
+[2024-03-04T09:00:02+0100] [ALPM] installed baz (2.0-1)
+[2024-03-04T09:00:03+0100] [ALPM] upgraded qu";
+
+    #[test]
+    fn hooks_attach_around_their_transaction() {
+        let transactions = parse_transactions(LOG.as_bytes());
+        assert_eq!(transactions.len(), 4);
+        let upgrade = &transactions[0];
+        assert_eq!(upgrade.id, 1);
+        assert_eq!(upgrade.command.as_deref(), Some("pacman -Syu"));
+        assert_eq!(upgrade.outcome, Outcome::Completed);
+        assert_eq!(
+            upgrade.hooks,
+            ["60-mkinitcpio-remove.hook", "90-mkinitcpio-install.hook"]
+        );
+        assert_eq!(upgrade.failed_hooks, ["90-mkinitcpio-install.hook"]);
+        assert_eq!(
+            upgrade.messages,
+            [
+                "foo: remember to configure it",
+                "error: command failed to execute correctly"
+            ]
+        );
+        assert!(upgrade.is_upgrade());
+        assert_eq!(upgrade.duration(), Some(TimeDelta::seconds(3)));
+
+        let changes: Vec<(Action, &str, String)> = upgrade
+            .changes
+            .iter()
+            .map(|change| (change.action, change.name.as_str(), change.versions()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (Action::Upgraded, "linux", "6.7.0-1 -> 6.7.1-1".to_string()),
+                (Action::Installed, "foo", "1.0-1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unended_transactions_are_marked() {
+        let transactions = parse_transactions(LOG.as_bytes());
+        let interrupted = &transactions[1];
+        assert_eq!(interrupted.outcome, Outcome::Interrupted);
+        assert_eq!(
+            interrupted.finished.as_deref(),
+            Some("2024-03-02T09:00:02+0100")
+        );
+        assert!(interrupted.changes.is_empty() && interrupted.hooks.is_empty());
+
+        // Cut short by the next transaction starting.
+        let removal = &transactions[2];
+        assert_eq!(removal.outcome, Outcome::Unfinished);
+        assert_eq!(removal.finished, None);
+        assert_eq!(removal.changes.len(), 1);
+        assert_eq!(removal.changes[0].new_version, None);
+
+        // The log ends inside it, with half a line to skip.
+        let truncated = &transactions[3];
+        assert_eq!(truncated.command.as_deref(), Some("pacman -S baz"));
+        assert_eq!(truncated.outcome, Outcome::Unfinished);
+        assert_eq!(truncated.changes.len(), 1);
+        assert_eq!(truncated.duration(), Some(TimeDelta::seconds(1)));
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::LocalDb;
+
+use crate::lookup;
+use crate::output::Palette;
+
+struct TreeWriter<'a, W> {
+    out: W,
+    palette: &'a Palette,
+    data: &'a PackageData,
+    local: &'a LocalDb,
+    max_depth: Option<usize>,
+    seen: HashSet<&'a str>,
+}
+
+impl<'a, W: Write> TreeWriter<'a, W> {
+    fn node(&mut self, name: &str, prefix: &str, connector: &str) -> io::Result<bool> {
+        let record = self.data.record(name);
+        let repeated = self.seen.contains(name);
+        if self.palette.accessible {
+            // The status is otherwise only told apart by colour.
+            write!(
+                self.out,
+                "{prefix}{connector}{name} ({}, {})",
+                self.palette.date_text(&record),
+                self.palette.label(record.status)
+            )?;
+        } else {
+            write!(
+                self.out,
+                "{prefix}{connector}{} {}",
+                self.palette.status(record.status).paint(name),
+                self.palette
+                    .date
+                    .paint(format!("({})", self.palette.date_text(&record)))
+            )?;
+        }
+        if repeated {
+            let marker = self.palette.glyph("…", "(already shown)");
+            write!(self.out, " {}", self.palette.date.dimmed().paint(marker))?;
+        }
+        writeln!(self.out)?;
+        Ok(!repeated)
+    }
+
+    // Each package is expanded the first time it appears; later occurrences
+    // are marked with an ellipsis, which also keeps dependency cycles finite.
+    fn children(&mut self, name: &'a str, prefix: &str, depth: usize) -> io::Result<()> {
+        self.seen.insert(name);
+        if self.max_depth.is_some_and(|max| depth >= max) {
+            return Ok(());
+        }
+
+        let mut deps = self.local.depends(name);
+        deps.sort_unstable();
+        deps.dedup();
+
+        let count = deps.len();
+        for (i, dep) in deps.into_iter().enumerate() {
+            let last = i + 1 == count;
+            let (connector, indent) = match (self.palette.accessible, last) {
+                (true, _) => (format!("level {}: ", depth + 1), ""),
+                (false, true) => ("└─ ".to_string(), "   "),
+                (false, false) => ("├─ ".to_string(), "│  "),
+            };
+            if self.node(dep, prefix, &connector)? {
+                self.children(dep, &format!("{prefix}{indent}"), depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn run(
+    data: &PackageData,
+    palette: &Palette,
+    package: &str,
+    max_depth: Option<usize>,
+) -> io::Result<()> {
+    let local = LocalDb::load();
+    let found = lookup::find(&local, package)?;
+    let root = found.name();
+    if let Some(note) = found.note(package) {
+        eprintln!("pkglist: {note}");
+    }
+
+    let mut tree = TreeWriter {
+        out: BufWriter::new(io::stdout().lock()),
+        palette,
+        data,
+        local: &local,
+        max_depth,
+        seen: HashSet::new(),
+    };
+    tree.node(root, "", "")?;
+    tree.children(root, "", 0)?;
+    tree.out.flush()
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufWriter, Write};
+
+use lazy_static::lazy_static;
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman;
+use regex::Regex;
+
+use crate::output::{self, Highlight, Palette};
+
+lazy_static! {
+    static ref SUMMARY_REGEX: Regex =
+        Regex::new(r"^(\S+): (\d+) total files, (\d+) (?:missing|altered) files$").unwrap();
+    static ref WARNING_REGEX: Regex = Regex::new(r"^warning: (\S+): (.+) \((.+)\)$").unwrap();
+}
+
+#[derive(Debug, Default)]
+struct Integrity {
+    total: u64,
+    missing: Vec<String>,
+    modified: Vec<(String, String)>,
+}
+
+impl Integrity {
+    fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.modified.is_empty()
+    }
+}
+
+fn parse_report(stdout: &str, stderr: &str) -> BTreeMap<String, Integrity> {
+    let mut report: BTreeMap<String, Integrity> = BTreeMap::new();
+
+    for caps in stdout
+        .lines()
+        .filter_map(|line| SUMMARY_REGEX.captures(line))
+    {
+        report.entry(caps[1].to_string()).or_default().total = caps[2].parse().unwrap_or(0);
+    }
+
+    for caps in stderr
+        .lines()
+        .filter_map(|line| WARNING_REGEX.captures(line))
+    {
+        let entry = report.entry(caps[1].to_string()).or_default();
+        let path = caps[2].to_string();
+        match &caps[3] {
+            "No such file or directory" => entry.missing.push(path),
+            reason => entry.modified.push((path, reason.to_string())),
+        }
+    }
+    report
+}
+
+pub fn run(
+    data: &PackageData,
+    palette: &Palette,
+    packages: &[String],
+    deep: bool,
+) -> io::Result<()> {
+    let captured = pacman::check_files(packages, deep)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "failed to run pacman -Qk"))?;
+    let report = parse_report(&captured.stdout, &captured.stderr);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut damaged = 0;
+
+    for (name, integrity) in report.iter().filter(|(_, integrity)| !integrity.is_clean()) {
+        damaged += 1;
+        let record = data.record(name);
+        let summary = format!(
+            "{} of {} files: {} missing, {} modified",
+            integrity.missing.len() + integrity.modified.len(),
+            integrity.total,
+            integrity.missing.len(),
+            integrity.modified.len()
+        );
+        output::write_record(&mut out, palette, &record, &[summary], Highlight::default())?;
+
+        for path in &integrity.missing {
+            writeln!(out, "    {} {path}", palette.rem.paint("missing"))?;
+        }
+        for (path, reason) in &integrity.modified {
+            writeln!(
+                out,
+                "    {} {path} ({reason})",
+                palette.upg.paint("modified")
+            )?;
+        }
+    }
+
+    writeln!(
+        out,
+        "{} packages checked ({}), {damaged} with missing or modified files",
+        report.len(),
+        if deep { "deep" } else { "quick" }
+    )?;
+    out.flush()
+}
@@ -0,0 +1,74 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use pkglist_core::dirs;
+use pkglist_core::history;
+use pkglist_core::pacman;
+
+const FEATURES: &[(&str, bool)] = &[
+    ("dnf", cfg!(feature = "dnf")),
+    ("scripting", cfg!(feature = "scripting")),
+    ("self-update", cfg!(feature = "self-update")),
+    ("sql", cfg!(feature = "sql")),
+];
+
+fn describe_file(path: &Path) -> String {
+    match fs::metadata(path) {
+        Ok(meta) => format!("{} ({} bytes)", path.display(), meta.len()),
+        Err(_) => format!("{} (missing)", path.display()),
+    }
+}
+
+pub fn print_version(cache_file: Option<&Path>) -> io::Result<()> {
+    let features: Vec<_> = FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+    let config_file = dirs::config_dir().map(|dir| dir.join("config.toml"));
+
+    let mut out = io::stdout().lock();
+    writeln!(
+        out,
+        "pkglist {} ({} {})",
+        env!("CARGO_PKG_VERSION"),
+        env!("PKGLIST_GIT_COMMIT"),
+        env!("PKGLIST_BUILD_DATE")
+    )?;
+    writeln!(
+        out,
+        "features: {}",
+        if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        }
+    )?;
+    writeln!(out, "backend:  pacman")?;
+    writeln!(
+        out,
+        "pacman:   {}",
+        pacman::version().unwrap_or_else(|| "not found".to_string())
+    )?;
+    let rotated = history::rotated_logs().len();
+    writeln!(
+        out,
+        "log:      {}{}",
+        describe_file(history::log_path()),
+        if rotated > 0 {
+            format!(" (+{rotated} rotated)")
+        } else {
+            String::new()
+        }
+    )?;
+
+    match cache_file {
+        Some(path) => writeln!(out, "cache:    {}", describe_file(path))?,
+        None => writeln!(out, "cache:    (disabled)")?,
+    }
+    match config_file {
+        Some(path) => writeln!(out, "config:   {}", describe_file(&path)),
+        None => writeln!(out, "config:   (no HOME)"),
+    }
+}
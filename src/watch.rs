@@ -0,0 +1,176 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use pkglist_core::history;
+use pkglist_core::packages::PackageData;
+use rustix::fs::inotify::{self, CreateFlags, WatchFlags};
+
+use crate::cli::Cli;
+use crate::config::{Config, HookConfig};
+use crate::hooks;
+use crate::listing::Listing;
+use crate::output::Palette;
+use crate::watchlist;
+
+// The lines pacman ends a transaction with, whatever its outcome.
+const TRANSACTION_ENDS: &[&str] = &[
+    "[ALPM] transaction completed",
+    "[ALPM] transaction failed",
+    "[ALPM] transaction interrupted",
+];
+
+fn ends_transaction(tail: &[u8]) -> bool {
+    let tail = String::from_utf8_lossy(tail);
+    TRANSACTION_ENDS.iter().any(|end| tail.contains(end))
+}
+
+// The packages whose latest event changed from `before` to `after`, as data
+// of their own so the listing prints them with the usual filters and columns.
+fn changes(before: &PackageData, after: &PackageData) -> PackageData {
+    let history: BTreeMap<_, _> = after
+        .history
+        .iter()
+        .filter(|(name, info)| before.history.get(*name) != Some(*info))
+        .map(|(name, info)| (name.clone(), info.clone()))
+        .collect();
+    PackageData {
+        installed: after
+            .installed
+            .iter()
+            .filter(|name| history.contains_key(*name))
+            .cloned()
+            .collect(),
+        history,
+        markers: after.markers.clone(),
+        estimated: HashMap::new(),
+    }
+}
+
+// What a finished transaction changed, with the `[hooks]` commands run for
+// it as `pkglist notify` would.
+fn finish(hooks: &HookConfig, before: &PackageData, after: &PackageData) -> PackageData {
+    let changed = changes(before, after);
+    hooks::run_all(hooks, &changed.history);
+    changed
+}
+
+// Waits on the log's directory rather than the log, so a log that logrotate
+// moved away and pacman started anew is followed too. Every change to the
+// log rereads what it gained since the last transaction; once that includes
+// the end of one, the history is reloaded, which the cache keeps incremental,
+// the hooks run and the packages that changed are listed.
+pub fn run(
+    cli: &Cli,
+    config: &Config,
+    palette: &Palette,
+    mut data: PackageData,
+    load: impl Fn() -> PackageData,
+) -> io::Result<()> {
+    let log = history::log_path();
+    let dir = log
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = log.file_name().unwrap_or(OsStr::new("")).as_bytes();
+    let inotify = inotify::init(CreateFlags::CLOEXEC)?;
+    inotify::add_watch(
+        &inotify,
+        dir,
+        WatchFlags::MODIFY | WatchFlags::CREATE | WatchFlags::MOVED_TO,
+    )?;
+    let mut buffer = [MaybeUninit::uninit(); 4096];
+    let mut events = inotify::Reader::new(&inotify, &mut buffer);
+    let mut offset = history::get_log_size();
+    loop {
+        let mut touched = false;
+        loop {
+            let event = events.next()?;
+            // Database logs change beside themselves too, in `-wal` files.
+            touched |= event
+                .file_name()
+                .is_some_and(|file| file.to_bytes().starts_with(name));
+            if events.is_buffer_empty() {
+                break;
+            }
+        }
+        if !touched {
+            continue;
+        }
+        let finished = match history::read_log_tail(offset) {
+            Some((tail, _)) => ends_transaction(&tail),
+            None => true,
+        };
+        if !finished {
+            continue;
+        }
+        offset = history::get_log_size();
+        let latest = load();
+        let changed = finish(&config.hooks, &data, &latest);
+        data = latest;
+        if let Err(e) = watchlist::check(config, &data) {
+            eprintln!("pkglist: watchlist: {e}");
+        }
+        if !changed.history.is_empty() {
+            Listing::new(config, &changed, false)?.print(cli, palette, &changed)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pkglist_core::history::{PackageInfo, Status};
+
+    use super::*;
+
+    fn data(events: &[(&str, &str, Status)]) -> PackageData {
+        PackageData {
+            history: events
+                .iter()
+                .map(|(name, date, status)| {
+                    let info = PackageInfo {
+                        date: history::parse_log_date(date),
+                        status: *status,
+                        versions: String::new(),
+                        source: None,
+                    };
+                    (name.to_string(), info)
+                })
+                .collect(),
+            installed: Vec::new(),
+            markers: Default::default(),
+            estimated: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn finished_transactions_run_hooks() {
+        let path = std::env::temp_dir().join(format!("pkglist-watch-{}", std::process::id()));
+        let hooks = HookConfig {
+            on_upgrade: Some(format!(
+                "echo \"$PKGLIST_EVENT $PKGLIST_PACKAGES $PKGLIST_COUNT\" >> {}",
+                path.display()
+            )),
+            ..HookConfig::default()
+        };
+        let before = data(&[
+            ("bash", "2024-01-01T10:00:00+0100", Status::Installed),
+            ("linux", "2024-01-01T10:00:00+0100", Status::Upgraded),
+        ]);
+        let after = data(&[
+            ("bash", "2024-01-01T10:00:00+0100", Status::Installed),
+            ("firefox", "2024-01-15T14:30:48+0100", Status::Upgraded),
+            ("linux", "2024-01-15T14:30:47+0100", Status::Upgraded),
+        ]);
+        let changed = finish(&hooks, &before, &after);
+        let written = fs::read_to_string(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(changed.history.len(), 2);
+        assert_eq!(written.unwrap(), "upgrade firefox linux 2\n");
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+
+use pkglist_core::dirs;
+use pkglist_core::exec;
+use pkglist_core::history::{PackageInfo, Status};
+use pkglist_core::packages::PackageData;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::notify::{self, Change};
+
+const STATE_FILE: &str = "watchlist.json";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct WatchState {
+    packages: BTreeMap<String, PackageInfo>,
+}
+
+pub struct Watchlist<'a> {
+    names: HashSet<&'a str>,
+}
+
+impl<'a> Watchlist<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Watchlist {
+            names: config.watchlist.iter().map(String::as_str).collect(),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+}
+
+fn current_state(config: &Config, data: &PackageData) -> WatchState {
+    let packages = config
+        .watchlist
+        .iter()
+        .filter_map(|name| {
+            let info = data.history.get(name).cloned().or_else(|| {
+                data.installed
+                    .binary_search(name)
+                    .ok()
+                    .map(|_| PackageInfo {
+                        date: None,
+                        status: Status::Installed,
+                        versions: String::new(),
+                        source: None,
+                    })
+            })?;
+            Some((name.clone(), info))
+        })
+        .collect();
+    WatchState { packages }
+}
+
+// Compares watched packages against the state recorded by the previous run
+// and fires the configured notifiers for any that changed. The first run
+// only records a baseline so enabling the watchlist doesn't alert on
+// everything at once.
+pub fn check(config: &Config, data: &PackageData) -> io::Result<()> {
+    if config.watchlist.is_empty() {
+        return Ok(());
+    }
+    let Some(state_dir) = dirs::state_dir() else {
+        return Ok(());
+    };
+    let state_file = state_dir.join(STATE_FILE);
+
+    let previous: Option<WatchState> = fs::read(&state_file)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    let current = current_state(config, data);
+
+    if let Some(previous) = previous {
+        let changes: Vec<_> = current
+            .packages
+            .iter()
+            .filter(|(name, info)| previous.packages.get(*name) != Some(info))
+            .map(|(name, info)| Change {
+                name,
+                label: config.labels.label(info.status),
+                date: info.date,
+            })
+            .collect();
+
+        if !changes.is_empty() {
+            let title = tr!("watchlist-changed", count = changes.len());
+            notify::send_all(&config.notifiers, &title, &changes);
+        }
+    }
+
+    exec::write_file(&state_file, &serde_json::to_vec(&current)?, None)
+}
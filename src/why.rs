@@ -0,0 +1,112 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufWriter, Write};
+
+use pkglist_core::history;
+use pkglist_core::packages::PackageData;
+use pkglist_core::pacman::LocalDb;
+
+use crate::lookup;
+use crate::output::Palette;
+
+// Walks `Required By` upwards breadth-first, so every explicitly installed
+// ancestor is reported through its shortest dependency chain.
+fn explicit_chains<'a>(local: &'a LocalDb, root: &'a str) -> Vec<Vec<&'a str>> {
+    let mut parent: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([root]);
+    let mut roots = Vec::new();
+    parent.insert(root, root);
+
+    while let Some(name) = queue.pop_front() {
+        for dependent in local.required_by(name) {
+            if parent.contains_key(dependent) {
+                continue;
+            }
+            parent.insert(dependent, name);
+            if local.is_explicit(dependent) {
+                roots.push(dependent);
+            }
+            queue.push_back(dependent);
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|explicit| {
+            let mut chain = vec![explicit];
+            let mut current = explicit;
+            while current != root {
+                current = parent[current];
+                chain.push(current);
+            }
+            chain
+        })
+        .collect()
+}
+
+pub fn run(data: &PackageData, palette: &Palette, package: &str) -> io::Result<()> {
+    let local = LocalDb::load();
+    let found = lookup::find(&local, package)?;
+    let name = found.name();
+
+    let first = history::read_log_file()
+        .ok()
+        .and_then(|log| history::first_install(&log, name));
+    let record = data.record(name);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(
+        out,
+        "{} :: first installed {} :: last {} {}",
+        palette.status(record.status).paint(name),
+        palette
+            .date
+            .paint(first.as_deref().unwrap_or("before the log began")),
+        palette
+            .status(record.status)
+            .paint(palette.label(record.status)),
+        palette.date.paint(palette.date_text(&record))
+    )?;
+    if let Some(note) = found.note(package) {
+        writeln!(out, "{note}")?;
+    }
+
+    if local.is_explicit(name) {
+        writeln!(out, "{name} was explicitly installed")?;
+    }
+
+    let chains = explicit_chains(&local, name);
+    if chains.is_empty() {
+        if !local.is_explicit(name) {
+            writeln!(
+                out,
+                "{}",
+                palette
+                    .upg
+                    .paint("no explicitly installed package requires it (orphan)")
+            )?;
+        }
+        return out.flush();
+    }
+
+    writeln!(
+        out,
+        "required by {} explicitly installed package(s):",
+        chains.len()
+    )?;
+    for chain in chains {
+        let explicit = data.record(chain[0]);
+        let path: Vec<_> = chain
+            .iter()
+            .map(|name| palette.pkg.paint(*name).to_string())
+            .collect();
+        writeln!(
+            out,
+            "  {} {}",
+            path.join(palette.glyph(" → ", " needs ")),
+            palette
+                .date
+                .paint(format!("({})", palette.date_text(&explicit)))
+        )?;
+    }
+    out.flush()
+}